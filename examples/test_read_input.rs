@@ -66,17 +66,18 @@ fn run() -> Result<(), Box<dyn Error>> {
         in_port,
         "midir-read-input",
         move |stamp, midi_bytes, _| {
-            let (msg, _len) =
-                MidiMsg::from_midi_with_context(&midi_bytes, &mut ctx).expect("Not an error");
+            let msgs = MidiMsg::from_callback_bytes(&midi_bytes, &mut ctx).expect("Not an error");
 
-            // Print everything but spammy clock messages.
-            if let MidiMsg::SystemRealTime {
-                msg: SystemRealTimeMsg::TimingClock,
-            } = msg
-            {
-                // no-op
-            } else {
-                println!("{}: {:?}", stamp, msg);
+            for msg in msgs {
+                // Print everything but spammy clock messages.
+                if let MidiMsg::SystemRealTime {
+                    msg: SystemRealTimeMsg::TimingClock,
+                } = msg
+                {
+                    // no-op
+                } else {
+                    println!("{}: {:?}", stamp, msg);
+                }
             }
         },
         (),
@@ -1,4 +1,4 @@
-use crate::ReceiverContext;
+use crate::{Channel, GMNote, GMPercussionMap, ReceiverContext};
 
 use super::parse_error::*;
 use super::util::*;
@@ -50,6 +50,24 @@ pub enum ChannelVoiceMsg {
 }
 
 impl ChannelVoiceMsg {
+    /// A [`ProgramChange`](Self::ProgramChange) for a device targeting General MIDI, which only defines
+    /// programs 0-127 (see [`GMSoundSet`](crate::GMSoundSet)).
+    ///
+    /// Returns [`ParseError::ByteOverflow`] if `program` is not a GM program.
+    pub fn program_change_gm_checked(program: u8) -> Result<Self, ParseError> {
+        Ok(Self::ProgramChange {
+            program: u8_from_u7(program)?,
+        })
+    }
+
+    /// Like [`ChannelVoiceMsg::program_change_gm_checked`], but maps programs outside of the GM range to the
+    /// nearest GM program, i.e. 127.
+    pub fn program_change_gm_clamped(program: u8) -> Self {
+        Self::ProgramChange {
+            program: to_u7(program),
+        }
+    }
+
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         match self {
             ChannelVoiceMsg::NoteOff { .. } => v.push(0x80),
@@ -139,6 +157,39 @@ impl ChannelVoiceMsg {
         }
     }
 
+    /// What the note of this message sounds like on a General MIDI device, when sent on `channel`: a
+    /// [`GMPercussionMap`](crate::GMPercussionMap) sound on the percussion channel (see [`Channel::is_gm_drum`]),
+    /// or a pitch otherwise.
+    ///
+    /// Returns `None` for messages without a note, and for notes on the percussion channel that General MIDI
+    /// defines no sound for.
+    pub fn gm_note(&self, channel: Channel) -> Option<GMNote> {
+        let note = match *self {
+            Self::NoteOn { note, .. }
+            | Self::NoteOff { note, .. }
+            | Self::HighResNoteOn { note, .. }
+            | Self::HighResNoteOff { note, .. }
+            | Self::PolyPressure { note, .. } => note,
+            _ => return None,
+        };
+        if channel.is_gm_drum() {
+            GMPercussionMap::from_note(note).map(GMNote::Percussion)
+        } else {
+            Some(GMNote::Melodic(note))
+        }
+    }
+
+    /// The bytes of this message without its status byte, and thus without its channel, as sent
+    /// when using running status.
+    ///
+    /// Out of necessity, includes a Channel message (with a channel 1 status byte) after the note message
+    /// for `HighResNoteOn/Off`.
+    pub fn to_midi_running(&self) -> Vec<u8> {
+        let mut r: Vec<u8> = vec![];
+        self.extend_midi_running(&mut r);
+        r
+    }
+
     /// Out of necessity, pushes a Channel message after the note message for `HighResNoteOn/Off`
     pub(crate) fn extend_midi_running(&self, v: &mut Vec<u8>) {
         match *self {
@@ -381,8 +432,21 @@ pub enum ControlChange {
         /// 0-16383
         value: u16,
     },
+    /// A control change using one of the control numbers 120-127, which are normally reserved for
+    /// channel mode messages. Only returned by [`ControlChange::from_midi_any`].
+    ///
+    /// Serialized as-is, for devices that use these control numbers as ordinary controls.
+    Undefined {
+        /// 120-127
+        control: u8,
+        /// 0-127
+        value: u8,
+    },
 
     /// 0-16383
+    ///
+    /// When deserializing, a Bank Select MSB that is not followed by an LSB results in a value with an LSB of 0.
+    /// The current bank of each channel is available from [`ReceiverContext::bank_select`](crate::ReceiverContext::bank_select).
     BankSelect(u16),
     /// 0-16383
     ModWheel(u16),
@@ -605,17 +669,20 @@ impl ControlChange {
     }
 
     pub fn to_simple(&self) -> Self {
-        Self::CC {
-            control: self.control(),
-            value: self.value(),
+        match self {
+            Self::Undefined { .. } => *self,
+            _ => Self::CC {
+                control: self.control_number(),
+                value: self.value(),
+            },
         }
     }
 
     pub fn to_simple_high_res(&self) -> Self {
         match self {
-            Self::CCHighRes { .. } => *self,
+            Self::CCHighRes { .. } | Self::Undefined { .. } => *self,
             _ => {
-                let cc = self.control();
+                let cc = self.control_number();
                 Self::CCHighRes {
                     control1: cc,
                     control2: cc + 32,
@@ -625,10 +692,21 @@ impl ControlChange {
         }
     }
 
+    /// The control number of the control change.
+    #[deprecated(note = "Use `ControlChange::control_number` instead")]
     pub fn control(&self) -> u8 {
+        self.control_number()
+    }
+
+    /// The control number of the control change, e.g. 7 for `Volume` or 64 for `Hold`, as sent by
+    /// [`ControlChange::to_midi_running`]. For messages that are sent as more than one control change,
+    /// this is the MSB controller, e.g. 99 (NRPN MSB) for a non-registered `Parameter` and 101 (RPN MSB)
+    /// for a registered one. Useful for filtering, e.g. dropping all CC 64 sustain events.
+    pub fn control_number(&self) -> u8 {
         match self {
             Self::CC { control, .. } => *control,
             Self::CCHighRes { control1, .. } => *control1,
+            Self::Undefined { control, .. } => *control,
             Self::BankSelect(_) => ControlNumber::BankSelect as u8,
             Self::ModWheel(_) => ControlNumber::ModWheel as u8,
             Self::Breath(_) => ControlNumber::Breath as u8,
@@ -701,6 +779,7 @@ impl ControlChange {
         match self {
             Self::CC { value, .. } => *value,
             Self::CCHighRes { value, .. } => (*value >> 7) as u8,
+            Self::Undefined { value, .. } => *value,
             Self::BankSelect(x)
             | Self::ModWheel(x)
             | Self::Breath(x)
@@ -775,6 +854,7 @@ impl ControlChange {
         match self {
             Self::CC { value, .. } => (*value as u16) << 7,
             Self::CCHighRes { value, .. } => *value,
+            Self::Undefined { value, .. } => (*value as u16) << 7,
             Self::BankSelect(x)
             | Self::ModWheel(x)
             | Self::Breath(x)
@@ -917,6 +997,10 @@ impl ControlChange {
                 control2,
                 value,
             } => ControlChange::undefined_high_res(v, control1, control2, value),
+            ControlChange::Undefined { control, value } => {
+                v.push(to_u7(control));
+                v.push(to_u7(value));
+            }
             ControlChange::Foot(x) => ControlChange::high_res_cc(v, 4, x),
             ControlChange::Portamento(x) => ControlChange::high_res_cc(v, 5, x),
             ControlChange::Volume(x) => ControlChange::high_res_cc(v, 7, x),
@@ -1136,6 +1220,37 @@ impl ControlChange {
         })
     }
 
+    /// Parse the two data bytes of a control change message (its control number and value), accepting any
+    /// control number from 0-127, and returning the control change along with the number of bytes read.
+    ///
+    /// Unlike when parsing a whole [`MidiMsg`](crate::MidiMsg), control numbers 120-127 are not treated as
+    /// channel mode messages, but are returned as [`ControlChange::Undefined`]. Other control numbers result in
+    /// a "simple" [`ControlChange::CC`]. This is useful when the bytes are known to be a control change, e.g. those
+    /// sent by non-standard devices or embedded in a system exclusive message.
+    pub fn from_midi_any(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        if m.len() < 2 {
+            return Err(crate::ParseError::UnexpectedEnd);
+        }
+        let control = u8_from_u7(m[0])?;
+        let value = u8_from_u7(m[1])?;
+        let cc = if control > 119 {
+            ControlChange::Undefined { control, value }
+        } else {
+            ControlChange::CC { control, value }
+        };
+        Ok((cc, 2))
+    }
+
+    /// Parse the two data bytes of a control change message (its control number and value), e.g. when the status
+    /// byte has already been stripped, returning the control change along with the number of bytes read.
+    ///
+    /// As when parsing a [`MidiMsg`](crate::MidiMsg) with a default [`ReceiverContext`], the result is a "simple"
+    /// [`ControlChange::CC`], and control numbers 120-127 are rejected since they are channel mode messages. See
+    /// [`ControlChange::from_midi_any`] to accept them.
+    pub fn parse(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        Ok((Self::from_midi(m, &ReceiverContext::new())?, 2))
+    }
+
     fn maybe_extend(&self, other: &Self) -> Result<Self, ()> {
         match (self, other) {
             (Self::BankSelect(msb), Self::CC { control, value })
@@ -1393,6 +1508,33 @@ pub enum Parameter {
 }
 
 impl Parameter {
+    /// The value carried by an `...Entry` variant, or `None` if this is not an entry.
+    ///
+    /// `PitchBendSensitivityEntry(semitones, cents)` is returned as a total number of cents.
+    pub fn entry_value(&self) -> Option<i32> {
+        match *self {
+            Self::PitchBendSensitivityEntry(semitones, cents) => {
+                Some(semitones as i32 * 100 + cents as i32)
+            }
+            Self::FineTuningEntry(v) => Some(v as i32),
+            Self::CoarseTuningEntry(v) => Some(v as i32),
+            Self::TuningProgramSelectEntry(v)
+            | Self::TuningBankSelectEntry(v)
+            | Self::PolyphonicExpressionEntry(v) => Some(v as i32),
+            Self::ModulationDepthRangeEntry(v)
+            | Self::AzimuthAngle3DSoundEntry(v)
+            | Self::ElevationAngle3DSoundEntry(v)
+            | Self::Gain3DSoundEntry(v)
+            | Self::DistanceRatio3DSoundEntry(v)
+            | Self::MaxiumumDistance3DSoundEntry(v)
+            | Self::GainAtMaxiumumDistance3DSoundEntry(v)
+            | Self::ReferenceDistanceRatio3DSoundEntry(v)
+            | Self::PanSpreadAngle3DSoundEntry(v)
+            | Self::RollAngle3DSoundEntry(v) => Some(v as i32),
+            _ => None,
+        }
+    }
+
     fn extend_midi_running(&self, v: &mut Vec<u8>) {
         match self {
             Self::Null => {
@@ -1601,7 +1743,7 @@ impl Parameter {
         }
     }
 
-    fn maybe_extend_cc(msb: u8, lsb: u8) -> Result<Self, ()> {
+    pub(crate) fn maybe_extend_cc(msb: u8, lsb: u8) -> Result<Self, ()> {
         match (msb, lsb) {
             (0x7F, 0x7F) => Ok(Self::Null),
             (0, 0) => Ok(Self::PitchBendSensitivity),
@@ -1631,7 +1773,7 @@ impl Parameter {
                 lsb.unwrap_or(0),
             )),
             Self::PitchBendSensitivityEntry(v1, v2) => Ok(Self::PitchBendSensitivityEntry(
-                msb.map_or(*v1, |v| v as u8),
+                msb.map_or(*v1, |v| (v >> 7) as u8),
                 lsb.unwrap_or(*v2),
             )),
             Self::FineTuning => Ok(Self::FineTuningEntry(i14_from_u7s(
@@ -1639,23 +1781,27 @@ impl Parameter {
                 lsb.unwrap_or(0),
             ))),
             Self::FineTuningEntry(v) => Ok(Self::FineTuningEntry(i14_from_u7s(
-                msb.map_or(i_to_u14(*v)[0], |v| v as u8),
+                msb.map_or(i_to_u14(*v)[0], |v| (v >> 7) as u8),
                 lsb.unwrap_or(i_to_u14(*v)[1]),
             ))),
-            Self::CoarseTuning => Ok(Self::CoarseTuningEntry(msb.map_or(0, |v| u7_to_i(v as u8)))),
+            Self::CoarseTuning => Ok(Self::CoarseTuningEntry(
+                msb.map_or(0, |v| u7_to_i((v >> 7) as u8)),
+            )),
             Self::CoarseTuningEntry(v) => Ok(Self::CoarseTuningEntry(
-                msb.map_or(*v, |v| u7_to_i(v as u8)),
+                msb.map_or(*v, |v| u7_to_i((v >> 7) as u8)),
+            )),
+            Self::TuningProgramSelect => Ok(Self::TuningProgramSelectEntry(
+                msb.map_or(0, |v| (v >> 7) as u8),
+            )),
+            Self::TuningProgramSelectEntry(v) => Ok(Self::TuningProgramSelectEntry(
+                msb.map_or(*v, |v| (v >> 7) as u8),
+            )),
+            Self::TuningBankSelect => Ok(Self::TuningBankSelectEntry(
+                msb.map_or(0, |v| (v >> 7) as u8),
+            )),
+            Self::TuningBankSelectEntry(v) => Ok(Self::TuningBankSelectEntry(
+                msb.map_or(*v, |v| (v >> 7) as u8),
             )),
-            Self::TuningProgramSelect => {
-                Ok(Self::TuningProgramSelectEntry(msb.map_or(0, |v| v as u8)))
-            }
-            Self::TuningProgramSelectEntry(v) => {
-                Ok(Self::TuningProgramSelectEntry(msb.map_or(*v, |v| v as u8)))
-            }
-            Self::TuningBankSelect => Ok(Self::TuningBankSelectEntry(msb.map_or(0, |v| v as u8))),
-            Self::TuningBankSelectEntry(v) => {
-                Ok(Self::TuningBankSelectEntry(msb.map_or(*v, |v| v as u8)))
-            }
             Self::ModulationDepthRange => Ok(Self::ModulationDepthRangeEntry(replace_u14_lsb(
                 msb.unwrap_or(0),
                 lsb.unwrap_or(0),
@@ -1663,12 +1809,12 @@ impl Parameter {
             Self::ModulationDepthRangeEntry(v) => Ok(Self::ModulationDepthRangeEntry(
                 replace_u14_lsb(msb.unwrap_or(*v), lsb.unwrap_or((*v as u8) & 0b01111111)),
             )),
-            Self::PolyphonicExpression => {
-                Ok(Self::PolyphonicExpressionEntry(msb.map_or(0, |v| v as u8)))
-            }
-            Self::PolyphonicExpressionEntry(v) => {
-                Ok(Self::PolyphonicExpressionEntry(msb.map_or(*v, |v| v as u8)))
-            }
+            Self::PolyphonicExpression => Ok(Self::PolyphonicExpressionEntry(
+                msb.map_or(0, |v| (v >> 7) as u8),
+            )),
+            Self::PolyphonicExpressionEntry(v) => Ok(Self::PolyphonicExpressionEntry(
+                msb.map_or(*v, |v| (v >> 7) as u8),
+            )),
             Self::AzimuthAngle3DSound => Ok(Self::AzimuthAngle3DSoundEntry(replace_u14_lsb(
                 msb.unwrap_or(0),
                 lsb.unwrap_or(0),
@@ -2002,7 +2148,7 @@ mod tests {
                 control: 20,
                 value: 40
             }
-            .control(),
+            .control_number(),
             20
         );
         assert_eq!(
@@ -2022,9 +2168,54 @@ mod tests {
             40 << 7
         );
 
-        assert_eq!(ControlChange::Breath(40 << 7).control(), 2);
+        assert_eq!(ControlChange::Breath(40 << 7).control_number(), 2);
         assert_eq!(ControlChange::Breath(40 << 7).value(), 40);
         assert_eq!(ControlChange::Breath(40 << 7).value_high_res(), 40 << 7);
+
+        // The control number is the MSB control that is serialized
+        for control in [
+            ControlChange::BankSelect(3),
+            ControlChange::Volume(100 << 7),
+            ControlChange::Hold(127),
+            ControlChange::ReverbSendLevel(10),
+            ControlChange::Parameter(Parameter::Unregistered(1000)),
+            ControlChange::Parameter(Parameter::PitchBendSensitivity),
+            ControlChange::DataEntry2(1, 2),
+            ControlChange::CCHighRes {
+                control1: 20,
+                control2: 52,
+                value: 1000,
+            },
+        ] {
+            let n = control.control_number();
+            assert!(control.to_midi_running().chunks(2).any(|cc| cc[0] == n));
+        }
+        assert_eq!(ControlChange::Hold(127).control_number(), 64);
+        assert_eq!(
+            ControlChange::Parameter(Parameter::Unregistered(1000)).control_number(),
+            99
+        );
+    }
+
+    #[test]
+    fn test_parameter_entry_value() {
+        assert_eq!(Parameter::FineTuningEntry(-30).entry_value(), Some(-30));
+        assert_eq!(Parameter::CoarseTuningEntry(-64).entry_value(), Some(-64));
+        assert_eq!(
+            Parameter::TuningProgramSelectEntry(5).entry_value(),
+            Some(5)
+        );
+        assert_eq!(
+            Parameter::ModulationDepthRangeEntry(16383).entry_value(),
+            Some(16383)
+        );
+        assert_eq!(
+            Parameter::PitchBendSensitivityEntry(2, 50).entry_value(),
+            Some(250)
+        );
+        assert_eq!(Parameter::FineTuning.entry_value(), None);
+        assert_eq!(Parameter::Unregistered(1000).entry_value(), None);
+        assert_eq!(Parameter::Null.entry_value(), None);
     }
 
     #[test]
@@ -2091,4 +2282,137 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_program_change_gm() {
+        assert_eq!(
+            ChannelVoiceMsg::program_change_gm_checked(GMSoundSet::Vibraphone as u8),
+            Ok(ChannelVoiceMsg::ProgramChange { program: 11 })
+        );
+        assert_eq!(
+            ChannelVoiceMsg::program_change_gm_checked(128),
+            Err(ParseError::ByteOverflow)
+        );
+        assert_eq!(
+            ChannelVoiceMsg::program_change_gm_clamped(200),
+            ChannelVoiceMsg::ProgramChange { program: 127 }
+        );
+        assert_eq!(
+            ChannelVoiceMsg::program_change_gm_clamped(42),
+            ChannelVoiceMsg::ProgramChange { program: 42 }
+        );
+    }
+
+    #[test]
+    fn test_channel_voice_to_midi_running() {
+        assert_eq!(
+            ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100
+            }
+            .to_midi_running(),
+            vec![60, 100]
+        );
+        assert_eq!(
+            ChannelVoiceMsg::PitchBend { bend: 0x2001 }.to_midi_running(),
+            vec![0x01, 0x40]
+        );
+    }
+
+    #[test]
+    fn test_gm_note() {
+        let note_on = ChannelVoiceMsg::NoteOn {
+            note: GMPercussionMap::AcousticSnare as u8,
+            velocity: 100,
+        };
+        assert!(Channel::Ch10.is_gm_drum());
+        assert!(!Channel::Ch9.is_gm_drum());
+        assert_eq!(
+            note_on.gm_note(Channel::Ch10),
+            Some(GMNote::Percussion(GMPercussionMap::AcousticSnare))
+        );
+        assert_eq!(note_on.gm_note(Channel::Ch1), Some(GMNote::Melodic(38)));
+        assert_eq!(
+            ChannelVoiceMsg::NoteOff {
+                note: 20,
+                velocity: 0
+            }
+            .gm_note(Channel::Ch10),
+            None
+        );
+        assert_eq!(
+            ChannelVoiceMsg::ProgramChange { program: 1 }.gm_note(Channel::Ch1),
+            None
+        );
+    }
+
+    #[test]
+    fn test_cc_parse() {
+        assert_eq!(
+            ControlChange::parse(&[7, 0x64, 0x00]),
+            Ok((
+                ControlChange::CC {
+                    control: 7,
+                    value: 0x64
+                },
+                2
+            ))
+        );
+        assert_eq!(
+            ControlChange::parse(&[123, 0x00]),
+            Err(ParseError::Invalid(
+                "Tried to parse a control change message, but it looks like a channel mode message"
+            ))
+        );
+        assert_eq!(
+            ControlChange::parse(&[7, 0x80]),
+            Err(ParseError::ByteOverflow)
+        );
+        assert_eq!(ControlChange::parse(&[7]), Err(ParseError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_cc_from_midi_any() {
+        assert_eq!(
+            ControlChange::from_midi(&[118, 0x40], &ReceiverContext::new()),
+            Ok(ControlChange::CC {
+                control: 118,
+                value: 0x40
+            })
+        );
+        assert!(ControlChange::from_midi(&[125, 0x40], &ReceiverContext::new()).is_err());
+
+        let (undefined, len) = ControlChange::from_midi_any(&[125, 0x40]).unwrap();
+        assert_eq!(
+            undefined,
+            ControlChange::Undefined {
+                control: 125,
+                value: 0x40
+            }
+        );
+        assert_eq!(len, 2);
+        assert_eq!(undefined.control_number(), 125);
+        assert_eq!(undefined.value(), 0x40);
+        assert_eq!(undefined.to_midi_running(), vec![125, 0x40]);
+        assert_eq!(undefined.to_complex(), undefined);
+        assert_eq!(undefined.to_simple(), undefined);
+        assert_eq!(
+            ControlChange::from_midi_any(&[118, 0x40]),
+            Ok((
+                ControlChange::CC {
+                    control: 118,
+                    value: 0x40
+                },
+                2
+            ))
+        );
+        assert_eq!(
+            ControlChange::from_midi_any(&[0x80, 0x40]),
+            Err(ParseError::ByteOverflow)
+        );
+        assert_eq!(
+            ControlChange::from_midi_any(&[125]),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
 }
@@ -0,0 +1,136 @@
+use super::{MidiMsg, ParseError};
+use alloc::vec::Vec;
+
+/// The number of bytes used by the length prefix of a framed message.
+const FRAME_HEADER_LEN: usize = 4;
+
+/// Serialize a [`MidiMsg`], prefixed with its length, for transport over byte streams
+/// (e.g. TCP or WebSockets) that do not otherwise preserve message boundaries.
+///
+/// The length is written as a big-endian `u32`, followed by the bytes of the message.
+/// Unlike raw MIDI, every frame is self-contained: `RunningChannelVoice` and `RunningChannelMode`
+/// messages are written with their status byte. Use [`decode_framed`] to read the frames back.
+///
+/// ```
+/// use midi_msg::*;
+///
+/// let msg = MidiMsg::ChannelVoice {
+///     channel: Channel::Ch1,
+///     msg: ChannelVoiceMsg::NoteOn { note: 60, velocity: 100 },
+/// };
+/// let framed = encode_framed(&msg);
+/// assert_eq!(framed, vec![0, 0, 0, 3, 0x90, 60, 100]);
+/// assert_eq!(decode_framed(&framed), Ok((msg, 7)));
+/// ```
+pub fn encode_framed(msg: &MidiMsg) -> Vec<u8> {
+    let mut v = alloc::vec![0; FRAME_HEADER_LEN];
+    match *msg {
+        MidiMsg::RunningChannelVoice { channel, msg } => {
+            MidiMsg::ChannelVoice { channel, msg }.extend_midi(&mut v)
+        }
+        MidiMsg::RunningChannelMode { channel, msg } => {
+            MidiMsg::ChannelMode { channel, msg }.extend_midi(&mut v)
+        }
+        _ => msg.extend_midi(&mut v),
+    }
+    let len = (v.len() - FRAME_HEADER_LEN) as u32;
+    v[..FRAME_HEADER_LEN].copy_from_slice(&len.to_be_bytes());
+    v
+}
+
+/// Read a [`MidiMsg`] from the start of a series of frames written by [`encode_framed`].
+///
+/// Ok results return the `MidiMsg` and the number of bytes consumed from the input, including
+/// the length prefix. [`ParseError::UnexpectedEnd`] is returned if the input does not contain a
+/// whole frame, e.g. when more bytes have yet to be received from the stream.
+pub fn decode_framed(m: &[u8]) -> Result<(MidiMsg, usize), ParseError> {
+    if m.len() < FRAME_HEADER_LEN {
+        return Err(ParseError::UnexpectedEnd);
+    }
+    let mut header = [0; FRAME_HEADER_LEN];
+    header.copy_from_slice(&m[..FRAME_HEADER_LEN]);
+    let len = u32::from_be_bytes(header) as usize;
+    let end = FRAME_HEADER_LEN
+        .checked_add(len)
+        .ok_or(ParseError::UnexpectedEnd)?;
+    if m.len() < end {
+        return Err(ParseError::UnexpectedEnd);
+    }
+    let (msg, msg_len) = MidiMsg::from_midi(&m[FRAME_HEADER_LEN..end])?;
+    if msg_len != len {
+        return Err(ParseError::Invalid(
+            "The length of a framed message does not match its contents",
+        ));
+    }
+    Ok((msg, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_framed_round_trip() {
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch3,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        let running_note_off = MidiMsg::RunningChannelVoice {
+            channel: Channel::Ch3,
+            msg: ChannelVoiceMsg::NoteOff {
+                note: 60,
+                velocity: 0,
+            },
+        };
+        let start = MidiMsg::SystemRealTime {
+            msg: SystemRealTimeMsg::Start,
+        };
+        #[cfg(feature = "sysex")]
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::NonCommercial {
+                data: vec![0x01, 0x02, 0x03, 0x04],
+            },
+        };
+
+        let mut stream = vec![];
+        stream.extend(encode_framed(&note_on));
+        stream.extend(encode_framed(&running_note_off));
+        stream.extend(encode_framed(&start));
+        #[cfg(feature = "sysex")]
+        stream.extend(encode_framed(&sysex));
+
+        let mut msgs = vec![];
+        let mut offset = 0;
+        while offset < stream.len() {
+            let (msg, len) = decode_framed(&stream[offset..]).expect("Not an error");
+            msgs.push(msg);
+            offset += len;
+        }
+
+        // Running status messages are framed with their status byte
+        let mut expected = vec![
+            note_on,
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch3,
+                msg: ChannelVoiceMsg::NoteOff {
+                    note: 60,
+                    velocity: 0,
+                },
+            },
+            start,
+        ];
+        #[cfg(feature = "sysex")]
+        expected.push(sysex);
+        assert_eq!(msgs, expected);
+
+        // An incomplete frame
+        assert_eq!(decode_framed(&stream[..5]), Err(ParseError::UnexpectedEnd));
+        assert_eq!(decode_framed(&[0, 0]), Err(ParseError::UnexpectedEnd));
+        // A frame whose length does not match its message
+        assert!(decode_framed(&[0, 0, 0, 4, 0x90, 60, 100, 0x40]).is_err());
+    }
+}
@@ -1,4 +1,7 @@
-use super::{MidiMsg, TimeCode};
+use super::{
+    util::replace_u14_lsb, Channel, ChannelVoiceMsg, ControlChange, MidiMsg, Parameter, TimeCode,
+};
+use alloc::vec::Vec;
 
 /// Passed to [`MidiMsg::from_midi_with_context`](crate::MidiMsg::from_midi_with_context) to allow
 /// for the capture and use of captured context while reading from a MIDI stream.
@@ -16,6 +19,13 @@ pub struct ReceiverContext {
     pub(crate) time_code: TimeCode,
     pub(crate) is_smf_sysex: bool,
     pub(crate) parsing_smf: bool,
+    pub(crate) bank_select: [Option<u16>; 16],
+    pub(crate) tune_requested: bool,
+    /// The (registered, number) of the parameter selected on each channel
+    pub(crate) parameter_number: [Option<(bool, u16)>; 16],
+    pub(crate) parameter_value: [Option<u16>; 16],
+    /// The system exclusive message received so far through [`ReceiverContext::push_sysex_packet`]
+    pub(crate) sysex_packets: Option<Vec<u8>>,
     /// If true, CC messages will be treated as complex CC messages, with their semantics taken from the Midi spec. Otherwise, they will be treated as simple CC messages - i.e. [`ControlChange::CC`](crate::ControlChange::CC).
     pub complex_cc: bool,
 }
@@ -31,6 +41,151 @@ impl ReceiverContext {
         self
     }
 
+    /// The most recent Bank Select value received on the given channel, if any, so that it can
+    /// be associated with a following [`ChannelVoiceMsg::ProgramChange`].
+    ///
+    /// A Bank Select MSB that is sent on its own is treated as having an LSB of 0. This is
+    /// tracked for both simple and complex CC messages.
+    pub fn bank_select(&self, channel: Channel) -> Option<u16> {
+        self.bank_select[channel as usize]
+    }
+
+    /// Whether a [`SystemCommonMsg::TuneRequest`](crate::SystemCommonMsg::TuneRequest) has been received,
+    /// e.g. so that a receiver can recall the tuning of its oscillators.
+    ///
+    /// This stays set until [`ReceiverContext::clear_tune_request`] is called.
+    pub fn tune_requested(&self) -> bool {
+        self.tune_requested
+    }
+
+    /// Reset the flag returned by [`ReceiverContext::tune_requested`], once the tune request has been acted upon.
+    pub fn clear_tune_request(&mut self) {
+        self.tune_requested = false;
+    }
+
+    /// The parameter most recently selected on the given channel through a Registered or Non-Registered
+    /// Parameter Number, if any.
+    ///
+    /// Returns `None` if an unknown Registered Parameter is selected.
+    /// Entry parameters are returned without their value: see [`ReceiverContext::parameter_value`].
+    pub fn selected_parameter(&self, channel: Channel) -> Option<Parameter> {
+        match self.parameter_number[channel as usize]? {
+            (false, number) => Some(Parameter::Unregistered(number)),
+            (true, number) => {
+                Parameter::maybe_extend_cc((number >> 7) as u8, number as u8 & 0x7F).ok()
+            }
+        }
+    }
+
+    /// The 14-bit value of the parameter selected on the given channel, if it is known.
+    ///
+    /// The value is set by [`ControlChange::DataEntry`] (or an entry [`Parameter`]), and then adjusted
+    /// by [`ControlChange::DataIncrement`] and [`ControlChange::DataDecrement`] as described by RP-018
+    /// (Response to Data Increment/Decrement Controllers): the value of those messages is ignored, and
+    /// they step the LSB, or the MSB for parameters that only use an MSB (Coarse Tuning, Tuning Program
+    /// Select and Tuning Bank Select). The value is unknown until it is set after a parameter is selected.
+    /// This is tracked for both simple and complex CC messages.
+    pub fn parameter_value(&self, channel: Channel) -> Option<u16> {
+        self.parameter_value[channel as usize]
+    }
+
+    /// Accumulate a system exclusive message that has been split into multiple packets, as in a Standard
+    /// MIDI File: the first packet starts with `0xF0`, and each continuation packet starts with an `0xF7`
+    /// that is not part of the message. Once a packet ends with the `0xF7` End of System Exclusive flag,
+    /// the reassembled message, from its `0xF0` up to and including that flag, is returned so that it can be
+    /// parsed, e.g. with [`MidiMsg::from_midi`](crate::MidiMsg::from_midi).
+    ///
+    /// A packet starting with `0xF0` discards any incomplete message, and continuation packets are ignored
+    /// when there is no incomplete message.
+    pub fn push_sysex_packet(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        match packet.first() {
+            Some(0xF0) => self.sysex_packets = Some(packet.to_vec()),
+            Some(0xF7) => self.sysex_packets.as_mut()?.extend_from_slice(&packet[1..]),
+            _ => return None,
+        }
+        // The leading 0xF0 or 0xF7 of the packet is not an End of System Exclusive flag
+        if packet.len() > 1 && packet.last() == Some(&0xF7) {
+            self.sysex_packets.take()
+        } else {
+            None
+        }
+    }
+
+    /// Whether a system exclusive message passed to [`ReceiverContext::push_sysex_packet`] is waiting for
+    /// more packets.
+    pub fn has_pending_sysex(&self) -> bool {
+        self.sysex_packets.is_some()
+    }
+
+    pub(crate) fn update_parameter(&mut self, msg: &MidiMsg) {
+        if let MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange { control },
+        } = msg
+        {
+            if !matches!(control.control_number(), 6 | 38 | 96..=101) {
+                return;
+            }
+            let number = &mut self.parameter_number[*channel as usize];
+            let value = &mut self.parameter_value[*channel as usize];
+            for pair in control.to_midi_running().chunks(2) {
+                let (control, data) = (pair[0], pair[1]);
+                match control {
+                    98..=101 => {
+                        let registered = control >= 100;
+                        let prev = match *number {
+                            Some((r, n)) if r == registered => n,
+                            _ => 0,
+                        };
+                        let n = if control & 1 == 1 {
+                            (prev & 0x7F) + ((data as u16) << 7)
+                        } else {
+                            replace_u14_lsb(prev, data)
+                        };
+                        *number = Some((registered, n));
+                        *value = None;
+                    }
+                    _ if *number == Some((true, 0x3FFF)) || number.is_none() => (),
+                    6 => *value = Some((data as u16) << 7),
+                    38 => *value = Some(replace_u14_lsb(value.unwrap_or(0), data)),
+                    96 | 97 => {
+                        // Coarse Tuning, Tuning Program Select and Tuning Bank Select only use an MSB
+                        let step = match *number {
+                            Some((true, 2..=4)) => 1 << 7,
+                            _ => 1,
+                        };
+                        *value = value.map(|v| {
+                            if control == 96 {
+                                (v + step).min(0x4000 - step)
+                            } else {
+                                v.saturating_sub(step)
+                            }
+                        });
+                    }
+                    _ => (),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn update_bank_select(&mut self, msg: &MidiMsg) {
+        if let MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange { control },
+        } = msg
+        {
+            let bank = &mut self.bank_select[*channel as usize];
+            match *control {
+                ControlChange::BankSelect(value) => *bank = Some(value),
+                ControlChange::CC { control: 0, value } => *bank = Some((value as u16) << 7),
+                ControlChange::CC { control: 32, value } => {
+                    *bank = Some(replace_u14_lsb(bank.unwrap_or(0), value))
+                }
+                _ => (),
+            }
+        }
+    }
+
     pub(crate) fn parsing_smf(mut self) -> Self {
         self.parsing_smf = true;
         self
@@ -0,0 +1,216 @@
+use super::{MidiMsg, ParseError};
+use alloc::vec::Vec;
+
+/// Serializes a stream of [`MidiMsg`]s, keeping track of the "running status" of the
+/// stream so that repeated status bytes can be omitted.
+///
+/// Unlike [`MidiMsg::to_midi`], which always produces a status byte for channel messages,
+/// consecutive channel messages with the same status will only have it written once. This
+/// makes `MidiEncoder` suited to writing to fixed-size output buffers, e.g. the ring buffer
+/// of a MIDI output port, across many calls.
+///
+/// ```
+/// use midi_msg::*;
+///
+/// let mut encoder = MidiEncoder::new();
+/// let mut buf = [0; 8];
+/// let note_on = |note| MidiMsg::ChannelVoice {
+///     channel: Channel::Ch1,
+///     msg: ChannelVoiceMsg::NoteOn { note, velocity: 100 },
+/// };
+///
+/// assert_eq!(encoder.encode(&note_on(60), &mut buf), Ok(3));
+/// assert_eq!(&buf[..3], &[0x90, 60, 100]);
+/// // The status byte is omitted from the second message
+/// assert_eq!(encoder.encode(&note_on(64), &mut buf), Ok(2));
+/// assert_eq!(&buf[..2], &[64, 100]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MidiEncoder {
+    running_status: Option<u8>,
+}
+
+impl MidiEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Forget the running status, so that the next channel message will be written with
+    /// its status byte.
+    pub fn reset(&mut self) {
+        self.running_status = None;
+    }
+
+    /// Serialize `msg` into the start of `buf`, omitting its status byte if it is the same
+    /// as the current running status.
+    ///
+    /// `RunningChannelVoice` and `RunningChannelMode` messages are treated like their
+    /// `ChannelVoice` and `ChannelMode` counterparts: their status byte is written if the
+    /// running status does not match.
+    ///
+    /// Ok results return the number of bytes written to `buf`. If `buf` is too small to hold
+    /// the message, [`ParseError::BufferTooSmall`] is returned, nothing is written, and the
+    /// running status is left untouched.
+    pub fn encode(&mut self, msg: &MidiMsg, buf: &mut [u8]) -> Result<usize, ParseError> {
+        let (bytes, skip, running_status) = self.prepare(msg);
+        let bytes = &bytes[skip..];
+        if buf.len() < bytes.len() {
+            return Err(ParseError::BufferTooSmall);
+        }
+        buf[..bytes.len()].copy_from_slice(bytes);
+        self.running_status = running_status;
+        Ok(bytes.len())
+    }
+
+    /// Append the serialization of `msg` to `v`, omitting its status byte if it is the same
+    /// as the current running status. Like [`MidiEncoder::encode`], but for growable output.
+    pub fn extend_midi(&mut self, msg: &MidiMsg, v: &mut Vec<u8>) {
+        let (bytes, skip, running_status) = self.prepare(msg);
+        v.extend_from_slice(&bytes[skip..]);
+        self.running_status = running_status;
+    }
+
+    /// The full serialization of `msg`, the number of leading bytes to skip, and the running
+    /// status after it has been written.
+    fn prepare(&self, msg: &MidiMsg) -> (Vec<u8>, usize, Option<u8>) {
+        let bytes = match *msg {
+            MidiMsg::RunningChannelVoice { channel, msg } => {
+                MidiMsg::ChannelVoice { channel, msg }.to_midi()
+            }
+            MidiMsg::RunningChannelMode { channel, msg } => {
+                MidiMsg::ChannelMode { channel, msg }.to_midi()
+            }
+            _ => msg.to_midi(),
+        };
+        let status = match bytes.first() {
+            Some(b) => *b,
+            None => return (bytes, 0, self.running_status),
+        };
+
+        match status >> 4 {
+            0x8..=0xE => {
+                let skip = if self.running_status == Some(status) {
+                    1
+                } else {
+                    0
+                };
+                // Some messages, e.g. `HighResNoteOn`, contain more than one status byte
+                let last_status = bytes.iter().rev().find(|b| *b & 0x80 != 0).copied();
+                (bytes, skip, last_status)
+            }
+            // System real-time messages do not affect the running status
+            0xF if status >= 0xF8 => (bytes, 0, self.running_status),
+            // All other system messages, including system exclusive, cancel it
+            _ => (bytes, 0, None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_encoder_running_status() {
+        let mut encoder = MidiEncoder::new();
+        let mut buf = [0; 16];
+        let mut out = vec![];
+        let msgs = [
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 60,
+                    velocity: 100,
+                },
+            },
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 64,
+                    velocity: 100,
+                },
+            },
+            // Real-time messages don't interrupt the running status
+            MidiMsg::SystemRealTime {
+                msg: SystemRealTimeMsg::TimingClock,
+            },
+            MidiMsg::RunningChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 67,
+                    velocity: 100,
+                },
+            },
+            // A new status
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch3,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 67,
+                    velocity: 100,
+                },
+            },
+            // System common messages cancel the running status
+            MidiMsg::SystemCommon {
+                msg: SystemCommonMsg::TuneRequest,
+            },
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch3,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 67,
+                    velocity: 0,
+                },
+            },
+        ];
+        for msg in msgs.iter() {
+            let len = encoder.encode(msg, &mut buf).unwrap();
+            out.extend_from_slice(&buf[..len]);
+        }
+
+        assert_eq!(
+            out,
+            vec![0x91, 60, 100, 64, 100, 0xF8, 67, 100, 0x92, 67, 100, 0xF6, 0x92, 67, 0]
+        );
+
+        // The output can be read back with a ReceiverContext
+        let mut ctx = ReceiverContext::new();
+        let mut offset = 0;
+        let mut read = vec![];
+        while offset < out.len() {
+            let (msg, len) = MidiMsg::from_midi_with_context(&out[offset..], &mut ctx).unwrap();
+            read.push(msg);
+            offset += len;
+        }
+        assert_eq!(read.len(), msgs.len());
+        assert_eq!(
+            read[3],
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 67,
+                    velocity: 100,
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_encoder_buffer_too_small() {
+        let mut encoder = MidiEncoder::new();
+        let msg = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        let mut buf = [0; 2];
+        assert_eq!(
+            encoder.encode(&msg, &mut buf),
+            Err(ParseError::BufferTooSmall)
+        );
+        // The status byte is still written after a failure
+        let mut buf = [0; 3];
+        assert_eq!(encoder.encode(&msg, &mut buf), Ok(3));
+    }
+}
@@ -3,6 +3,7 @@ use alloc::format;
 use alloc::string::{String, ToString};
 use alloc::vec;
 use alloc::vec::Vec;
+use core::mem;
 use core::ops;
 use core::str;
 
@@ -13,8 +14,8 @@ use micromath::F32Ext;
 use std::error;
 
 use super::{
-    util::*, Channel, HighResTimeCode, MidiMsg, ParseError, ReceiverContext, SystemExclusiveMsg,
-    TimeCodeType,
+    util::*, Channel, ChannelModeMsg, ChannelVoiceMsg, HighResTimeCode, MidiMsg, ParseError,
+    ReceiverContext, SystemExclusiveMsg, TimeCodeType,
 };
 
 // Standard Midi File 1.0 (SMF): RP-001 support
@@ -69,6 +70,82 @@ pub struct MidiFile {
     pub tracks: Vec<Track>,
 }
 
+/// The encoding of the text of meta events such as [`Meta::Text`] and [`Meta::TrackName`]. The Standard Midi File
+/// specification doesn't say which encoding is used, and while most new software uses UTF-8, many older files use
+/// Latin-1 or Windows-1252.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextEncoding {
+    /// UTF-8. Invalid sequences are decoded as U+FFFD, the replacement character.
+    #[default]
+    Utf8,
+    /// ISO-8859-1. Characters that can't be encoded are encoded as `?`.
+    Latin1,
+    /// Windows-1252, the superset of Latin-1 that replaces its C1 control characters (0x80-0x9F) with printable
+    /// characters such as `€` and `™`. The five bytes it leaves undefined are decoded as the corresponding C1
+    /// control characters. Characters that can't be encoded are encoded as `?`.
+    Windows1252,
+}
+
+/// The characters of Windows-1252 bytes 0x80-0x9F.
+const WINDOWS_1252_C1: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+impl TextEncoding {
+    /// Encode a string as bytes.
+    pub fn encode(&self, s: &str) -> Vec<u8> {
+        match self {
+            Self::Utf8 => s.as_bytes().to_vec(),
+            Self::Latin1 => s
+                .chars()
+                .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+                .collect(),
+            Self::Windows1252 => s
+                .chars()
+                .map(|c| match WINDOWS_1252_C1.iter().position(|x| *x == c) {
+                    Some(i) => 0x80 + i as u8,
+                    None if (c as u32) < 0x80 || (0xA0..0x100).contains(&(c as u32)) => c as u8,
+                    None => b'?',
+                })
+                .collect(),
+        }
+    }
+
+    /// Whether `bytes` are valid text in this encoding, i.e. whether they can be decoded without loss. Only UTF-8
+    /// can be invalid.
+    pub fn is_valid(&self, bytes: &[u8]) -> bool {
+        match self {
+            Self::Utf8 => str::from_utf8(bytes).is_ok(),
+            Self::Latin1 | Self::Windows1252 => true,
+        }
+    }
+
+    /// Decode bytes as a string.
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        match self {
+            Self::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            Self::Latin1 => bytes.iter().map(|b| *b as char).collect(),
+            Self::Windows1252 => bytes
+                .iter()
+                .map(|b| match b {
+                    0x80..=0x9F => WINDOWS_1252_C1[(b - 0x80) as usize],
+                    _ => *b as char,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// How a [`MidiFile`] is turned into bytes by [`MidiFile::to_midi_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SerializeOptions {
+    /// Whether to use running status. See [`MidiFile::to_midi_with_running_status`].
+    pub running_status: bool,
+    /// The encoding of the text of meta events.
+    pub text_encoding: TextEncoding,
+}
+
 #[derive(Debug)]
 struct ParseCtx<'a, 'b> {
     input: &'a [u8],
@@ -76,6 +153,7 @@ struct ParseCtx<'a, 'b> {
     parsing: String,
     file: &'b mut MidiFile,
     track_end: usize,
+    text_encoding: TextEncoding,
 }
 
 impl<'a, 'b> ParseCtx<'a, 'b> {
@@ -86,6 +164,7 @@ impl<'a, 'b> ParseCtx<'a, 'b> {
             parsing: "header".into(),
             file,
             track_end: 0,
+            text_encoding: TextEncoding::Utf8,
         }
     }
 
@@ -120,66 +199,185 @@ impl<'a, 'b> ParseCtx<'a, 'b> {
     fn extend_track(&mut self, event: TrackEvent) {
         self.file.tracks.last_mut().unwrap().extend(event);
     }
+
+    /// Turn this context into a `MidiFileParseError`, taking whatever has been parsed of the file.
+    fn error(self, error: ParseError) -> MidiFileParseError {
+        let remaining_bytes = self.remaining();
+        MidiFileParseError {
+            error,
+            offset: self.offset,
+            next_bytes: self.slice(0..(20.min(remaining_bytes))).to_vec(),
+            remaining_bytes,
+            parsing: self.parsing,
+            file: mem::take(self.file),
+        }
+    }
 }
 
 impl MidiFile {
-    /// Turn a series of bytes into a `MidiFile`.
+    /// Turn a series of bytes into a `MidiFile`. The text of meta events is decoded as UTF-8.
     pub fn from_midi(v: &[u8]) -> Result<Self, MidiFileParseError> {
+        Self::from_midi_with_text_encoding(v, TextEncoding::Utf8)
+    }
+
+    /// Like [`MidiFile::from_midi`], but the text of meta events is decoded with the given encoding.
+    pub fn from_midi_with_text_encoding(
+        v: &[u8],
+        text_encoding: TextEncoding,
+    ) -> Result<Self, MidiFileParseError> {
+        Self::from_midi_with_decoder(v, |chunks, header| {
+            let mut tracks = vec![];
+            for (track_num, offset) in chunks.iter().enumerate() {
+                let track = Track::from_chunk(v, *offset, track_num as u16, header, text_encoding);
+                let failed = track.is_err();
+                tracks.push(track);
+                if failed {
+                    break;
+                }
+            }
+            tracks
+        })
+    }
+
+    /// Like [`MidiFile::from_midi`], but the tracks of the file are decoded in parallel.
+    ///
+    /// Each track chunk is located before any of them are decoded, so large files with many tracks can be parsed
+    /// faster this way. The result is the same as that of [`MidiFile::from_midi`].
+    #[cfg(feature = "parallel")]
+    pub fn from_midi_parallel(v: &[u8]) -> Result<Self, MidiFileParseError> {
+        use rayon::prelude::*;
+
+        Self::from_midi_with_decoder(v, |chunks, header| {
+            chunks
+                .par_iter()
+                .enumerate()
+                .map(|(track_num, offset)| {
+                    Track::from_chunk(v, *offset, track_num as u16, header, TextEncoding::Utf8)
+                })
+                .collect()
+        })
+    }
+
+    // Parse the header and locate each track chunk, then use `decode_tracks` to decode the chunks at the located offsets.
+    fn from_midi_with_decoder<F>(v: &[u8], decode_tracks: F) -> Result<Self, MidiFileParseError>
+    where
+        F: FnOnce(&[usize], &Header) -> Vec<Result<Track, MidiFileParseError>>,
+    {
         let mut file = MidiFile {
             header: Header::default(),
             tracks: vec![],
         };
         let mut ctx = ParseCtx::new(v, &mut file);
-        match Header::parse_midi_file(&mut ctx) {
-            Ok(_) => (),
-            Err(error) => {
-                let offset = ctx.offset;
-                let parsing = ctx.parsing.clone();
-                let remaining_bytes = ctx.remaining();
-                let next_bytes = ctx.slice(0..(20.min(ctx.remaining()))).to_vec();
-                return Err(MidiFileParseError {
-                    error,
-                    file,
-                    offset,
-                    parsing,
-                    remaining_bytes,
-                    next_bytes,
-                });
-            }
-        }
-
-        for i in 0..ctx.file.header.num_tracks {
-            match Track::parse_midi_file(&mut ctx, i) {
-                Ok(_) => (),
-                Err(error) => {
-                    let offset = ctx.offset;
-                    let parsing = ctx.parsing.clone();
-                    let remaining_bytes = ctx.remaining();
-                    let next_bytes = ctx.slice(0..(20.min(ctx.remaining()))).to_vec();
-                    return Err(MidiFileParseError {
-                        error,
-                        file,
-                        offset,
-                        parsing,
-                        remaining_bytes,
-                        next_bytes,
-                    });
+        if let Err(error) = Header::parse_midi_file(&mut ctx) {
+            return Err(ctx.error(error));
+        }
+
+        let mut chunks = vec![];
+        let located = Track::locate_chunks(&mut ctx, &mut chunks);
+        let header = ctx.file.header.clone();
+        // Even if not every chunk could be located, the ones that were are decoded so that they are part of the error
+        for track in decode_tracks(&chunks, &header) {
+            match track {
+                Ok(track) => ctx.add_track(track),
+                Err(mut e) => {
+                    let mut tracks = mem::take(&mut ctx.file.tracks);
+                    tracks.append(&mut e.file.tracks);
+                    e.file.tracks = tracks;
+                    return Err(e);
                 }
             }
         }
+        if let Err(error) = located {
+            return Err(ctx.error(error));
+        }
         Ok(file)
     }
 
+    /// Record a sequence of timestamped messages, e.g. from a live performance, into a single track file.
+    ///
+    /// Each message is paired with the time at which it occurred, in seconds. Messages should be given in
+    /// chronological order. The file has a [`Division::TicksPerQuarterNote`] of `ppq`, and the times of the messages
+    /// are converted to beats at the given constant tempo. The track starts with a [`Meta::SetTempo`] event for the
+    /// tempo, and ends with a [`Meta::EndOfTrack`] event at the time of the last message.
+    pub fn from_messages(msgs: &[(f64, MidiMsg)], ppq: u16, bpm: f32) -> MidiFile {
+        let mut file = MidiFile {
+            header: Header {
+                format: SMFFormat::SingleTrack,
+                num_tracks: 0,
+                division: Division::TicksPerQuarterNote(ppq),
+            },
+            tracks: vec![],
+        };
+        file.add_track(Track::default());
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::SetTempo(bpm_to_tempo(bpm)),
+            },
+            0.0,
+        );
+        let mut last_beat = 0.0;
+        for (seconds, msg) in msgs.iter() {
+            let beat = (*seconds * bpm as f64 / 60.0) as f32;
+            file.extend_track(0, msg.clone(), beat);
+            last_beat = beat;
+        }
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+            last_beat,
+        );
+        file
+    }
+
     /// Turn a `MidiFile` into a series of bytes.
     pub fn to_midi(&self) -> Vec<u8> {
+        self.to_midi_with_options(&SerializeOptions::default())
+    }
+
+    /// Turn a `MidiFile` into a series of bytes, using running status to shrink its tracks: the status byte of a
+    /// channel message is left out when it is the same as that of the previous channel message in the track.
+    /// Every system exclusive, meta, and other system event in between resets the running status, so the next
+    /// channel message includes its status byte. [`MidiMsg::RunningChannelVoice`] and
+    /// [`MidiMsg::RunningChannelMode`] events are written as their full equivalents.
+    ///
+    /// [`MidiFile::serialized_len`] gives the length of [`MidiFile::to_midi`], not of this.
+    pub fn to_midi_with_running_status(&self) -> Vec<u8> {
+        self.to_midi_with_options(&SerializeOptions {
+            running_status: true,
+            ..Default::default()
+        })
+    }
+
+    /// Turn a `MidiFile` into a series of bytes, as configured by `options`. E.g. the text of meta events can be
+    /// written in an encoding other than UTF-8, for software that expects Latin-1 text. Read such a file back with
+    /// [`MidiFile::from_midi_with_text_encoding`].
+    ///
+    /// [`MidiFile::serialized_len`] gives the length of [`MidiFile::to_midi`], not of this.
+    pub fn to_midi_with_options(&self, options: &SerializeOptions) -> Vec<u8> {
         let mut r: Vec<u8> = vec![];
         self.header.extend_midi(&mut r);
         for track in &self.tracks {
-            track.extend_midi(&mut r);
+            track.extend_midi(&mut r, options);
         }
         r
     }
 
+    /// The number of bytes that [`MidiFile::to_midi`] produces for this file, e.g. to preallocate a buffer for it.
+    pub fn serialized_len(&self) -> usize {
+        // Each event is measured by serializing it into this buffer, which is reused so that no more than the
+        // largest event is ever held
+        let mut scratch = vec![];
+        // The header chunk is always 14 bytes
+        14 + self
+            .tracks
+            .iter()
+            .map(|t| t.serialized_len(&mut scratch))
+            .sum::<usize>()
+    }
+
     /// Add a track to the file. Increments the `num_tracks` field in the header.
     pub fn add_track(&mut self, track: Track) {
         self.tracks.push(track);
@@ -192,886 +390,4390 @@ impl MidiFile {
         self.header.num_tracks -= 1;
     }
 
-    /// Add a midi event to a track in the file, given its absolute beat or frame time. The event delta time is calculated from the previous event in the track and the time division of the file.
-    pub fn extend_track(&mut self, track_num: usize, event: MidiMsg, beat_or_frame: f32) {
-        match &mut self.tracks[track_num] {
-            Track::Midi(events) => {
-                let last_beat_or_frame = events.last().map(|e| e.beat_or_frame).unwrap_or(0.0);
-                let last_event_tick = self
-                    .header
-                    .division
-                    .beat_or_frame_to_tick(last_beat_or_frame);
-                let this_event_tick = self.header.division.beat_or_frame_to_tick(beat_or_frame);
-                events.push(TrackEvent {
-                    delta_time: this_event_tick - last_event_tick,
+    /// Keep only the events, in every track, for which `f` returns true. See [`Track::retain_events`].
+    pub fn retain_events<F: FnMut(&TrackEvent) -> bool>(&mut self, mut f: F) {
+        for track in self.tracks.iter_mut() {
+            track.retain_events(&mut f);
+        }
+    }
+
+    /// Interleave the events of every track into a single stream, ordered by the absolute tick at which they occur,
+    /// e.g. to play back a [`SMFFormat::MultiTrack`] file. Events that occur at the same tick are ordered by track.
+    ///
+    /// The `delta_time` of each event is recomputed for the merged stream. Each track's [`Meta::EndOfTrack`] is
+    /// dropped, and a single one is added at the end of the longest track. `AlienChunk` tracks are skipped.
+    pub fn merge_tracks(&self) -> Vec<TrackEvent> {
+        let mut merged = AbsoluteTrack::default();
+        let mut end: u32 = 0;
+        for track in self.tracks.iter() {
+            for (tick, event) in AbsoluteTrack::from_delta(track).0 {
+                end = end.max(tick);
+                if !matches!(
                     event,
-                    beat_or_frame,
-                })
+                    MidiMsg::Meta {
+                        msg: Meta::EndOfTrack
+                    }
+                ) {
+                    merged.0.push((tick, event));
+                }
             }
-
-            Track::AlienChunk(_) => panic!("Cannot extend an alien chunk"),
+        }
+        merged.0.push((
+            end,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+        ));
+        match merged.into_delta(&self.header.division) {
+            Track::Midi(events) => events,
+            Track::AlienChunk(_) => unreachable!(),
         }
     }
-}
-
-/// The header chunk of a Standard Midi File
-#[derive(Debug, Clone, PartialEq, Default)]
-pub struct Header {
-    /// The format of the file
-    pub format: SMFFormat,
-    /// How many tracks are in the file
-    pub num_tracks: u16,
-    /// The "division" of the file, which specifies the meaning of the delta times in the file
-    pub division: Division,
-}
 
-impl Header {
-    // We pass the file to the from_midi function, so that we can have a running context of what's been parsed so far.
-    fn parse_midi_file(ctx: &mut ParseCtx) -> Result<(), ParseError> {
-        if ctx.remaining() < 14 {
-            return Err(ParseError::UnexpectedEnd);
+    /// Convert this file to a [`SMFFormat::SingleTrack`] file, with the events of every track merged into a single
+    /// track by [`MidiFile::merge_tracks`]. Since events at the same tick keep the order of their tracks, the
+    /// tempo and time signature events that usually open the first track still come first.
+    ///
+    /// `AlienChunk` tracks are removed, since a single track file must have exactly one track.
+    pub fn to_single_track(&self) -> MidiFile {
+        MidiFile {
+            header: Header {
+                format: SMFFormat::SingleTrack,
+                num_tracks: 1,
+                division: self.header.division,
+            },
+            tracks: vec![Track::Midi(self.merge_tracks())],
         }
-        if str::from_utf8(ctx.slice(0..4)) != Ok("MThd") {
-            return Err(ParseError::Invalid("Invalid header"));
+    }
+
+    /// Convert this file to a [`SMFFormat::MultiTrack`] file with one track per channel, the inverse of
+    /// [`MidiFile::to_single_track`]. The events of every track are first merged by [`MidiFile::merge_tracks`].
+    ///
+    /// The first track of the new file is a "conductor" track that holds every event that isn't a channel voice or
+    /// channel mode message, e.g. tempo, time signature, and other meta events, and system exclusive messages. It is
+    /// followed by a track for each channel that is used, in order of channel. Every track ends with a
+    /// [`Meta::EndOfTrack`] at the end of the file. `AlienChunk` tracks are removed.
+    pub fn split_by_channel(&self) -> MidiFile {
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut conductor = AbsoluteTrack::default();
+        let mut channels: Vec<AbsoluteTrack> = vec![AbsoluteTrack::default(); 16];
+        let mut tick: u32 = 0;
+        for event in self.merge_tracks() {
+            tick += event.delta_time;
+            match event.event {
+                MidiMsg::ChannelVoice { channel, .. }
+                | MidiMsg::RunningChannelVoice { channel, .. }
+                | MidiMsg::ChannelMode { channel, .. }
+                | MidiMsg::RunningChannelMode { channel, .. } => {
+                    channels[channel as usize].0.push((tick, event.event))
+                }
+                MidiMsg::Meta {
+                    msg: Meta::EndOfTrack,
+                } => (),
+                _ => conductor.0.push((tick, event.event)),
+            }
         }
-        ctx.advance(4);
-        if u32_from_midi(ctx.slice(0..4)) != Ok(6) {
-            return Err(ParseError::Invalid("Invalid header length"));
+
+        let mut file = MidiFile {
+            header: Header {
+                format: SMFFormat::MultiTrack,
+                num_tracks: 0,
+                division: self.header.division,
+            },
+            tracks: vec![],
+        };
+        conductor.0.push((tick, end.clone()));
+        file.add_track(conductor.into_delta(&self.header.division));
+        for mut track in channels.into_iter().filter(|t| !t.0.is_empty()) {
+            track.0.push((tick, end.clone()));
+            file.add_track(track.into_delta(&self.header.division));
         }
-        ctx.advance(4);
-        let v = ctx.data();
+        file
+    }
 
-        let (format, _) = SMFFormat::from_midi(v)?;
-        let num_tracks = u16::from_be_bytes([v[2], v[3]]);
-        let division = if v[4] & 0b1000_0000 == 0 {
-            Division::TicksPerQuarterNote(u16::from_be_bytes([v[4], v[5]]))
-        } else {
-            Division::TimeCode {
-                frames_per_second: match v[4] & 0b0111_1111 {
-                    0 => TimeCodeType::FPS24,
-                    1 => TimeCodeType::FPS25,
-                    2 => TimeCodeType::DF30,
-                    3 => TimeCodeType::NDF30,
-                    _ => return Err(ParseError::Invalid("Invalid time code type")),
-                },
-                ticks_per_frame: v[5],
-            }
+    /// A copy of this file that only contains its performance data: channel voice and channel mode
+    /// events, along with the [`Meta::SetTempo`] events needed to preserve their timing and the
+    /// [`Meta::EndOfTrack`] events that end each track. Any `AlienChunk` tracks are removed.
+    pub fn performance_only(&self) -> MidiFile {
+        let mut file = self.clone();
+        file.tracks.retain(|t| matches!(t, Track::Midi(_)));
+        file.header.num_tracks = file.tracks.len() as u16;
+        file.retain_events(|e| {
+            matches!(
+                e.event,
+                MidiMsg::ChannelVoice { .. }
+                    | MidiMsg::RunningChannelVoice { .. }
+                    | MidiMsg::ChannelMode { .. }
+                    | MidiMsg::RunningChannelMode { .. }
+                    | MidiMsg::Meta {
+                        msg: Meta::SetTempo(_) | Meta::EndOfTrack
+                    }
+            )
+        });
+        file
+    }
+
+    /// A copy of this file that only contains the events from `start_beat` up to (but not including)
+    /// `end_beat`, shifted so that `start_beat` becomes the start of the file. For files with a
+    /// [`Division::TimeCode`] division, the range is given in frames.
+    ///
+    /// Notes that are sounding at `start_beat` are started again at the start of the new file, and notes
+    /// that are still sounding at `end_beat` are ended there. The most recent [`Meta::SetTempo`],
+    /// [`Meta::TimeSignature`], and [`Meta::KeySignature`] events before `start_beat` are also placed at the
+    /// start of each track, so that the slice keeps its timing. Every track is ended with a
+    /// [`Meta::EndOfTrack`] at `end_beat`. `AlienChunk` tracks are copied unchanged.
+    pub fn slice(&self, start_beat: f32, end_beat: f32) -> MidiFile {
+        let mut file = MidiFile {
+            header: Header {
+                num_tracks: 0,
+                ..self.header.clone()
+            },
+            tracks: vec![],
         };
-        ctx.advance(6);
-        ctx.file.header = Self {
-            format,
-            num_tracks,
-            division,
+        let end_beat = end_beat.max(start_beat);
+
+        // Begin the last track of `file` with the given meta events and note ons
+        let start = |file: &mut MidiFile,
+                     metas: [&Option<MidiMsg>; 3],
+                     sounding: &[(Channel, u8, u16, bool)]| {
+            let track_num = file.tracks.len() - 1;
+            for msg in metas.iter().copied().flatten() {
+                file.extend_track(track_num, msg.clone(), 0.0);
+            }
+            for (channel, note, velocity, high_res) in sounding.iter().copied() {
+                let msg = if high_res {
+                    ChannelVoiceMsg::HighResNoteOn { note, velocity }
+                } else {
+                    ChannelVoiceMsg::NoteOn {
+                        note,
+                        velocity: velocity as u8,
+                    }
+                };
+                file.extend_track(track_num, MidiMsg::ChannelVoice { channel, msg }, 0.0);
+            }
         };
-        Ok(())
-    }
 
-    fn extend_midi(&self, v: &mut Vec<u8>) {
-        v.extend_from_slice(b"MThd");
-        push_u32(6, v); // Length of header, always 6 bytes
+        for track in self.tracks.iter() {
+            let events = match track {
+                Track::Midi(events) => events,
+                Track::AlienChunk(_) => {
+                    file.add_track(track.clone());
+                    continue;
+                }
+            };
+            file.add_track(Track::default());
+            let track_num = file.tracks.len() - 1;
 
-        self.format.extend_midi(v);
-        push_u16(self.num_tracks, v);
-        match self.division {
-            Division::TicksPerQuarterNote(tpqn) => {
-                push_u16(tpqn, v);
+            // Sounding notes, as (channel, note, velocity, high_res)
+            let mut sounding: Vec<(Channel, u8, u16, bool)> = vec![];
+            let mut tempo: Option<MidiMsg> = None;
+            let mut time_signature: Option<MidiMsg> = None;
+            let mut key_signature: Option<MidiMsg> = None;
+            let mut started = false;
+
+            for event in events.iter() {
+                if event.beat_or_frame >= end_beat {
+                    break;
+                }
+                let in_range = event.beat_or_frame >= start_beat;
+                if in_range && !started {
+                    started = true;
+                    start(
+                        &mut file,
+                        [&tempo, &time_signature, &key_signature],
+                        &sounding,
+                    );
+                }
+
+                match note_event(&event.event) {
+                    Some(NoteEvent::On {
+                        channel,
+                        note,
+                        velocity,
+                        high_res,
+                    }) => sounding.push((channel, note, velocity, high_res)),
+                    Some(NoteEvent::Off { channel, note }) => {
+                        if let Some(i) = sounding
+                            .iter()
+                            .position(|(c, n, ..)| (*c, *n) == (channel, note))
+                        {
+                            sounding.remove(i);
+                        }
+                    }
+                    Some(NoteEvent::AllOff { channel }) => {
+                        sounding.retain(|(c, ..)| *c != channel);
+                    }
+                    None => (),
+                }
+                if let MidiMsg::Meta { msg } = &event.event {
+                    match msg {
+                        Meta::EndOfTrack => continue,
+                        Meta::SetTempo(_) => tempo = Some(event.event.clone()),
+                        Meta::TimeSignature(_) => time_signature = Some(event.event.clone()),
+                        Meta::KeySignature(_) => key_signature = Some(event.event.clone()),
+                        _ => (),
+                    }
+                }
+
+                if in_range {
+                    file.extend_track(
+                        track_num,
+                        event.event.clone(),
+                        event.beat_or_frame - start_beat,
+                    );
+                }
             }
-            Division::TimeCode {
-                frames_per_second,
-                ticks_per_frame,
-            } => {
-                v.push(0b1000_0000 | frames_per_second as u8);
-                v.push(ticks_per_frame);
+
+            if !started {
+                start(
+                    &mut file,
+                    [&tempo, &time_signature, &key_signature],
+                    &sounding,
+                );
+            }
+            let end = end_beat - start_beat;
+            for (channel, note, _, high_res) in sounding.iter().copied() {
+                let msg = if high_res {
+                    ChannelVoiceMsg::HighResNoteOff { note, velocity: 0 }
+                } else {
+                    ChannelVoiceMsg::NoteOff { note, velocity: 0 }
+                };
+                file.extend_track(track_num, MidiMsg::ChannelVoice { channel, msg }, end);
             }
+            file.extend_track(
+                track_num,
+                MidiMsg::Meta {
+                    msg: Meta::EndOfTrack,
+                },
+                end,
+            );
         }
+        file
     }
-}
 
-/// The format of a Standard Midi File
-#[derive(Debug, Clone, PartialEq)]
-pub enum SMFFormat {
-    /// A single track file
-    SingleTrack,
-    /// The file contains multiple tracks, but they are all meant to be played simultaneously
-    MultiTrack,
-    /// The file contains multiple tracks, but they are independent of each other
-    MultiSong,
-}
+    /// The [`Meta::SequenceNumber`] of each track in the file. See [`Track::sequence_number`].
+    ///
+    /// In a [`SMFFormat::MultiSong`] file, these identify each song, e.g. for cueing.
+    pub fn sequence_numbers(&self) -> Vec<Option<u16>> {
+        self.tracks.iter().map(|t| t.sequence_number()).collect()
+    }
 
-impl Default for SMFFormat {
-    fn default() -> Self {
-        SMFFormat::MultiTrack
+    /// The output port that each track of the file is intended for, as given by its [`Meta::MidiPort`]. See
+    /// [`Track::midi_port`].
+    ///
+    /// Files written by sequencers with more than one output, often in [`SMFFormat::MultiTrack`] files, use these
+    /// to route each track to the right device.
+    pub fn assign_ports(&self) -> Vec<Option<u8>> {
+        self.tracks.iter().map(|t| t.midi_port()).collect()
     }
-}
 
-impl SMFFormat {
-    fn from_midi(v: &[u8]) -> Result<(Self, usize), ParseError> {
-        if v.len() < 2 {
-            return Err(ParseError::UnexpectedEnd);
-        }
-        Ok((
-            // Big endian 16 bit value: Only the LSB is used
-            match v[1] {
-                0 => SMFFormat::SingleTrack,
-                1 => SMFFormat::MultiTrack,
-                2 => SMFFormat::MultiSong,
-                _ => return Err(ParseError::Invalid("Invalid SMF format")),
-            },
-            2,
-        ))
+    /// Whether any control change in the file is sent with 14-bit resolution, i.e. with an LSB controller (32-63),
+    /// or with the High Resolution Velocity Prefix (CC 88).
+    pub fn uses_high_res_cc(&self) -> bool {
+        self.tracks.iter().any(|track| {
+            track.events().iter().any(|e| match e.event {
+                MidiMsg::ChannelVoice { msg, .. } | MidiMsg::RunningChannelVoice { msg, .. } => {
+                    match msg {
+                        ChannelVoiceMsg::HighResNoteOn { .. }
+                        | ChannelVoiceMsg::HighResNoteOff { .. } => true,
+                        ChannelVoiceMsg::ControlChange { control } => control
+                            .to_midi_running()
+                            .chunks(2)
+                            .any(|cc| (32..64).contains(&cc[0]) || cc[0] == 88),
+                        _ => false,
+                    }
+                }
+                _ => false,
+            })
+        })
     }
 
-    fn extend_midi(&self, v: &mut Vec<u8>) {
-        match self {
-            SMFFormat::SingleTrack => v.extend_from_slice(&[0, 0]),
-            SMFFormat::MultiTrack => v.extend_from_slice(&[0, 1]),
-            SMFFormat::MultiSong => v.extend_from_slice(&[0, 2]),
+    /// A 64 bit hash of the musical content of the file, so that files can be compared regardless of how they were
+    /// encoded.
+    ///
+    /// The hash covers, for every MIDI track in order: the time (in beats or frames, to 1/10000th) and content of
+    /// each channel voice, channel mode, and system exclusive event, as well as each [`Meta::SetTempo`],
+    /// [`Meta::TimeSignature`], and [`Meta::KeySignature`] event. Everything else is ignored, including the
+    /// file's [`Division`], whether running status was used, text and name events, and `AlienChunk` tracks.
+    ///
+    /// The hash (FNV-1a) is stable across runs and platforms, but is not cryptographically secure.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash: u64 = 0xcbf29ce484222325;
+        let mut hash_bytes = |bytes: &[u8]| {
+            for b in bytes {
+                hash ^= *b as u64;
+                hash = hash.wrapping_mul(0x100000001b3);
+            }
+        };
+
+        for track in self.tracks.iter() {
+            if let Track::Midi(events) = track {
+                hash_bytes(b"MTrk");
+                for e in events.iter() {
+                    let event = match e.event.clone() {
+                        MidiMsg::RunningChannelVoice { channel, msg } => {
+                            MidiMsg::ChannelVoice { channel, msg }
+                        }
+                        MidiMsg::RunningChannelMode { channel, msg } => {
+                            MidiMsg::ChannelMode { channel, msg }
+                        }
+                        event @ (MidiMsg::ChannelVoice { .. }
+                        | MidiMsg::ChannelMode { .. }
+                        | MidiMsg::SystemExclusive { .. }
+                        | MidiMsg::Meta {
+                            msg: Meta::SetTempo(_) | Meta::TimeSignature(_) | Meta::KeySignature(_),
+                        }) => event,
+                        _ => continue,
+                    };
+                    let time = (e.beat_or_frame * 10_000.0).round() as u64;
+                    hash_bytes(&time.to_le_bytes());
+                    hash_bytes(&event.to_midi());
+                }
+            }
         }
+        hash
     }
-}
-
-/// The division of a Standard Midi File, which specifies the meaning of the delta times in the file
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Division {
-    /// Metrical time. Number of "ticks" per quarter note.
-    TicksPerQuarterNote(u16),
-    /// Time code based time.
-    TimeCode {
-        frames_per_second: TimeCodeType,
-        ticks_per_frame: u8,
-    },
-}
 
-impl Default for Division {
-    fn default() -> Self {
-        Division::TicksPerQuarterNote(96)
-    }
-}
+    /// The notes that are sounding at the end of each bar of the file, as `(bar, notes)` pairs where `bar`
+    /// counts from 0.
+    ///
+    /// Bar lines are found from the [`Meta::TimeSignature`] events of every track, assuming 4/4 until the first
+    /// one. A time signature that occurs partway through a bar starts a new bar. A note counts as sounding at
+    /// the end of a bar if it was started before the bar line and not yet released, so a note that ends exactly
+    /// on the bar line is included while one that starts on it is not. Only bars that end at or before the last
+    /// event of the file are returned.
+    ///
+    /// Returns an empty `Vec` for files with a [`Division::TimeCode`] division, since they have no bars.
+    pub fn notes_at_bar_boundaries(&self) -> Vec<(u32, Vec<(Channel, u8)>)> {
+        let ticks_per_quarter_note = match self.header.division {
+            Division::TicksPerQuarterNote(tpqn) => tpqn as u32,
+            Division::TimeCode { .. } => return vec![],
+        };
 
-impl Division {
-    /// Convert a beat or frame to the number of "ticks" in a file with this division.
-    pub fn beat_or_frame_to_tick(&self, beat_or_frame: f32) -> u32 {
-        match self {
-            Division::TicksPerQuarterNote(tpqn) => (beat_or_frame * *tpqn as f32) as u32,
-            Division::TimeCode {
-                ticks_per_frame, ..
-            } => (beat_or_frame * *ticks_per_frame as f32) as u32,
+        // All events of the file as (tick, event), ordered by tick and then by track
+        let mut events: Vec<(u32, &MidiMsg)> = vec![];
+        for track in self.tracks.iter() {
+            let mut tick: u32 = 0;
+            for event in track.events() {
+                tick += event.delta_time;
+                events.push((tick, &event.event));
+            }
         }
+        events.sort_by_key(|(tick, _)| *tick);
+
+        // Time signature changes as (tick, bar length in ticks)
+        let signatures: Vec<(u32, u32)> = events
+            .iter()
+            .filter_map(|(tick, e)| match e {
+                MidiMsg::Meta {
+                    msg: Meta::TimeSignature(sig),
+                } if sig.numerator > 0 => Some((
+                    *tick,
+                    ticks_per_quarter_note * 4 * sig.numerator as u32
+                        / sig.denominator.max(1) as u32,
+                )),
+                _ => None,
+            })
+            .collect();
+        let mut next_signature = 0;
+        let mut bar_len = ticks_per_quarter_note * 4;
+        let mut bar_start: u32 = 0;
+        let mut bar: u32 = 0;
+
+        let mut bars = vec![];
+        // Sounding (channel, note) pairs
+        let mut sounding: Vec<(Channel, u8)> = vec![];
+        for (tick, event) in events {
+            loop {
+                // Apply any time signature that starts the current bar
+                while next_signature < signatures.len() && signatures[next_signature].0 <= bar_start
+                {
+                    bar_len = signatures[next_signature].1.max(1);
+                    next_signature += 1;
+                }
+                let mut bar_end = bar_start + bar_len;
+                if let Some((sig_tick, _)) = signatures.get(next_signature) {
+                    bar_end = bar_end.min(*sig_tick);
+                }
+                if bar_end > tick {
+                    break;
+                }
+                bars.push((bar, sounding.clone()));
+                bar += 1;
+                bar_start = bar_end;
+            }
+
+            match note_event(event) {
+                Some(NoteEvent::On { channel, note, .. }) => sounding.push((channel, note)),
+                Some(NoteEvent::Off { channel, note }) => {
+                    if let Some(i) = sounding.iter().position(|n| *n == (channel, note)) {
+                        sounding.remove(i);
+                    }
+                }
+                Some(NoteEvent::AllOff { channel }) => sounding.retain(|(c, _)| *c != channel),
+                None => (),
+            }
+        }
+
+        bars
     }
 
-    /// Convert a number of file "ticks" to a beat or frame in a file with this division.
-    pub fn ticks_to_beats_or_frames(&self, ticks: u32) -> f32 {
-        match self {
-            Division::TicksPerQuarterNote(tpqn) => ticks as f32 / *tpqn as f32,
-            Division::TimeCode {
-                ticks_per_frame, ..
-            } => ticks as f32 / *ticks_per_frame as f32,
+    /// Scan the serialized file `v` for channel events that make use of running status, i.e. that omit their
+    /// status byte, without parsing the events themselves.
+    ///
+    /// Running status is not retained in a parsed `MidiFile`, so this works on the raw bytes, e.g. those that were
+    /// passed to [`MidiFile::from_midi`].
+    pub fn scan_running_status(v: &[u8]) -> Result<bool, ParseError> {
+        let byte = |i: usize| v.get(i).copied().ok_or(ParseError::UnexpectedEnd);
+        let vlq = |i: usize| read_vlq(v.get(i..).ok_or(ParseError::UnexpectedEnd)?);
+        let chunk_len = |i: usize| u32_from_midi(v.get(i + 4..).ok_or(ParseError::UnexpectedEnd)?);
+
+        let mut i = 0;
+        while i < v.len() {
+            let end = i + 8 + chunk_len(i)? as usize;
+            if v.get(i..i + 4) != Some(b"MTrk") {
+                i = end;
+                continue;
+            }
+            i += 8;
+            while i < end {
+                let (_, delta_len) = vlq(i)?;
+                i += delta_len;
+                let status = byte(i)?;
+                match status {
+                    0x00..=0x7F => return Ok(true),
+                    0xFF => {
+                        let (len, len_offset) = vlq(i + 2)?;
+                        i += 2 + len_offset + len as usize;
+                    }
+                    0xF0 | 0xF7 => {
+                        let (len, len_offset) = vlq(i + 1)?;
+                        i += 1 + len_offset + len as usize;
+                    }
+                    0xC0..=0xDF | 0xF1 | 0xF3 => i += 2,
+                    0xF2 => i += 3,
+                    0xF4..=0xFE => i += 1,
+                    _ => i += 3,
+                }
+            }
+            i = end;
         }
+        Ok(false)
     }
-}
 
-/// A track in a Standard Midi File
-#[derive(Debug, Clone, PartialEq)]
-pub enum Track {
-    /// A standard "MTrk" chunk
-    Midi(Vec<TrackEvent>),
-    /// Any other chunk data.
+    /// The time at which the file is supposed to start, as given by the first
+    /// [`Meta::SmpteOffset`] event found.
     ///
-    /// This includes the entire chuck data, include whatever chunk type and length.
-    AlienChunk(Vec<u8>),
-}
+    /// Tracks are searched in order, so if more than one track carries an offset, the one
+    /// belonging to the earliest track is returned.
+    pub fn smpte_offset(&self) -> Option<HighResTimeCode> {
+        self.tracks.iter().find_map(|track| {
+            track.events().iter().find_map(|e| match e.event {
+                MidiMsg::Meta {
+                    msg: Meta::SmpteOffset(time),
+                } => Some(time),
+                _ => None,
+            })
+        })
+    }
 
-impl Default for Track {
-    fn default() -> Self {
-        Track::Midi(vec![])
+    /// The [`MidiFile::smpte_offset`] of the file, in seconds. Files without an offset start at 0.
+    pub fn start_seconds(&self) -> f32 {
+        match self.smpte_offset() {
+            Some(time) => {
+                let frames = time.frames as f32 + time.fractional_frames as f32 / 100.0;
+                time.hours as f32 * 3600.0
+                    + time.minutes as f32 * 60.0
+                    + time.seconds as f32
+                    + frames / time.code_type.frames_per_second() as f32
+            }
+            None => 0.0,
+        }
     }
-}
 
-impl Track {
-    /// Get the number of events in the track, or the length in bytes of an `AlienChunk`.
-    pub fn len(&self) -> usize {
-        match self {
-            Track::Midi(events) => events.len(),
-            Track::AlienChunk(data) => data.len(),
+    /// The [`Meta::SetTempo`] events of the file, by the absolute tick at which they occur.
+    ///
+    /// Tempo events are collected from every track, since in a [`SMFFormat::MultiTrack`] file they usually live in
+    /// the first track but apply to all of them. The tracks of a [`SMFFormat::MultiSong`] file are independent, so
+    /// use [`TempoMap::from_track`] for those instead.
+    pub fn tempo_map(&self) -> TempoMap {
+        let mut map = TempoMap::default();
+        for track in self.tracks.iter() {
+            map.0.extend(TempoMap::from_track(track).0);
         }
+        map.0.sort_by_key(|(tick, _)| *tick);
+        map
     }
 
-    /// Get the [`TrackEvent`] events in the track. Will be empty for an `AlienChunk`.
-    pub fn events(&self) -> &[TrackEvent] {
-        match self {
-            Track::Midi(events) => events,
-            Track::AlienChunk(_) => &[],
+    /// The time in seconds at which the given tick of a track occurs, taking every tempo change before it into account.
+    ///
+    /// Files with a [`Division::TimeCode`] division ignore tempo changes, and use [`Division::ticks_to_seconds`].
+    /// Only the track's own tempo changes are used in a [`SMFFormat::MultiSong`] file, otherwise those of the whole
+    /// file are used (see [`MidiFile::tempo_map`]). The [`MidiFile::start_seconds`] offset is not added.
+    pub fn tick_to_seconds(&self, track_num: usize, tick: u32) -> f32 {
+        match self.header.division {
+            Division::TicksPerQuarterNote(tpqn) => {
+                let map = match self.header.format {
+                    SMFFormat::MultiSong => TempoMap::from_track(&self.tracks[track_num]),
+                    _ => self.tempo_map(),
+                };
+                map.ticks_to_seconds(tick, tpqn)
+            }
+            division => division.ticks_to_seconds(tick).unwrap_or(0.0),
         }
     }
 
-    fn extend(&mut self, event: TrackEvent) {
-        match self {
-            Track::Midi(events) => events.push(event),
+    /// The length of the file in ticks: the largest sum of the delta times of a track's events.
+    pub fn duration_ticks(&self) -> u32 {
+        self.tracks
+            .iter()
+            .map(|t| t.duration_ticks())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The length of the file in beats, or in frames for a file with a [`Division::TimeCode`] division.
+    pub fn duration_beats(&self) -> f32 {
+        self.header
+            .division
+            .ticks_to_beats_or_frames(self.duration_ticks())
+    }
+
+    /// The length of the file in seconds, taking tempo changes into account as [`MidiFile::tick_to_seconds`] does.
+    /// Since each track of a [`SMFFormat::MultiSong`] file has its own tempo, this is the length of the longest one.
+    pub fn duration_seconds(&self) -> f32 {
+        (0..self.tracks.len())
+            .map(|i| self.tick_to_seconds(i, self.tracks[i].duration_ticks()))
+            .fold(0.0, f32::max)
+    }
+
+    /// Every event of the file, paired with the time in microseconds at which it occurs, in time order. Events
+    /// that occur at the same time are ordered by track, then by their order in the track.
+    ///
+    /// Times are computed as [`MidiFile::tick_to_seconds`] does, but are exact to the microsecond: tempo changes
+    /// are integrated in whole ticks and microseconds, so no rounding error accumulates over long files. The
+    /// [`MidiFile::start_seconds`] offset is not added.
+    pub fn to_timestamped(&self) -> Vec<(u64, MidiMsg)> {
+        let file_map = self.tempo_map();
+        let mut events = vec![];
+        for track in self.tracks.iter() {
+            let track_map;
+            let map = match self.header.format {
+                SMFFormat::MultiSong => {
+                    track_map = TempoMap::from_track(track);
+                    &track_map
+                }
+                _ => &file_map,
+            };
+            for (tick, event) in AbsoluteTrack::from_delta(track).0 {
+                let micros = match self.header.division {
+                    Division::TicksPerQuarterNote(tpqn) => map.ticks_to_micros(tick, tpqn),
+                    division => division.ticks_to_micros(tick).unwrap_or(0),
+                };
+                events.push((micros, event));
+            }
+        }
+        events.sort_by_key(|(micros, _)| *micros);
+        events
+    }
+
+    /// Add a midi event to a track in the file, given its absolute beat or frame time. The event delta time is calculated from the previous event in the track and the time division of the file.
+    pub fn extend_track(&mut self, track_num: usize, event: MidiMsg, beat_or_frame: f32) {
+        match &mut self.tracks[track_num] {
+            Track::Midi(events) => {
+                let last_beat_or_frame = events.last().map(|e| e.beat_or_frame).unwrap_or(0.0);
+                events.push(TrackEvent {
+                    delta_time: self
+                        .header
+                        .division
+                        .delta_ticks(last_beat_or_frame, beat_or_frame),
+                    event,
+                    beat_or_frame,
+                })
+            }
+
             Track::AlienChunk(_) => panic!("Cannot extend an alien chunk"),
         }
     }
 
-    fn parse_midi_file(ctx: &mut ParseCtx, track_num: u16) -> Result<(), ParseError> {
-        if ctx.remaining() < 8 {
-            return Err(ParseError::UnexpectedEnd);
+    /// Sort the events of a track by their `beat_or_frame`, e.g. after adding them out of order with
+    /// [`MidiFile::extend_track`], and recompute each `delta_time` from the file's [`Division`]. The sort is stable,
+    /// so events at the same time keep their order.
+    ///
+    /// [`Meta::EndOfTrack`] is moved to the end of the track, and is moved to the time of the last event if it comes
+    /// before it. Does nothing to an `AlienChunk`.
+    pub fn sort_track(&mut self, track_num: usize) {
+        let division = self.header.division;
+        if let Track::Midi(events) = &mut self.tracks[track_num] {
+            let is_end = |e: &TrackEvent| {
+                matches!(
+                    e.event,
+                    MidiMsg::Meta {
+                        msg: Meta::EndOfTrack
+                    }
+                )
+            };
+            events.sort_by(|a, b| {
+                is_end(a).cmp(&is_end(b)).then(
+                    a.beat_or_frame
+                        .partial_cmp(&b.beat_or_frame)
+                        .unwrap_or(core::cmp::Ordering::Equal),
+                )
+            });
+            let mut last_beat_or_frame = 0.0;
+            for event in events.iter_mut() {
+                if is_end(event) {
+                    event.beat_or_frame = event.beat_or_frame.max(last_beat_or_frame);
+                }
+                event.delta_time = division.delta_ticks(last_beat_or_frame, event.beat_or_frame);
+                last_beat_or_frame = event.beat_or_frame;
+            }
         }
-        ctx.parsing(format!("track {}", track_num));
-        let len = u32_from_midi(ctx.slice(4..8))? as usize;
-        if ctx.remaining() < len + 8 {
-            return Err(ParseError::UnexpectedEnd);
+    }
+
+    /// Set the tempo, in beats per minute, from `beat` on, by inserting a [`Meta::SetTempo`] event into a track.
+    ///
+    /// As with [`MidiFile::add_tempo_ramp`], the event is inserted before any events in the track that occur at the
+    /// same time, so that it takes effect immediately, and the delta time of the event that follows it is adjusted.
+    pub fn set_tempo_bpm(&mut self, track_num: usize, beat: f32, bpm: f32) {
+        self.insert_event(
+            track_num,
+            MidiMsg::Meta {
+                msg: Meta::SetTempo(bpm_to_tempo(bpm)),
+            },
+            beat,
+        );
+    }
+
+    /// The tempo at `beat`, in beats per minute, given the [`MidiFile::tempo_map`] of the file. A tempo change
+    /// takes effect at its own beat.
+    pub fn tempo_at_beat(&self, beat: f32) -> f32 {
+        let tick = self.header.division.beat_or_frame_to_tick(beat);
+        60_000_000.0 / self.tempo_map().tempo_at(tick) as f32
+    }
+
+    /// Insert a series of [`Meta::SetTempo`] events into a track, gradually changing the tempo from `start_bpm` at `start_beat` to `end_bpm` at `end_beat`, as an accelerando or ritardando.
+    ///
+    /// `steps` tempo events are inserted at evenly spaced beats, always including one at `start_beat` and one at `end_beat`, so at least two events are inserted. The tempo is interpolated linearly in beats per minute, not microseconds per beat.
+    ///
+    /// The events are inserted before any events in the track that occur at the same time, so that they take effect immediately, and the delta times of the events that follow them are adjusted.
+    pub fn add_tempo_ramp(
+        &mut self,
+        track_num: usize,
+        start_beat: f32,
+        end_beat: f32,
+        start_bpm: f32,
+        end_bpm: f32,
+        steps: usize,
+    ) {
+        let steps = steps.max(2);
+        for i in 0..steps {
+            let t = i as f32 / (steps - 1) as f32;
+            let beat = start_beat + (end_beat - start_beat) * t;
+            let bpm = start_bpm + (end_bpm - start_bpm) * t;
+            self.insert_event(
+                track_num,
+                MidiMsg::Meta {
+                    msg: Meta::SetTempo(bpm_to_tempo(bpm)),
+                },
+                beat,
+            );
         }
-        if str::from_utf8(ctx.slice(0..4)) != Ok("MTrk") {
-            ctx.add_track(Self::AlienChunk(ctx.slice(0..len + 8).to_vec()));
-            ctx.advance(len + 8);
-            return Ok(());
+    }
+
+    /// Add a new track containing a metronome click for `bars` bars of the given time signature, with the
+    /// [`Meta::TimeSignature`] itself as its first event.
+    ///
+    /// There is one click per beat of the time signature, i.e. per `time_signature.denominator`th note. Each
+    /// click plays `note` on `channel` for half of a beat, with the first beat of each bar accented by
+    /// a velocity of 127 rather than 80. Beats are counted in quarter notes, as with [`MidiFile::extend_track`].
+    pub fn add_metronome_track(
+        &mut self,
+        channel: Channel,
+        note: u8,
+        bars: u32,
+        time_signature: FileTimeSignature,
+    ) {
+        let beats_per_click = 4.0 / time_signature.denominator.max(1) as f32;
+        let clicks_per_bar = time_signature.numerator as u32;
+        self.add_track(Track::default());
+        let track_num = self.tracks.len() - 1;
+        self.extend_track(
+            track_num,
+            MidiMsg::Meta {
+                msg: Meta::TimeSignature(time_signature),
+            },
+            0.0,
+        );
+        for click in 0..bars * clicks_per_bar {
+            let beat = click as f32 * beats_per_click;
+            let velocity = if click % clicks_per_bar == 0 { 127 } else { 80 };
+            self.extend_track(
+                track_num,
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::NoteOn { note, velocity },
+                },
+                beat,
+            );
+            self.extend_track(
+                track_num,
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::NoteOff { note, velocity: 0 },
+                },
+                beat + beats_per_click / 2.0,
+            );
         }
-        ctx.add_track(Self::Midi(vec![]));
-        ctx.advance(8);
-        ctx.track_length(len);
-        let reciever_ctx = &mut ReceiverContext::default().parsing_smf();
+        self.extend_track(
+            track_num,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+            (bars * clicks_per_bar) as f32 * beats_per_click,
+        );
+    }
 
-        let mut i = 0;
-        let mut last_beat_or_frame = 0.0;
-        while ctx.offset < ctx.track_end {
-            ctx.parsing(format!("track {} event {}", track_num, i));
-            let (event, event_len) = TrackEvent::from_midi(
-                ctx.data(),
-                reciever_ctx,
-                &ctx.file.header.division,
-                last_beat_or_frame,
-            )?;
-            last_beat_or_frame = event.beat_or_frame;
-            ctx.extend_track(event);
-            ctx.advance(event_len);
-            i += 1;
+    /// Insert an event into a track before any events occurring at or after `beat_or_frame`, adjusting the delta time of the event that follows it.
+    ///
+    /// A leading [`Meta::SequenceNumber`] is kept as the first event of the track.
+    fn insert_event(&mut self, track_num: usize, event: MidiMsg, beat_or_frame: f32) {
+        let division = self.header.division;
+        let leading = self.tracks[track_num].sequence_number().is_some() as usize;
+        match &mut self.tracks[track_num] {
+            Track::Midi(events) => {
+                let i = events
+                    .partition_point(|e| e.beat_or_frame < beat_or_frame)
+                    .max(leading);
+                let last_beat_or_frame = if i > 0 {
+                    events[i - 1].beat_or_frame
+                } else {
+                    0.0
+                };
+                if let Some(next) = events.get_mut(i) {
+                    next.delta_time = division.delta_ticks(beat_or_frame, next.beat_or_frame);
+                }
+                events.insert(
+                    i,
+                    TrackEvent {
+                        delta_time: division.delta_ticks(last_beat_or_frame, beat_or_frame),
+                        event,
+                        beat_or_frame,
+                    },
+                );
+            }
+            Track::AlienChunk(_) => panic!("Cannot extend an alien chunk"),
         }
-        if ctx.offset > ctx.track_end {
-            return Err(ParseError::Invalid(
-                "Track length exceeded the provided length",
-            ));
+    }
+}
+
+// Convert beats per minute to the microseconds per quarter note of a `Meta::SetTempo`.
+fn bpm_to_tempo(bpm: f32) -> u32 {
+    (60_000_000.0 / bpm).round() as u32
+}
+
+/// The header chunk of a Standard Midi File
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Header {
+    /// The format of the file
+    pub format: SMFFormat,
+    /// How many tracks are in the file
+    pub num_tracks: u16,
+    /// The "division" of the file, which specifies the meaning of the delta times in the file
+    pub division: Division,
+}
+
+impl Header {
+    // We pass the file to the from_midi function, so that we can have a running context of what's been parsed so far.
+    fn parse_midi_file(ctx: &mut ParseCtx) -> Result<(), ParseError> {
+        if ctx.remaining() < 14 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        if str::from_utf8(ctx.slice(0..4)) != Ok("MThd") {
+            return Err(ParseError::Invalid("Invalid header"));
+        }
+        ctx.advance(4);
+        if u32_from_midi(ctx.slice(0..4)) != Ok(6) {
+            return Err(ParseError::Invalid("Invalid header length"));
         }
+        ctx.advance(4);
+        let v = ctx.data();
+
+        let (format, _) = SMFFormat::from_midi(v)?;
+        let num_tracks = u16::from_be_bytes([v[2], v[3]]);
+        let division = if v[4] & 0b1000_0000 == 0 {
+            Division::TicksPerQuarterNote(u16::from_be_bytes([v[4], v[5]]))
+        } else {
+            Division::TimeCode {
+                frames_per_second: match v[4] & 0b0111_1111 {
+                    0 => TimeCodeType::FPS24,
+                    1 => TimeCodeType::FPS25,
+                    2 => TimeCodeType::DF30,
+                    3 => TimeCodeType::NDF30,
+                    _ => return Err(ParseError::Invalid("Invalid time code type")),
+                },
+                ticks_per_frame: v[5],
+            }
+        };
+        ctx.advance(6);
+        ctx.file.header = Self {
+            format,
+            num_tracks,
+            division,
+        };
         Ok(())
     }
 
     fn extend_midi(&self, v: &mut Vec<u8>) {
-        match self {
-            Track::Midi(events) => {
-                v.extend_from_slice(b"MTrk");
-                let s = v.len();
-                push_u32(0, v); // We will fill this in after we know the length
+        v.extend_from_slice(b"MThd");
+        push_u32(6, v); // Length of header, always 6 bytes
 
-                for event in events {
-                    event.extend_midi(v);
-                }
-                let e = v.len();
-                // Fill in the length
-                v[s..s + 4].copy_from_slice(&(e as u32 - s as u32 - 4).to_be_bytes());
+        self.format.extend_midi(v);
+        push_u16(self.num_tracks, v);
+        match self.division {
+            Division::TicksPerQuarterNote(tpqn) => {
+                push_u16(tpqn, v);
             }
-            Track::AlienChunk(data) => {
-                v.extend_from_slice(&data);
+            Division::TimeCode {
+                frames_per_second,
+                ticks_per_frame,
+            } => {
+                v.push(0b1000_0000 | frames_per_second as u8);
+                v.push(ticks_per_frame);
             }
         }
     }
 }
 
-/// An event occurring in a track in a Standard Midi File
+/// The format of a Standard Midi File
 #[derive(Debug, Clone, PartialEq)]
-pub struct TrackEvent {
-    /// The time since the last event. The meaning of this value is determined by the file header's [`Division`].
-    pub delta_time: u32,
-    /// The actual midi event.
-    pub event: MidiMsg,
-    /// Given the file's [`Division`], the time in beats or frames at which this event occurs.
-    ///
-    /// When deserializing, this is derived from the `delta_time` and the previous event's `beat_or_frame`.
-    ///
-    /// When manually constructing `TrackEvent`s (i.e. when not using the [`MidiFile::extend_track`] convenience function), this field can set to any value, as it is not used when serializing the file.
-    pub beat_or_frame: f32,
+pub enum SMFFormat {
+    /// A single track file
+    SingleTrack,
+    /// The file contains multiple tracks, but they are all meant to be played simultaneously
+    MultiTrack,
+    /// The file contains multiple tracks, but they are independent of each other
+    MultiSong,
 }
 
-impl TrackEvent {
-    fn from_midi(
-        v: &[u8],
-        ctx: &mut ReceiverContext,
-        division: &Division,
-        last_beat_or_frame: f32,
-    ) -> Result<(Self, usize), ParseError> {
-        let (delta_time, time_offset) = read_vlq(v)?;
-        let beat_or_frame = last_beat_or_frame + division.ticks_to_beats_or_frames(delta_time);
-        match v[time_offset..].first() {
-            Some(b) => match b >> 4 {
-                0xF => match b & 0b0000_1111 {
-                    0x0 => {
-                        let (len, len_offset) = read_vlq(&v[time_offset + 1..])?;
-                        let p = time_offset + len_offset + 1;
-                        ctx.is_smf_sysex = true;
-                        let event = match SystemExclusiveMsg::from_midi(&v[p..], ctx) {
-                            Ok((event, event_len)) => {
-                                // len is not the length of the entire message, since we don't have the status byte
-                                if event_len - 1 != len as usize {
-                                    return Err(ParseError::Invalid(
-                                        "Invalid system exclusive message",
-                                    ));
-                                }
-                                MidiMsg::SystemExclusive { msg: event }
-                            }
-                            Err(e) => MidiMsg::Invalid {
-                                bytes: v[p..p + len as usize].to_vec(),
-                                error: e,
-                            },
-                        };
-                        Ok((
-                            Self {
-                                delta_time,
-                                event,
-                                beat_or_frame,
-                            },
-                            p + len as usize,
-                        ))
-                    }
-                    0x7 => {
-                        let (len, len_offset) = read_vlq(&v[time_offset + 1..])?;
-                        let p = time_offset + len_offset + 1;
-                        ctx.is_smf_sysex = false;
-                        let event = match MidiMsg::from_midi_with_context(&v[p..], ctx) {
-                            Ok((event, event_len)) => {
-                                // len _is_ the length of the entire message
-                                if event_len != len as usize {
-                                    return Err(ParseError::Invalid(
-                                        "Invalid system exclusive message",
-                                    ));
-                                }
-                                event
-                            }
-                            Err(e) => MidiMsg::Invalid {
-                                bytes: v[p..p + len as usize].to_vec(),
-                                error: e,
-                            },
-                        };
+impl Default for SMFFormat {
+    fn default() -> Self {
+        SMFFormat::MultiTrack
+    }
+}
 
-                        Ok((
-                            Self {
-                                delta_time,
-                                event,
-                                beat_or_frame,
-                            },
-                            p + len as usize,
-                        ))
-                    }
-                    0xF => {
-                        let p = time_offset + 1;
-                        let (event, event_len) = Meta::from_midi(&v[p..])?;
-                        Ok((
-                            Self {
-                                delta_time,
-                                event: MidiMsg::Meta { msg: event },
-                                beat_or_frame,
-                            },
-                            p + event_len,
-                        ))
-                    }
-                    _ => Err(ParseError::Invalid("Invalid track event")),
-                },
-                _ => {
-                    ctx.is_smf_sysex = false;
-                    let (event, event_len) =
-                        MidiMsg::from_midi_with_context(&v[time_offset..], ctx)?;
-                    Ok((
-                        Self {
-                            delta_time,
-                            event,
-                            beat_or_frame,
-                        },
-                        time_offset + event_len,
-                    ))
-                }
-            },
-            None => Err(ParseError::UnexpectedEnd),
+impl SMFFormat {
+    fn from_midi(v: &[u8]) -> Result<(Self, usize), ParseError> {
+        if v.len() < 2 {
+            return Err(ParseError::UnexpectedEnd);
         }
+        Ok((
+            // Big endian 16 bit value: Only the LSB is used
+            match v[1] {
+                0 => SMFFormat::SingleTrack,
+                1 => SMFFormat::MultiTrack,
+                2 => SMFFormat::MultiSong,
+                _ => return Err(ParseError::Invalid("Invalid SMF format")),
+            },
+            2,
+        ))
     }
 
     fn extend_midi(&self, v: &mut Vec<u8>) {
-        if matches!(
-            self.event,
-            MidiMsg::SystemRealTime {
-                msg: crate::SystemRealTimeMsg::SystemReset,
-            }
-        ) {
-            #[cfg(feature = "std")]
-            log::warn!("SMF contains System Reset event, which is not valid. Skipping.");
-            return;
-        } else if self.event.is_invalid() {
-            return;
+        match self {
+            SMFFormat::SingleTrack => v.extend_from_slice(&[0, 0]),
+            SMFFormat::MultiTrack => v.extend_from_slice(&[0, 1]),
+            SMFFormat::MultiSong => v.extend_from_slice(&[0, 2]),
         }
+    }
+}
 
-        push_vlq(self.delta_time, v);
-        // TODO this doesn't handle running-status events
-        let event = self.event.to_midi();
+/// The division of a Standard Midi File, which specifies the meaning of the delta times in the file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Division {
+    /// Metrical time. Number of "ticks" per quarter note.
+    TicksPerQuarterNote(u16),
+    /// Time code based time.
+    TimeCode {
+        frames_per_second: TimeCodeType,
+        ticks_per_frame: u8,
+    },
+}
 
-        let is_meta = matches!(self.event, MidiMsg::Meta { .. });
-        // Any kind of system event
-        let is_system = match self.event {
-            MidiMsg::SystemExclusive { .. }
-            | MidiMsg::SystemCommon { .. }
-            | MidiMsg::SystemRealTime { .. } => true,
-            _ => false,
-        };
-        if is_meta {
-            v.push(0xFF);
-        } else if is_system {
-            // We always use the 0xF7 format for system events, since it can be used for all system events, not just system exclusive
-            v.push(0xF7);
-            push_vlq(event.len() as u32, v);
-        }
-        v.extend_from_slice(&event);
+impl Default for Division {
+    fn default() -> Self {
+        Division::TicksPerQuarterNote(96)
     }
 }
 
-/// A meta event in a Standard Midi File
-#[derive(Debug, Clone, PartialEq)]
-pub enum Meta {
-    /// Must occur at the start of a track, and specifies the sequence number of the track. In a MultiSong file, this is the "pattern" number that identifies the song for cueing purposes.
-    SequenceNumber(u16),
-    /// Any text, describing anything
-    Text(String),
-    /// A copyright notice
-    Copyright(String),
-    /// The name of the track
-    TrackName(String),
-    /// The name of the instrument used in the track
-    InstrumentName(String),
-    /// A lyric. See RP-017 for guidance on the use of this meta event.
-    Lyric(String),
-    /// Normally only used in a SingleTrack file, or the first track of a MultiTrack file. Used to mark significant points in the music.
-    Marker(String),
-    /// A description of something happening at a point in time
-    CuePoint(String),
-    /// The MIDI channel that the following track events are intended for. Effective until the next event that specifies a channel.
-    ChannelPrefix(Channel),
-    /// Marks the end of a track. This event is not optional. It must be the last event in every track.
-    EndOfTrack,
-    /// The tempo in microseconds per quarter note.
-    SetTempo(u32),
-    /// If present, the time at which the track is supposed to start. Should be present at the start of the track.
-    SmpteOffset(HighResTimeCode),
-    /// A time signature.
-    TimeSignature(FileTimeSignature),
-    /// A key signature.
-    KeySignature(KeySignature),
-    /// A chunk of data that is specific to the sequencer that created the file.
-    SequencerSpecific(Vec<u8>),
-    // TODO: RP-32
-    // TODO: RP-19
-    /// Any other meta event that is not recognized
-    Unknown { meta_type: u8, data: Vec<u8> },
-}
+impl Division {
+    /// Convert a beat or frame to the number of "ticks" in a file with this division.
+    pub fn beat_or_frame_to_tick(&self, beat_or_frame: f32) -> u32 {
+        match self {
+            Division::TicksPerQuarterNote(tpqn) => (beat_or_frame * *tpqn as f32) as u32,
+            Division::TimeCode {
+                ticks_per_frame, ..
+            } => (beat_or_frame * *ticks_per_frame as f32) as u32,
+        }
+    }
 
-impl Meta {
-    // We do not extend with 0xFF, as this is done in TrackEvent::extend_midi
-    pub(crate) fn from_midi(v: &[u8]) -> Result<(Self, usize), ParseError> {
-        if v.len() < 2 {
-            return Err(ParseError::UnexpectedEnd);
+    /// The number of "ticks" between two beats or frames in a file with this division.
+    ///
+    /// Returns 0 if `to_beat` comes before `from_beat`, since delta times cannot be negative.
+    pub fn delta_ticks(&self, from_beat: f32, to_beat: f32) -> u32 {
+        self.beat_or_frame_to_tick(to_beat)
+            .saturating_sub(self.beat_or_frame_to_tick(from_beat))
+    }
+
+    /// Convert a number of file "ticks" to a beat or frame in a file with this division.
+    ///
+    /// Frames are nominal frame counts, e.g. 30 frames per second for [`TimeCodeType::DF30`].
+    pub fn ticks_to_beats_or_frames(&self, ticks: u32) -> f32 {
+        match self {
+            Division::TicksPerQuarterNote(tpqn) => ticks as f32 / *tpqn as f32,
+            Division::TimeCode {
+                ticks_per_frame, ..
+            } => ticks as f32 / *ticks_per_frame as f32,
         }
-        let meta_type = v[0];
-        let (len, len_offset) = read_vlq(&v[1..])?;
-        if v.len() < len as usize + len_offset + 1 {
-            return Err(ParseError::UnexpectedEnd);
+    }
+
+    /// Convert a number of file "ticks" to seconds of real time in a file with a `TimeCode` division.
+    ///
+    /// [`TimeCodeType::DF30`] frames are counted at 30 frames per second, but last 1001/1000 as long as a nominal frame,
+    /// so the result is corrected to 29.97 frames per second.
+    ///
+    /// Returns `None` for `TicksPerQuarterNote` divisions, whose duration depends on the tempo.
+    pub fn ticks_to_seconds(&self, ticks: u32) -> Option<f32> {
+        match self {
+            Division::TicksPerQuarterNote(_) => None,
+            Division::TimeCode {
+                frames_per_second, ..
+            } => Some(
+                self.ticks_to_beats_or_frames(ticks) / frames_per_second.real_frames_per_second(),
+            ),
         }
-        let end = len as usize + len_offset + 1;
-        let data = &v[len_offset + 1..end];
-        match meta_type {
-            0x00 => {
-                if data.len() != 2 {
-                    return Err(ParseError::Invalid(
-                        "Sequence number meta event must have exactly 2 bytes",
-                    ));
-                }
-                Ok((
-                    Self::SequenceNumber(u16::from_be_bytes([data[0], data[1]])),
-                    end,
-                ))
+    }
+
+    /// Like [`Division::ticks_to_seconds`], but in whole microseconds. Unlike converting the result of
+    /// `ticks_to_seconds`, this is exact (rounded down) for every [`TimeCodeType`], including `DF30`.
+    /// A `ticks_per_frame` of 0 is treated as 1.
+    pub fn ticks_to_micros(&self, ticks: u32) -> Option<u64> {
+        match self {
+            Division::TicksPerQuarterNote(_) => None,
+            Division::TimeCode {
+                frames_per_second,
+                ticks_per_frame,
+            } => {
+                let (num, den) = match frames_per_second {
+                    TimeCodeType::DF30 => (1001, 1000),
+                    _ => (1, 1),
+                };
+                Some(
+                    ticks as u64 * 1_000_000 * num
+                        / ((*ticks_per_frame).max(1) as u64
+                            * frames_per_second.frames_per_second() as u64
+                            * den),
+                )
             }
-            0x01 => Ok((Self::Text(String::from_utf8_lossy(data).to_string()), end)),
-            0x02 => Ok((
-                Self::Copyright(String::from_utf8_lossy(data).to_string()),
-                end,
-            )),
-            0x03 => Ok((
-                Self::TrackName(String::from_utf8_lossy(data).to_string()),
-                end,
-            )),
-            0x04 => Ok((
-                Self::InstrumentName(String::from_utf8_lossy(data).to_string()),
-                end,
-            )),
-            0x05 => Ok((Self::Lyric(String::from_utf8_lossy(data).to_string()), end)),
-            0x06 => Ok((Self::Marker(String::from_utf8_lossy(data).to_string()), end)),
-            0x07 => Ok((
-                Self::CuePoint(String::from_utf8_lossy(data).to_string()),
-                end,
-            )),
-            0x20 => {
-                if data.len() != 1 {
-                    return Err(ParseError::Invalid(
-                        "ChannelPrefix meta event must have exactly 1 byte",
-                    ));
+        }
+    }
+}
+
+/// A track in a Standard Midi File
+#[derive(Debug, Clone, PartialEq)]
+pub enum Track {
+    /// A standard "MTrk" chunk
+    Midi(Vec<TrackEvent>),
+    /// Any other chunk data.
+    ///
+    /// This includes the entire chuck data, include whatever chunk type and length.
+    AlienChunk(Vec<u8>),
+}
+
+impl Default for Track {
+    fn default() -> Self {
+        Track::Midi(vec![])
+    }
+}
+
+impl Track {
+    /// Iterate over the events of the track along with the beat or frame at which they occur, given the file's
+    /// `division`. The time is computed from the delta times of the events, not from their `beat_or_frame`, so
+    /// it is correct for tracks that were built by hand. An `AlienChunk` has no events.
+    ///
+    /// See [`AbsoluteTrack::from_delta`] for the absolute ticks of the events.
+    pub fn iter_absolute<'a>(
+        &'a self,
+        division: &'a Division,
+    ) -> impl Iterator<Item = (f32, &'a MidiMsg)> + 'a {
+        self.events().iter().scan(0u32, move |tick, e| {
+            *tick += e.delta_time;
+            Some((division.ticks_to_beats_or_frames(*tick), &e.event))
+        })
+    }
+
+    /// The sum of the delta times of the track's events, i.e. the tick of its last event. 0 for an `AlienChunk`.
+    pub fn duration_ticks(&self) -> u32 {
+        self.events()
+            .iter()
+            .map(|e| e.delta_time)
+            .fold(0, u32::saturating_add)
+    }
+
+    /// Get the number of events in the track, or the length in bytes of an `AlienChunk`.
+    pub fn len(&self) -> usize {
+        match self {
+            Track::Midi(events) => events.len(),
+            Track::AlienChunk(data) => data.len(),
+        }
+    }
+
+    /// Get the [`TrackEvent`] events in the track. Will be empty for an `AlienChunk`.
+    pub fn events(&self) -> &[TrackEvent] {
+        match self {
+            Track::Midi(events) => events,
+            Track::AlienChunk(_) => &[],
+        }
+    }
+
+    /// The [`Meta::SequenceNumber`] of the track, if it has one.
+    ///
+    /// A sequence number must be the first event of a track.
+    pub fn sequence_number(&self) -> Option<u16> {
+        match self.events().first() {
+            Some(TrackEvent {
+                event:
+                    MidiMsg::Meta {
+                        msg: Meta::SequenceNumber(n),
+                    },
+                ..
+            }) => Some(*n),
+            _ => None,
+        }
+    }
+
+    /// The port given by the first [`Meta::MidiPort`] of the track, if it has one.
+    pub fn midi_port(&self) -> Option<u8> {
+        self.events().iter().find_map(|e| match e.event {
+            MidiMsg::Meta {
+                msg: Meta::MidiPort(port),
+            } => Some(port),
+            _ => None,
+        })
+    }
+
+    /// The channel that each event of the track is associated with.
+    ///
+    /// Channel voice and channel mode events carry their own channel. Meta and system exclusive events are associated
+    /// with the channel given by the most recent [`Meta::ChannelPrefix`], which is effective until the next channel
+    /// voice or channel mode event. All other events, and any events not preceded by a prefix, have no channel.
+    pub fn event_channels(&self) -> Vec<Option<Channel>> {
+        let mut prefix = None;
+        self.events()
+            .iter()
+            .map(|e| match e.event {
+                MidiMsg::ChannelVoice { channel, .. }
+                | MidiMsg::RunningChannelVoice { channel, .. }
+                | MidiMsg::ChannelMode { channel, .. }
+                | MidiMsg::RunningChannelMode { channel, .. } => {
+                    prefix = None;
+                    Some(channel)
+                }
+                MidiMsg::Meta {
+                    msg: Meta::ChannelPrefix(channel),
+                } => {
+                    prefix = Some(channel);
+                    Some(channel)
+                }
+                MidiMsg::Meta { .. } | MidiMsg::SystemExclusive { .. } => prefix,
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// A copy of the track where every event that is associated with a channel through a [`Meta::ChannelPrefix`] (see
+    /// [`Track::event_channels`]) is immediately preceded by its own `ChannelPrefix`. The original prefixes are
+    /// removed, so the channel of an event no longer depends on the events that came before it.
+    ///
+    /// [`Meta::EndOfTrack`] is never given a prefix. An `AlienChunk` is returned as-is.
+    pub fn resolve_channel_prefix(&self) -> Track {
+        let events = match self {
+            Track::Midi(events) => events,
+            Track::AlienChunk(_) => return self.clone(),
+        };
+        let mut resolved = Vec::with_capacity(events.len());
+        let mut carried_delta = 0;
+        for (e, channel) in events.iter().zip(self.event_channels()) {
+            let delta_time = e.delta_time + carried_delta;
+            carried_delta = 0;
+            match (&e.event, channel) {
+                (
+                    MidiMsg::Meta {
+                        msg: Meta::ChannelPrefix(_),
+                    },
+                    _,
+                ) => carried_delta = delta_time,
+                (
+                    MidiMsg::Meta {
+                        msg: Meta::EndOfTrack,
+                    },
+                    _,
+                )
+                | (MidiMsg::ChannelVoice { .. }, _)
+                | (MidiMsg::RunningChannelVoice { .. }, _)
+                | (MidiMsg::ChannelMode { .. }, _)
+                | (MidiMsg::RunningChannelMode { .. }, _)
+                | (_, None) => resolved.push(TrackEvent {
+                    delta_time,
+                    ..e.clone()
+                }),
+                (_, Some(channel)) => {
+                    resolved.push(TrackEvent {
+                        delta_time,
+                        event: MidiMsg::Meta {
+                            msg: Meta::ChannelPrefix(channel),
+                        },
+                        beat_or_frame: e.beat_or_frame,
+                    });
+                    resolved.push(TrackEvent {
+                        delta_time: 0,
+                        ..e.clone()
+                    });
                 }
-                Ok((Self::ChannelPrefix(Channel::from_u8(data[0])), end))
             }
-            0x2F => Ok((Self::EndOfTrack, end)),
-            0x51 => {
-                if data.len() != 3 {
-                    return Err(ParseError::Invalid(
-                        "SetTempo meta event must have exactly 3 bytes",
-                    ));
+        }
+        Track::Midi(resolved)
+    }
+
+    /// The indices of the events in the track that could not be parsed as a system exclusive message, i.e. the
+    /// [`MidiMsg::Invalid`] events created when parsing a file.
+    ///
+    /// Events whose declared length does not match the length of the sysex message found in them have the error
+    /// `ParseError::Invalid("Invalid system exclusive message")`. Otherwise the error is the one encountered while
+    /// parsing the message.
+    pub fn validate_sysex(&self) -> Vec<usize> {
+        self.events()
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| e.event.is_invalid())
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Find the chords in the track: groups of two or more `NoteOn` events on the same channel
+    /// that start within `tolerance_ticks` of the first note in the group.
+    ///
+    /// Returns the tick at which each chord occurs -- the average of the ticks of its notes --
+    /// along with the notes that make it up, ordered by tick. `NoteOn` events with a velocity
+    /// of 0 are treated as note offs and are ignored.
+    pub fn chords(&self, tolerance_ticks: u32) -> Vec<(u32, Vec<u8>)> {
+        // (channel, first tick, ticks of each note, notes)
+        let mut groups: Vec<(Channel, u32, Vec<u32>, Vec<u8>)> = vec![];
+        let mut chords: Vec<(u32, Vec<u8>)> = vec![];
+        let mut tick: u32 = 0;
+
+        fn close_group(group: (Channel, u32, Vec<u32>, Vec<u8>), chords: &mut Vec<(u32, Vec<u8>)>) {
+            let (_, _, ticks, notes) = group;
+            if notes.len() > 1 {
+                let sum: u64 = ticks.iter().map(|t| *t as u64).sum();
+                chords.push(((sum / ticks.len() as u64) as u32, notes));
+            }
+        }
+
+        for event in self.events() {
+            tick += event.delta_time;
+            let (channel, note) = match event.event {
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::NoteOn { note, velocity },
+                } if velocity > 0 => (channel, note),
+                MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::HighResNoteOn { note, velocity },
+                } if velocity > 0 => (channel, note),
+                _ => continue,
+            };
+
+            // Close any groups that can no longer be added to
+            let mut i = 0;
+            while i < groups.len() {
+                if tick - groups[i].1 > tolerance_ticks {
+                    close_group(groups.remove(i), &mut chords);
+                } else {
+                    i += 1;
                 }
-                Ok((
-                    Self::SetTempo(u32::from_be_bytes([0, data[0], data[1], data[2]])),
-                    end,
-                ))
             }
-            0x54 => {
-                if data.len() != 5 {
-                    return Err(ParseError::Invalid(
-                        "SmpteOffset meta event must have exactly 5 bytes",
-                    ));
+
+            match groups.iter_mut().find(|g| g.0 == channel) {
+                Some(group) => {
+                    group.2.push(tick);
+                    group.3.push(note);
                 }
-                let (time, _) = HighResTimeCode::from_midi(data)?;
-                Ok((Self::SmpteOffset(time), end))
+                None => groups.push((channel, tick, vec![tick], vec![note])),
             }
-            0x58 => {
-                if data.len() != 4 {
-                    return Err(ParseError::Invalid(
-                        "TimeSignature meta event must have exactly 4 bytes",
-                    ));
+        }
+        for group in groups {
+            close_group(group, &mut chords);
+        }
+
+        chords.sort_by_key(|(tick, _)| *tick);
+        chords
+    }
+
+    /// Find the longest stretch of the track in which no notes are sounding.
+    ///
+    /// Returns the tick at which the gap starts and its length in ticks. Only notes count as activity: other events,
+    /// like control changes or meta events, may occur during a gap. The silence before the first note and after the
+    /// last note (until the last event in the track) are also considered gaps. `AllNotesOff` and `AllSoundOff` end
+    /// all the notes sounding on their channel. If there are multiple longest gaps, the first is returned.
+    ///
+    /// Returns `None` if the track has no gaps.
+    pub fn longest_gap(&self) -> Option<(u32, u32)> {
+        let mut longest: Option<(u32, u32)> = None;
+        // Sounding (channel, note) pairs
+        let mut sounding: Vec<(Channel, u8)> = vec![];
+        let mut gap_start = Some(0);
+        let mut tick: u32 = 0;
+
+        let mut close_gap = |start: u32, end: u32| {
+            let len = end - start;
+            if len > 0 && !matches!(longest, Some((_, l)) if l >= len) {
+                longest = Some((start, len));
+            }
+        };
+
+        for event in self.events() {
+            tick += event.delta_time;
+            let was_sounding = !sounding.is_empty();
+            match note_event(&event.event) {
+                Some(NoteEvent::On { channel, note, .. }) => {
+                    if let Some(start) = gap_start.take() {
+                        close_gap(start, tick);
+                    }
+                    sounding.push((channel, note));
                 }
-                Ok((
-                    Self::TimeSignature(FileTimeSignature::from_midi(data)?),
-                    end,
-                ))
+                Some(NoteEvent::Off { channel, note }) => {
+                    if let Some(i) = sounding.iter().position(|n| *n == (channel, note)) {
+                        sounding.remove(i);
+                    }
+                }
+                Some(NoteEvent::AllOff { channel }) => sounding.retain(|(c, _)| *c != channel),
+                None => (),
             }
-            0x59 => {
-                if data.len() != 2 {
-                    return Err(ParseError::Invalid(
-                        "KeySignature meta event must have exactly 2 bytes",
-                    ));
+            if was_sounding && sounding.is_empty() {
+                gap_start = Some(tick);
+            }
+        }
+        if let Some(start) = gap_start {
+            close_gap(start, tick);
+        }
+
+        longest
+    }
+
+    /// Find the notes that are started again while they are already sounding, without a note off in between.
+    ///
+    /// Returns the channel and note of each such retrigger, along with the tick at which it occurs. As with
+    /// [`Track::longest_gap`], `AllNotesOff` and `AllSoundOff` end all the notes sounding on their channel. A
+    /// retriggered note is sounding until it has received as many note offs as note ons.
+    pub fn overlapping_notes(&self) -> Vec<(Channel, u8, u32)> {
+        let mut overlapping = vec![];
+        // Sounding (channel, note) pairs
+        let mut sounding: Vec<(Channel, u8)> = vec![];
+        let mut tick: u32 = 0;
+
+        for event in self.events() {
+            tick += event.delta_time;
+            match note_event(&event.event) {
+                Some(NoteEvent::On { channel, note, .. }) => {
+                    if sounding.contains(&(channel, note)) {
+                        overlapping.push((channel, note, tick));
+                    }
+                    sounding.push((channel, note));
                 }
-                Ok((Self::KeySignature(KeySignature::from_midi(data)?), end))
+                Some(NoteEvent::Off { channel, note }) => {
+                    if let Some(i) = sounding.iter().position(|n| *n == (channel, note)) {
+                        sounding.remove(i);
+                    }
+                }
+                Some(NoteEvent::AllOff { channel }) => sounding.retain(|(c, _)| *c != channel),
+                None => (),
             }
-            0x7F => Ok((Self::SequencerSpecific(data.to_vec()), end)),
-            _ => Ok((
-                Self::Unknown {
-                    meta_type,
-                    data: data.to_vec(),
-                },
-                end,
-            )),
         }
+
+        overlapping
+    }
+
+    /// Match each note on of the track to the note off that ends it, as a [`NotePair`], in the order the notes start.
+    ///
+    /// A note off ends the earliest note on of the same channel and note that has not yet been ended, so a
+    /// retriggered note (see [`Track::overlapping_notes`]) gives one pair per note on. As with
+    /// [`Track::longest_gap`], `AllNotesOff` and `AllSoundOff` end all the notes sounding on their channel. Notes
+    /// that are never ended last until the last event of the track.
+    pub fn note_pairs(&self) -> Vec<NotePair> {
+        let mut pairs: Vec<NotePair> = vec![];
+        // Indices of the pairs that are still sounding
+        let mut sounding: Vec<usize> = vec![];
+        let mut tick: u32 = 0;
+
+        for event in self.events() {
+            tick += event.delta_time;
+            match note_event(&event.event) {
+                Some(NoteEvent::On {
+                    channel,
+                    note,
+                    velocity,
+                    high_res,
+                }) => {
+                    sounding.push(pairs.len());
+                    pairs.push(NotePair {
+                        channel,
+                        note,
+                        velocity: if high_res {
+                            (velocity >> 7) as u8
+                        } else {
+                            velocity as u8
+                        },
+                        start_tick: tick,
+                        duration_ticks: 0,
+                    });
+                }
+                Some(NoteEvent::Off { channel, note }) => {
+                    if let Some(i) = sounding
+                        .iter()
+                        .position(|p| pairs[*p].channel == channel && pairs[*p].note == note)
+                    {
+                        let pair = &mut pairs[sounding.remove(i)];
+                        pair.duration_ticks = tick - pair.start_tick;
+                    }
+                }
+                Some(NoteEvent::AllOff { channel }) => sounding.retain(|p| {
+                    let pair = &mut pairs[*p];
+                    if pair.channel == channel {
+                        pair.duration_ticks = tick - pair.start_tick;
+                    }
+                    pair.channel != channel
+                }),
+                None => (),
+            }
+        }
+        for p in sounding {
+            pairs[p].duration_ticks = tick - pairs[p].start_tick;
+        }
+
+        pairs
+    }
+
+    /// Keep only the events for which `f` returns true.
+    ///
+    /// The delta times of removed events are added to the next retained event, so the retained
+    /// events keep their timing. Does nothing to an `AlienChunk`.
+    pub fn retain_events<F: FnMut(&TrackEvent) -> bool>(&mut self, mut f: F) {
+        if let Track::Midi(events) = self {
+            let mut carried_delta = 0;
+            events.retain_mut(|e| {
+                if f(e) {
+                    e.delta_time += carried_delta;
+                    carried_delta = 0;
+                    true
+                } else {
+                    carried_delta += e.delta_time;
+                    false
+                }
+            });
+        }
+    }
+
+    fn extend(&mut self, event: TrackEvent) {
+        match self {
+            Track::Midi(events) => events.push(event),
+            Track::AlienChunk(_) => panic!("Cannot extend an alien chunk"),
+        }
+    }
+
+    // Find the offset of each track chunk, without decoding them.
+    fn locate_chunks(ctx: &mut ParseCtx, chunks: &mut Vec<usize>) -> Result<(), ParseError> {
+        for track_num in 0..ctx.file.header.num_tracks {
+            if ctx.remaining() < 8 {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            ctx.parsing(format!("track {}", track_num));
+            let len = u32_from_midi(ctx.slice(4..8))? as usize;
+            if ctx.remaining() < len + 8 {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            chunks.push(ctx.offset);
+            ctx.advance(len + 8);
+        }
+        Ok(())
+    }
+
+    // Decode the track chunk starting at `offset`. Errors contain a file with the given header and whatever could be
+    // parsed of the track.
+    fn from_chunk(
+        v: &[u8],
+        offset: usize,
+        track_num: u16,
+        header: &Header,
+        text_encoding: TextEncoding,
+    ) -> Result<Self, MidiFileParseError> {
+        let mut file = MidiFile {
+            header: header.clone(),
+            tracks: vec![],
+        };
+        let mut ctx = ParseCtx::new(v, &mut file);
+        ctx.text_encoding = text_encoding;
+        ctx.advance(offset);
+        match Self::parse_midi_file(&mut ctx, track_num) {
+            Ok(_) => Ok(ctx.file.tracks.pop().unwrap()),
+            Err(error) => Err(ctx.error(error)),
+        }
+    }
+
+    fn parse_midi_file(ctx: &mut ParseCtx, track_num: u16) -> Result<(), ParseError> {
+        if ctx.remaining() < 8 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        ctx.parsing(format!("track {}", track_num));
+        let len = u32_from_midi(ctx.slice(4..8))? as usize;
+        if ctx.remaining() < len + 8 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        if str::from_utf8(ctx.slice(0..4)) != Ok("MTrk") {
+            ctx.add_track(Self::AlienChunk(ctx.slice(0..len + 8).to_vec()));
+            ctx.advance(len + 8);
+            return Ok(());
+        }
+        ctx.add_track(Self::Midi(vec![]));
+        ctx.advance(8);
+        ctx.track_length(len);
+        let reciever_ctx = &mut ReceiverContext::default().parsing_smf();
+
+        let mut i = 0;
+        let mut last_beat_or_frame = 0.0;
+        // The delta time of the packets of a split system exclusive message, which is carried to the next event
+        let mut carried_delta = 0;
+        while ctx.offset < ctx.track_end {
+            ctx.parsing(format!("track {} event {}", track_num, i));
+            let (event, event_len) = TrackEvent::from_midi(
+                ctx.data(),
+                reciever_ctx,
+                &ctx.file.header.division,
+                last_beat_or_frame,
+                ctx.text_encoding,
+            )?;
+            let mut event = match event {
+                Some(event) => event,
+                None => {
+                    let (delta_time, _) = read_vlq(ctx.data())?;
+                    carried_delta += delta_time;
+                    last_beat_or_frame += ctx
+                        .file
+                        .header
+                        .division
+                        .ticks_to_beats_or_frames(delta_time);
+                    ctx.advance(event_len);
+                    i += 1;
+                    continue;
+                }
+            };
+            ctx.advance(event_len);
+            i += 1;
+            if let MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            } = event.event
+            {
+                // A split system exclusive message that was never finished
+                if let Some(bytes) = reciever_ctx.sysex_packets.take() {
+                    ctx.extend_track(TrackEvent {
+                        delta_time: carried_delta,
+                        event: MidiMsg::Invalid {
+                            bytes: bytes[1..].to_vec(),
+                            error: ParseError::NoEndOfSystemExclusiveFlag,
+                        },
+                        beat_or_frame: last_beat_or_frame,
+                    });
+                    carried_delta = 0;
+                }
+            }
+            event.delta_time += carried_delta;
+            carried_delta = 0;
+            last_beat_or_frame = event.beat_or_frame;
+            ctx.extend_track(event);
+        }
+        if ctx.offset > ctx.track_end {
+            return Err(ParseError::Invalid(
+                "Track length exceeded the provided length",
+            ));
+        }
+        Ok(())
+    }
+
+    fn serialized_len(&self, scratch: &mut Vec<u8>) -> usize {
+        match self {
+            Track::Midi(events) => {
+                8 + events
+                    .iter()
+                    .map(|e| e.serialized_len(scratch))
+                    .sum::<usize>()
+            }
+            Track::AlienChunk(data) => data.len(),
+        }
+    }
+
+    fn extend_midi(&self, v: &mut Vec<u8>, options: &SerializeOptions) {
+        match self {
+            Track::Midi(events) => {
+                v.extend_from_slice(b"MTrk");
+                let s = v.len();
+                push_u32(0, v); // We will fill this in after we know the length
+
+                let mut status = None;
+                for event in events {
+                    if options.running_status {
+                        event.extend_midi_running(v, &mut status, options.text_encoding);
+                    } else {
+                        event.extend_midi(v, options.text_encoding);
+                    }
+                }
+                let e = v.len();
+                // Fill in the length
+                v[s..s + 4].copy_from_slice(&(e as u32 - s as u32 - 4).to_be_bytes());
+            }
+            Track::AlienChunk(data) => {
+                v.extend_from_slice(&data);
+            }
+        }
+    }
+}
+
+/// A note of a [`Track`], from its note on to the note off that ends it. See [`Track::note_pairs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotePair {
+    /// The channel the note is played on.
+    pub channel: Channel,
+    /// The MIDI note number, 0-127.
+    pub note: u8,
+    /// The note on velocity, 1-127. Only the most significant 7 bits of a
+    /// [`HighResNoteOn`](ChannelVoiceMsg::HighResNoteOn) velocity are kept.
+    pub velocity: u8,
+    /// The absolute tick at which the note starts.
+    pub start_tick: u32,
+    /// The number of ticks from the note on to the note off.
+    pub duration_ticks: u32,
+}
+
+/// How a message affects the notes that are sounding. Used by the methods that track sounding notes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NoteEvent {
+    /// A note on with a velocity above 0. The velocity is as stored in the message: 14 bits if `high_res`.
+    On {
+        channel: Channel,
+        note: u8,
+        velocity: u16,
+        high_res: bool,
+    },
+    /// A note off, or a note on with a velocity of 0.
+    Off { channel: Channel, note: u8 },
+    /// `AllNotesOff` or `AllSoundOff`, which end all the notes sounding on the channel.
+    AllOff { channel: Channel },
+}
+
+fn note_event(msg: &MidiMsg) -> Option<NoteEvent> {
+    match *msg {
+        MidiMsg::ChannelVoice {
+            channel,
+            msg: voice,
+        }
+        | MidiMsg::RunningChannelVoice {
+            channel,
+            msg: voice,
+        } => {
+            let note = msg.note()?;
+            let velocity = msg.velocity()?;
+            Some(match voice {
+                ChannelVoiceMsg::NoteOn { .. } | ChannelVoiceMsg::HighResNoteOn { .. }
+                    if velocity > 0 =>
+                {
+                    NoteEvent::On {
+                        channel,
+                        note,
+                        velocity,
+                        high_res: matches!(voice, ChannelVoiceMsg::HighResNoteOn { .. }),
+                    }
+                }
+                _ => NoteEvent::Off { channel, note },
+            })
+        }
+        MidiMsg::ChannelMode { channel, msg } | MidiMsg::RunningChannelMode { channel, msg } => {
+            match msg {
+                ChannelModeMsg::AllNotesOff | ChannelModeMsg::AllSoundOff => {
+                    Some(NoteEvent::AllOff { channel })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// The events of a [`Track`], positioned by the absolute tick at which they occur rather than by their delta time,
+/// which makes it simpler to insert, remove, and move events.
+///
+/// Convert to and from a `Track` with [`AbsoluteTrack::from_delta`] and [`AbsoluteTrack::into_delta`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AbsoluteTrack(pub Vec<(u32, MidiMsg)>);
+
+impl AbsoluteTrack {
+    /// The events of `track` along with their absolute ticks. An `AlienChunk` has no events.
+    pub fn from_delta(track: &Track) -> Self {
+        let mut tick: u32 = 0;
+        Self(
+            track
+                .events()
+                .iter()
+                .map(|e| {
+                    tick += e.delta_time;
+                    (tick, e.event.clone())
+                })
+                .collect(),
+        )
+    }
+
+    /// Turn these events back into a delta-encoded [`Track`], with the `beat_or_frame` of each event set according
+    /// to `division`.
+    ///
+    /// The events are first sorted by tick. Events that occur at the same tick keep their order.
+    pub fn into_delta(mut self, division: &Division) -> Track {
+        self.0.sort_by_key(|(tick, _)| *tick);
+        let mut last_tick = 0;
+        Track::Midi(
+            self.0
+                .into_iter()
+                .map(|(tick, event)| {
+                    let delta_time = tick - last_tick;
+                    last_tick = tick;
+                    TrackEvent {
+                        delta_time,
+                        event,
+                        beat_or_frame: division.ticks_to_beats_or_frames(tick),
+                    }
+                })
+                .collect(),
+        )
+    }
+}
+
+/// The tempo changes of a file or track: each [`Meta::SetTempo`] event, as the absolute tick at which it occurs
+/// and the new tempo in microseconds per quarter note. The tempo is 120 BPM (500000 microseconds per quarter note)
+/// until the first change.
+///
+/// Created by [`MidiFile::tempo_map`] or [`TempoMap::from_track`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TempoMap(pub Vec<(u32, u32)>);
+
+impl TempoMap {
+    /// The tempo when no [`Meta::SetTempo`] event has been given, in microseconds per quarter note.
+    pub const DEFAULT_TEMPO: u32 = 500_000;
+
+    /// The tempo changes of a single track. An `AlienChunk` has none.
+    pub fn from_track(track: &Track) -> Self {
+        Self(
+            AbsoluteTrack::from_delta(track)
+                .0
+                .into_iter()
+                .filter_map(|(tick, event)| match event {
+                    MidiMsg::Meta {
+                        msg: Meta::SetTempo(tempo),
+                    } => Some((tick, tempo)),
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// The tempo in effect at `tick`, in microseconds per quarter note. A tempo change takes effect at its own tick.
+    pub fn tempo_at(&self, tick: u32) -> u32 {
+        self.0
+            .iter()
+            .take_while(|(t, _)| *t <= tick)
+            .last()
+            .map(|(_, tempo)| *tempo)
+            .unwrap_or(Self::DEFAULT_TEMPO)
+    }
+
+    /// Convert an absolute tick to seconds, in a file with a [`Division::TicksPerQuarterNote`] division of
+    /// `ticks_per_quarter_note`.
+    pub fn ticks_to_seconds(&self, tick: u32, ticks_per_quarter_note: u16) -> f32 {
+        let mut micros = 0.0;
+        let mut last_tick = 0;
+        let mut tempo = Self::DEFAULT_TEMPO;
+        for (t, new_tempo) in self.0.iter().take_while(|(t, _)| *t < tick) {
+            micros += (t - last_tick) as f64 * tempo as f64;
+            last_tick = *t;
+            tempo = *new_tempo;
+        }
+        micros += (tick - last_tick) as f64 * tempo as f64;
+        (micros / ticks_per_quarter_note as f64 / 1_000_000.0) as f32
+    }
+
+    /// Like [`TempoMap::ticks_to_seconds`], but in whole microseconds. The time is accumulated exactly and rounded
+    /// down once, so it does not drift however many tempo changes come before `tick`. A `ticks_per_quarter_note` of 0
+    /// is treated as 1.
+    pub fn ticks_to_micros(&self, tick: u32, ticks_per_quarter_note: u16) -> u64 {
+        // Microseconds times ticks per quarter note
+        let mut scaled: u128 = 0;
+        let mut last_tick = 0;
+        let mut tempo = Self::DEFAULT_TEMPO;
+        for (t, new_tempo) in self.0.iter().take_while(|(t, _)| *t < tick) {
+            scaled += (t - last_tick) as u128 * tempo as u128;
+            last_tick = *t;
+            tempo = *new_tempo;
+        }
+        scaled += (tick - last_tick) as u128 * tempo as u128;
+        (scaled / ticks_per_quarter_note.max(1) as u128) as u64
+    }
+}
+
+/// An event occurring in a track in a Standard Midi File
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackEvent {
+    /// The time since the last event. The meaning of this value is determined by the file header's [`Division`].
+    pub delta_time: u32,
+    /// The actual midi event.
+    pub event: MidiMsg,
+    /// Given the file's [`Division`], the time in beats or frames at which this event occurs.
+    ///
+    /// When deserializing, this is derived from the `delta_time` and the previous event's `beat_or_frame`.
+    ///
+    /// When manually constructing `TrackEvent`s (i.e. when not using the [`MidiFile::extend_track`] convenience function), this field can set to any value, as it is not used when serializing the file.
+    pub beat_or_frame: f32,
+}
+
+impl TrackEvent {
+    /// Returns the event along with its length in bytes. The event is `None` when it is a packet of a system
+    /// exclusive message that is split across several events, other than the final packet: the reassembled
+    /// message is returned at its final packet.
+    fn from_midi(
+        v: &[u8],
+        ctx: &mut ReceiverContext,
+        division: &Division,
+        last_beat_or_frame: f32,
+        text_encoding: TextEncoding,
+    ) -> Result<(Option<Self>, usize), ParseError> {
+        let (delta_time, time_offset) = read_vlq(v)?;
+        let beat_or_frame = last_beat_or_frame + division.ticks_to_beats_or_frames(delta_time);
+        match v[time_offset..].first() {
+            Some(b) => match b >> 4 {
+                0xF => match b & 0b0000_1111 {
+                    0x0 => {
+                        let (len, len_offset) = read_vlq(&v[time_offset + 1..])?;
+                        let p = time_offset + len_offset + 1;
+                        if v.len() < p + len as usize {
+                            return Err(ParseError::UnexpectedEnd);
+                        }
+                        let mut len = len as usize;
+                        // The first packet of a split message has no End of System Exclusive flag. If the flag
+                        // directly follows the declared length, the length is assumed to be off by one instead.
+                        if !v[p..p + len].contains(&0xF7) && v.get(p + len) != Some(&0xF7) {
+                            let mut packet = vec![0xF0];
+                            packet.extend_from_slice(&v[p..p + len]);
+                            ctx.push_sysex_packet(&packet);
+                            return Ok((None, p + len));
+                        }
+                        ctx.is_smf_sysex = true;
+                        let event = match SystemExclusiveMsg::from_midi(&v[p..], ctx) {
+                            // Neither len nor event_len include the status byte
+                            Ok((event, event_len)) if event_len == len => {
+                                MidiMsg::SystemExclusive { msg: event }
+                            }
+                            Ok((_, event_len)) => {
+                                len = Self::resync_sysex_len(&v[p..], len, event_len);
+                                MidiMsg::Invalid {
+                                    bytes: v[p..p + len].to_vec(),
+                                    error: ParseError::Invalid("Invalid system exclusive message"),
+                                }
+                            }
+                            Err(e) => MidiMsg::Invalid {
+                                bytes: v[p..p + len].to_vec(),
+                                error: e,
+                            },
+                        };
+                        Ok((
+                            Some(Self {
+                                delta_time,
+                                event,
+                                beat_or_frame,
+                            }),
+                            p + len,
+                        ))
+                    }
+                    0x7 => {
+                        let (len, len_offset) = read_vlq(&v[time_offset + 1..])?;
+                        let p = time_offset + len_offset + 1;
+                        if v.len() < p + len as usize {
+                            return Err(ParseError::UnexpectedEnd);
+                        }
+                        ctx.is_smf_sysex = false;
+                        if ctx.has_pending_sysex() {
+                            let mut packet = vec![0xF7];
+                            packet.extend_from_slice(&v[p..p + len as usize]);
+                            let event = match ctx.push_sysex_packet(&packet) {
+                                Some(bytes) => match SystemExclusiveMsg::from_midi(&bytes, ctx) {
+                                    Ok((msg, event_len)) if event_len == bytes.len() => {
+                                        MidiMsg::SystemExclusive { msg }
+                                    }
+                                    Ok(_) => MidiMsg::Invalid {
+                                        bytes: bytes[1..].to_vec(),
+                                        error: ParseError::Invalid(
+                                            "Invalid system exclusive message",
+                                        ),
+                                    },
+                                    Err(e) => MidiMsg::Invalid {
+                                        bytes: bytes[1..].to_vec(),
+                                        error: e,
+                                    },
+                                },
+                                None => return Ok((None, p + len as usize)),
+                            };
+                            return Ok((
+                                Some(Self {
+                                    delta_time,
+                                    event,
+                                    beat_or_frame,
+                                }),
+                                p + len as usize,
+                            ));
+                        }
+                        let event = match MidiMsg::from_midi_with_context(&v[p..], ctx) {
+                            // len _is_ the length of the entire message
+                            Ok((event, event_len)) if event_len == len as usize => event,
+                            Ok(_) => MidiMsg::Invalid {
+                                bytes: v[p..p + len as usize].to_vec(),
+                                error: ParseError::Invalid("Invalid system exclusive message"),
+                            },
+                            Err(e) => MidiMsg::Invalid {
+                                bytes: v[p..p + len as usize].to_vec(),
+                                error: e,
+                            },
+                        };
+
+                        Ok((
+                            Some(Self {
+                                delta_time,
+                                event,
+                                beat_or_frame,
+                            }),
+                            p + len as usize,
+                        ))
+                    }
+                    0xF => {
+                        let p = time_offset + 1;
+                        let (event, event_len) =
+                            Meta::from_midi_with_encoding(&v[p..], text_encoding)?;
+                        Ok((
+                            Some(Self {
+                                delta_time,
+                                event: MidiMsg::Meta { msg: event },
+                                beat_or_frame,
+                            }),
+                            p + event_len,
+                        ))
+                    }
+                    _ => Err(ParseError::Invalid("Invalid track event")),
+                },
+                _ => {
+                    ctx.is_smf_sysex = false;
+                    let (event, event_len) =
+                        MidiMsg::from_midi_with_context(&v[time_offset..], ctx)?;
+                    Ok((
+                        Some(Self {
+                            delta_time,
+                            event,
+                            beat_or_frame,
+                        }),
+                        time_offset + event_len,
+                    ))
+                }
+            },
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
+
+    /// Files in the wild often declare a sysex length that is off by one from the position of its
+    /// End of System Exclusive flag, e.g. by counting the leading 0xF0. In that case, trust the
+    /// `parsed_len` over the `declared_len`, as long as another event can start after it.
+    /// Otherwise the `declared_len` is used.
+    fn resync_sysex_len(v: &[u8], declared_len: usize, parsed_len: usize) -> usize {
+        if declared_len.abs_diff(parsed_len) != 1 {
+            return declared_len;
+        }
+        match read_vlq(&v[parsed_len..]) {
+            Ok((_, delta_len)) if v.len() > parsed_len + delta_len => parsed_len,
+            _ => declared_len,
+        }
+    }
+
+    /// The number of bytes that `extend_midi` adds for this event. `scratch` is cleared and used to measure the
+    /// message.
+    fn serialized_len(&self, scratch: &mut Vec<u8>) -> usize {
+        // As in `extend_midi`, System Reset and invalid events are skipped
+        if matches!(
+            self.event,
+            MidiMsg::SystemRealTime {
+                msg: crate::SystemRealTimeMsg::SystemReset,
+            }
+        ) || self.event.is_invalid()
+        {
+            return 0;
+        }
+        scratch.clear();
+        self.event.extend_midi(scratch);
+        let len = scratch.len();
+        let status_len = match self.event {
+            MidiMsg::Meta { .. } => 1,
+            MidiMsg::SystemExclusive { .. }
+            | MidiMsg::SystemCommon { .. }
+            | MidiMsg::SystemRealTime { .. } => 1 + vlq_len(len as u32),
+            _ => 0,
+        };
+        vlq_len(self.delta_time) + status_len + len
+    }
+
+    /// Like `extend_midi`, but leaves out the status bytes of channel messages that are the same as the running
+    /// `status`, which is the last status byte written for a channel message, if any.
+    fn extend_midi_running(
+        &self,
+        v: &mut Vec<u8>,
+        status: &mut Option<u8>,
+        text_encoding: TextEncoding,
+    ) {
+        let event = match self.event {
+            MidiMsg::RunningChannelVoice { channel, msg } => MidiMsg::ChannelVoice { channel, msg },
+            MidiMsg::RunningChannelMode { channel, msg } => MidiMsg::ChannelMode { channel, msg },
+            MidiMsg::ChannelVoice { .. } | MidiMsg::ChannelMode { .. } => self.event.clone(),
+            _ => {
+                *status = None;
+                self.extend_midi(v, text_encoding);
+                return;
+            }
+        };
+
+        push_vlq(self.delta_time, v);
+        // A single event may be made up of several channel messages, e.g. a high resolution CC
+        for b in event.to_midi() {
+            if b > 0x7F {
+                if *status == Some(b) {
+                    continue;
+                }
+                *status = Some(b);
+            }
+            v.push(b);
+        }
+    }
+
+    fn extend_midi(&self, v: &mut Vec<u8>, text_encoding: TextEncoding) {
+        if matches!(
+            self.event,
+            MidiMsg::SystemRealTime {
+                msg: crate::SystemRealTimeMsg::SystemReset,
+            }
+        ) {
+            #[cfg(feature = "std")]
+            log::warn!("SMF contains System Reset event, which is not valid. Skipping.");
+            return;
+        } else if self.event.is_invalid() {
+            return;
+        }
+
+        push_vlq(self.delta_time, v);
+        if let MidiMsg::Meta { msg } = &self.event {
+            v.push(0xFF);
+            msg.extend_midi_with_encoding(v, text_encoding);
+            return;
+        }
+        // TODO this doesn't handle running-status events
+        let event = self.event.to_midi();
+
+        // Any kind of system event
+        let is_system = match self.event {
+            MidiMsg::SystemExclusive { .. }
+            | MidiMsg::SystemCommon { .. }
+            | MidiMsg::SystemRealTime { .. } => true,
+            _ => false,
+        };
+        if is_system {
+            // We always use the 0xF7 format for system events, since it can be used for all system events, not just system exclusive
+            v.push(0xF7);
+            push_vlq(event.len() as u32, v);
+        }
+        v.extend_from_slice(&event);
+    }
+}
+
+/// A meta event in a Standard Midi File
+#[derive(Debug, Clone, PartialEq)]
+pub enum Meta {
+    /// Must occur at the start of a track, and specifies the sequence number of the track. In a MultiSong file, this is the "pattern" number that identifies the song for cueing purposes.
+    SequenceNumber(u16),
+    /// Any text, describing anything
+    Text(String),
+    /// A copyright notice
+    Copyright(String),
+    /// The name of the track
+    TrackName(String),
+    /// The name of the instrument used in the track
+    InstrumentName(String),
+    /// A lyric. See RP-017 for guidance on the use of this meta event.
+    Lyric(String),
+    /// Normally only used in a SingleTrack file, or the first track of a MultiTrack file. Used to mark significant points in the music.
+    Marker(String),
+    /// A description of something happening at a point in time
+    CuePoint(String),
+    /// The name of the program (patch) used in the track. Defined in RP-019.
+    ProgramName(String),
+    /// The name of the device that the track is intended to be played on. Defined in RP-019.
+    DeviceName(String),
+    /// The MIDI channel that the following track events are intended for. Effective until the next event that specifies a channel.
+    ChannelPrefix(Channel),
+    /// The MIDI port, 0-127, that the following track events are intended for. This event is not part of the
+    /// Standard Midi File specification, but is written by many sequencers.
+    ///
+    /// A port meta event that is not exactly one byte long is parsed as [`Meta::Unknown`].
+    MidiPort(u8),
+    /// Marks the end of a track. This event is not optional. It must be the last event in every track.
+    EndOfTrack,
+    /// The tempo in microseconds per quarter note.
+    SetTempo(u32),
+    /// If present, the time at which the track is supposed to start. Should be present at the start of the track.
+    SmpteOffset(HighResTimeCode),
+    /// A time signature.
+    TimeSignature(FileTimeSignature),
+    /// A key signature.
+    KeySignature(KeySignature),
+    /// A chunk of data that is specific to the sequencer that created the file.
+    SequencerSpecific(Vec<u8>),
+    /// A text meta event (types 0x01-0x09, e.g. [`Meta::TrackName`]) whose bytes are not valid in the
+    /// [`TextEncoding`] it was parsed with. The bytes are kept as they are, so that they are written back unchanged.
+    /// See [`Meta::decode_raw_text`].
+    RawText { meta_type: u8, data: Vec<u8> },
+    // TODO: RP-32
+    /// Any other meta event that is not recognized
+    Unknown { meta_type: u8, data: Vec<u8> },
+}
+
+impl Meta {
+    // We do not extend with 0xFF, as this is done in TrackEvent::extend_midi
+    pub(crate) fn from_midi(v: &[u8]) -> Result<(Self, usize), ParseError> {
+        Self::from_midi_with_encoding(v, TextEncoding::Utf8)
+    }
+
+    /// The RP-026 language and display prefixes of a [`Meta::Text`] or [`Meta::Lyric`], along with the text that
+    /// follows them. `None` for any other meta event. See [`DisplayText`].
+    pub fn display_text(&self) -> Option<DisplayText> {
+        match self {
+            Meta::Text(s) | Meta::Lyric(s) => Some(DisplayText::parse(s)),
+            _ => None,
+        }
+    }
+
+    /// A [`Meta::SetTempo`] of `bpm` beats (quarter notes) per minute. The tempo is rounded to the nearest
+    /// microsecond per quarter note.
+    pub fn set_tempo_bpm(bpm: f32) -> Self {
+        Meta::SetTempo(bpm_to_tempo(bpm))
+    }
+
+    /// The tempo of a [`Meta::SetTempo`] in beats (quarter notes) per minute, or `None` for any other meta event.
+    pub fn tempo_bpm(&self) -> Option<f32> {
+        match self {
+            Meta::SetTempo(tempo) => Some(60_000_000.0 / *tempo as f32),
+            _ => None,
+        }
+    }
+
+    /// Turn a [`Meta::RawText`] into the text meta event it represents, decoding its bytes with `text_encoding`.
+    /// Invalid bytes are decoded as [`TextEncoding::decode`] does. Any other meta event is returned unchanged.
+    pub fn decode_raw_text(&self, text_encoding: TextEncoding) -> Self {
+        match self {
+            Meta::RawText { meta_type, data } => {
+                Self::from_text(*meta_type, text_encoding.decode(data))
+                    .unwrap_or_else(|| self.clone())
+            }
+            _ => self.clone(),
+        }
+    }
+
+    fn from_text(meta_type: u8, s: String) -> Option<Self> {
+        Some(match meta_type {
+            0x01 => Self::Text(s),
+            0x02 => Self::Copyright(s),
+            0x03 => Self::TrackName(s),
+            0x04 => Self::InstrumentName(s),
+            0x05 => Self::Lyric(s),
+            0x06 => Self::Marker(s),
+            0x07 => Self::CuePoint(s),
+            0x08 => Self::ProgramName(s),
+            0x09 => Self::DeviceName(s),
+            _ => return None,
+        })
+    }
+
+    fn from_midi_with_encoding(
+        v: &[u8],
+        text_encoding: TextEncoding,
+    ) -> Result<(Self, usize), ParseError> {
+        if v.len() < 2 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let meta_type = v[0];
+        let (len, len_offset) = read_vlq(&v[1..])?;
+        if v.len() < len as usize + len_offset + 1 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let end = len as usize + len_offset + 1;
+        let data = &v[len_offset + 1..end];
+        if (0x01..=0x09).contains(&meta_type) && !text_encoding.is_valid(data) {
+            return Ok((
+                Self::RawText {
+                    meta_type,
+                    data: data.to_vec(),
+                },
+                end,
+            ));
+        }
+        match meta_type {
+            0x00 => {
+                if data.len() != 2 {
+                    return Err(ParseError::Invalid(
+                        "Sequence number meta event must have exactly 2 bytes",
+                    ));
+                }
+                Ok((
+                    Self::SequenceNumber(u16::from_be_bytes([data[0], data[1]])),
+                    end,
+                ))
+            }
+            0x01..=0x09 => Ok((
+                Self::from_text(meta_type, text_encoding.decode(data)).unwrap(),
+                end,
+            )),
+            0x20 => {
+                if data.len() != 1 {
+                    return Err(ParseError::Invalid(
+                        "ChannelPrefix meta event must have exactly 1 byte",
+                    ));
+                }
+                Ok((Self::ChannelPrefix(Channel::from_u8(data[0])), end))
+            }
+            0x21 if data.len() == 1 => Ok((Self::MidiPort(data[0]), end)),
+            0x2F => Ok((Self::EndOfTrack, end)),
+            0x51 => {
+                if data.len() != 3 {
+                    return Err(ParseError::Invalid(
+                        "SetTempo meta event must have exactly 3 bytes",
+                    ));
+                }
+                Ok((
+                    Self::SetTempo(u32::from_be_bytes([0, data[0], data[1], data[2]])),
+                    end,
+                ))
+            }
+            0x54 => {
+                if data.len() != 5 {
+                    return Err(ParseError::Invalid(
+                        "SmpteOffset meta event must have exactly 5 bytes",
+                    ));
+                }
+                let (time, _) = HighResTimeCode::from_midi(data)?;
+                Ok((Self::SmpteOffset(time), end))
+            }
+            0x58 => {
+                if data.len() != 4 {
+                    return Err(ParseError::Invalid(
+                        "TimeSignature meta event must have exactly 4 bytes",
+                    ));
+                }
+                Ok((
+                    Self::TimeSignature(FileTimeSignature::from_midi(data)?),
+                    end,
+                ))
+            }
+            0x59 => {
+                if data.len() != 2 {
+                    return Err(ParseError::Invalid(
+                        "KeySignature meta event must have exactly 2 bytes",
+                    ));
+                }
+                Ok((Self::KeySignature(KeySignature::from_midi(data)?), end))
+            }
+            0x7F => Ok((Self::SequencerSpecific(data.to_vec()), end)),
+            _ => Ok((
+                Self::Unknown {
+                    meta_type,
+                    data: data.to_vec(),
+                },
+                end,
+            )),
+        }
+    }
+
+    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
+        self.extend_midi_with_encoding(v, TextEncoding::Utf8)
+    }
+
+    fn extend_midi_with_encoding(&self, v: &mut Vec<u8>, text_encoding: TextEncoding) {
+        let text = |meta_type: u8, s: &str, v: &mut Vec<u8>| {
+            v.push(meta_type);
+            let bytes = text_encoding.encode(s);
+            push_vlq(bytes.len() as u32, v);
+            v.extend_from_slice(&bytes);
+        };
+        match self {
+            Meta::SequenceNumber(n) => {
+                v.push(0x00);
+                push_vlq(2, v);
+                v.extend_from_slice(&n.to_be_bytes());
+            }
+            Meta::Text(s) => text(0x01, s, v),
+            Meta::Copyright(s) => text(0x02, s, v),
+            Meta::TrackName(s) => text(0x03, s, v),
+            Meta::InstrumentName(s) => text(0x04, s, v),
+            Meta::Lyric(s) => text(0x05, s, v),
+            Meta::Marker(s) => text(0x06, s, v),
+            Meta::CuePoint(s) => text(0x07, s, v),
+            Meta::ProgramName(s) => text(0x08, s, v),
+            Meta::DeviceName(s) => text(0x09, s, v),
+            Meta::ChannelPrefix(n) => {
+                v.push(0x20);
+                push_vlq(1, v);
+                v.push(*n as u8);
+            }
+            Meta::MidiPort(n) => {
+                v.push(0x21);
+                push_vlq(1, v);
+                v.push(*n);
+            }
+            Meta::EndOfTrack => {
+                v.push(0x2F);
+                push_vlq(0, v);
+            }
+            Meta::SetTempo(n) => {
+                v.push(0x51);
+                push_vlq(3, v);
+                v.extend_from_slice(&n.to_be_bytes()[1..]);
+            }
+            Meta::SmpteOffset(t) => {
+                v.push(0x54);
+                push_vlq(5, v);
+                t.extend_midi(v);
+            }
+            Meta::TimeSignature(s) => {
+                v.push(0x58);
+                push_vlq(4, v);
+                s.extend_midi(v);
+            }
+            Meta::KeySignature(k) => {
+                v.push(0x59);
+                push_vlq(2, v);
+                k.extend_midi(v);
+            }
+            Meta::SequencerSpecific(d) => {
+                v.push(0x7F);
+                push_vlq(d.len() as u32, v);
+                v.extend_from_slice(d);
+            }
+            Meta::RawText { meta_type, data } | Meta::Unknown { meta_type, data } => {
+                v.push(*meta_type);
+                push_vlq(data.len() as u32, v);
+                v.extend_from_slice(data);
+            }
+        }
+    }
+}
+
+/// A time signature occurring in a Standard Midi File.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileTimeSignature {
+    /// The numerator of the time signature, as it would be notated.
+    pub numerator: u8,
+    /// The denominator of the time signature, as it would be notated.
+    ///
+    /// This is tranformed from the power-of-two representation used in the file. When serialized, a denominator
+    /// that is not a power of two is rounded to the nearest one, up to a maximum of 2^15.
+    pub denominator: u16,
+    /// The number of MIDI clocks per metronome tick.
+    pub clocks_per_metronome_tick: u8,
+    /// How many 32nd notes are in a MIDI quarter note, which should usually be 8.
+    pub thirty_second_notes_per_24_clocks: u8,
+}
+
+impl FileTimeSignature {
+    /// A time signature of `numerator` over `denominator`, with the standard 24 MIDI clocks per metronome tick
+    /// and 8 32nd notes per quarter note.
+    ///
+    /// The denominator should be a power of two. When serialized, any other denominator is rounded to the
+    /// nearest power of two, and denominators above 2^15 are clamped to it.
+    pub fn simple(numerator: u8, denominator: u16) -> Self {
+        Self {
+            numerator,
+            denominator,
+            clocks_per_metronome_tick: 24,
+            thirty_second_notes_per_24_clocks: 8,
+        }
+    }
+
+    pub(crate) fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        if m.len() < 4 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        Ok(Self {
+            numerator: m[0],
+            denominator: u16::checked_pow(2, m[1] as u32).ok_or(ParseError::Invalid(
+                "Time signature denominator is too large",
+            ))?,
+            clocks_per_metronome_tick: m[2],
+            thirty_second_notes_per_24_clocks: m[3],
+        })
+    }
+
+    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
+        v.push(self.numerator);
+        v.push(((self.denominator.max(1) as f32).log2().round() as u8).min(15));
+        v.push(self.clocks_per_metronome_tick);
+        v.push(self.thirty_second_notes_per_24_clocks);
+    }
+}
+
+/// A key signature occurring in a Standard Midi File.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySignature {
+    /// Negative for number of flats, positive for number of sharps
+    pub key: i8,
+    /// 0 for major, 1 for minor
+    pub scale: u8,
+}
+
+impl KeySignature {
+    /// The name of the key, e.g. "C major", "F# minor" or "Bb major". Sharps and flats are written as `#` and `b`.
+    ///
+    /// Returns "Unknown" if there are more than 7 sharps or flats, or if the scale is neither 0 (major) nor 1 (minor).
+    pub fn name(&self) -> &'static str {
+        const MAJOR: [&str; 15] = [
+            "Cb major", "Gb major", "Db major", "Ab major", "Eb major", "Bb major", "F major",
+            "C major", "G major", "D major", "A major", "E major", "B major", "F# major",
+            "C# major",
+        ];
+        const MINOR: [&str; 15] = [
+            "Ab minor", "Eb minor", "Bb minor", "F minor", "C minor", "G minor", "D minor",
+            "A minor", "E minor", "B minor", "F# minor", "C# minor", "G# minor", "D# minor",
+            "A# minor",
+        ];
+        if !(-7..=7).contains(&self.key) {
+            return "Unknown";
+        }
+        let i = (self.key + 7) as usize;
+        match self.scale {
+            0 => MAJOR[i],
+            1 => MINOR[i],
+            _ => "Unknown",
+        }
+    }
+
+    pub(crate) fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        if m.len() < 2 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        Ok(Self {
+            key: m[0] as i8,
+            scale: m[1],
+        })
+    }
+
+    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
+        v.push(self.key as u8);
+        v.push(self.scale);
+    }
+}
+
+/// The text of a [`Meta::Text`] or [`Meta::Lyric`], split into the prefixes defined by RP-026 (SMF Language and
+/// Display Extensions) and the text to be displayed. See [`Meta::display_text`].
+///
+/// RP-026 prefixes are enclosed in braces at the start of the text: `{@...}` tags the language (and character set) of
+/// the text that follows, e.g. `{@LATIN}` or `{@JP}`, and `{#...}` gives display information, e.g. `{#Title=...}`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DisplayText {
+    /// The language tag of the last `{@...}` prefix, without its braces or `@`, e.g. `LATIN`.
+    pub language: Option<String>,
+    /// The `{#...}` display prefixes, in order, split at their first `=` into a name and a value. A prefix without
+    /// an `=` has an empty value.
+    pub display: Vec<(String, String)>,
+    /// The text that follows the prefixes.
+    pub text: String,
+}
+
+impl DisplayText {
+    fn parse(s: &str) -> Self {
+        let mut display_text = Self::default();
+        let mut rest = s;
+        while let Some(end) = rest.find('}') {
+            let prefix = &rest[..end];
+            if let Some(language) = prefix.strip_prefix("{@") {
+                display_text.language = Some(language.to_string());
+            } else if let Some(display) = prefix.strip_prefix("{#") {
+                let (name, value) = display.split_once('=').unwrap_or((display, ""));
+                display_text
+                    .display
+                    .push((name.to_string(), value.to_string()));
+            } else {
+                break;
+            }
+            rest = &rest[end + 1..];
+        }
+        display_text.text = rest.to_string();
+        display_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ControlChange, DeviceID, GeneralMidi, UniversalNonRealTimeMsg};
+
+    #[test]
+    fn test_file_time_signature() {
+        let midi_data = vec![4, 2, 24, 8];
+        let time_sig = FileTimeSignature::from_midi(&midi_data).unwrap();
+
+        assert_eq!(time_sig.numerator, 4);
+        assert_eq!(time_sig.denominator, 4);
+        assert_eq!(time_sig.clocks_per_metronome_tick, 24);
+        assert_eq!(time_sig.thirty_second_notes_per_24_clocks, 8);
+
+        let mut output = Vec::new();
+        time_sig.extend_midi(&mut output);
+        assert_eq!(output, midi_data);
+    }
+
+    #[test]
+    fn test_simple_time_signature() {
+        let mut output = Vec::new();
+        FileTimeSignature::simple(6, 8).extend_midi(&mut output);
+        assert_eq!(output, vec![6, 3, 24, 8]);
+
+        // Denominators that aren't powers of two are rounded
+        let mut output = Vec::new();
+        FileTimeSignature::simple(3, 7).extend_midi(&mut output);
+        assert_eq!(output[1], 3);
+        let mut output = Vec::new();
+        FileTimeSignature::simple(3, 5).extend_midi(&mut output);
+        assert_eq!(output[1], 2);
+
+        // Denominators that would round past 2^15 are clamped, so they can be read back
+        let mut output = Vec::new();
+        FileTimeSignature::simple(4, 60000).extend_midi(&mut output);
+        assert_eq!(output[1], 15);
+        assert_eq!(
+            FileTimeSignature::from_midi(&output).unwrap().denominator,
+            32768
+        );
+    }
+
+    #[test]
+    fn test_file_time_signature_error() {
+        let midi_data = vec![4, 2, 24];
+        assert!(matches!(
+            FileTimeSignature::from_midi(&midi_data),
+            Err(ParseError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            FileTimeSignature::from_midi(&[4, 16, 24, 8]),
+            Err(ParseError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_signature() {
+        let midi_data = vec![2, 0];
+        let key_sig = KeySignature::from_midi(&midi_data).unwrap();
+
+        assert_eq!(key_sig.key, 2);
+        assert_eq!(key_sig.scale, 0);
+
+        let mut output = Vec::new();
+        key_sig.extend_midi(&mut output);
+        assert_eq!(output, midi_data);
+    }
+
+    #[test]
+    fn test_key_signature_name() {
+        let name = |key, scale| KeySignature { key, scale }.name();
+        assert_eq!(name(0, 0), "C major");
+        assert_eq!(name(0, 1), "A minor");
+        assert_eq!(name(3, 1), "F# minor");
+        assert_eq!(name(-2, 0), "Bb major");
+        assert_eq!(name(-7, 0), "Cb major");
+        assert_eq!(name(7, 1), "A# minor");
+        assert_eq!(name(8, 0), "Unknown");
+        assert_eq!(name(0, 2), "Unknown");
+    }
+
+    #[test]
+    fn test_key_signature_error() {
+        let midi_data = vec![2];
+        assert!(matches!(
+            KeySignature::from_midi(&midi_data),
+            Err(ParseError::UnexpectedEnd)
+        ));
+    }
+
+    #[test]
+    fn test_midi_port() {
+        let midi_data = vec![0x21, 1, 3];
+        let (meta, len) = Meta::from_midi(&midi_data).unwrap();
+        assert_eq!(meta, Meta::MidiPort(3));
+        assert_eq!(len, 3);
+
+        let mut output = Vec::new();
+        meta.extend_midi(&mut output);
+        assert_eq!(output, midi_data);
+
+        // A malformed port event is kept as-is
+        let (meta, _) = Meta::from_midi(&[0x21, 2, 3, 4]).unwrap();
+        assert_eq!(
+            meta,
+            Meta::Unknown {
+                meta_type: 0x21,
+                data: vec![3, 4]
+            }
+        );
+    }
+
+    #[test]
+    fn test_rp019_names() {
+        let midi_data = vec![0x08, 5, b'P', b'i', b'a', b'n', b'o'];
+        let (meta, _) = Meta::from_midi(&midi_data).unwrap();
+        assert_eq!(meta, Meta::ProgramName("Piano".to_string()));
+        let mut output = Vec::new();
+        meta.extend_midi(&mut output);
+        assert_eq!(output, midi_data);
+
+        let midi_data = vec![0x09, 3, b'M', b'T', 0xE9];
+        let (meta, _) = Meta::from_midi_with_encoding(&midi_data, TextEncoding::Latin1).unwrap();
+        assert_eq!(meta, Meta::DeviceName("MTé".to_string()));
+        let mut output = Vec::new();
+        meta.extend_midi_with_encoding(&mut output, TextEncoding::Latin1);
+        assert_eq!(output, midi_data);
+    }
+
+    #[test]
+    fn test_tempo_bpm() {
+        assert_eq!(Meta::set_tempo_bpm(120.0), Meta::SetTempo(500_000));
+        // Rounded, not truncated
+        assert_eq!(Meta::set_tempo_bpm(140.0), Meta::SetTempo(428_571));
+        assert_eq!(Meta::set_tempo_bpm(70.0), Meta::SetTempo(857_143));
+        assert_eq!(Meta::set_tempo_bpm(120.0).tempo_bpm(), Some(120.0));
+        assert_eq!(Meta::EndOfTrack.tempo_bpm(), None);
+    }
+
+    #[test]
+    fn test_display_text() {
+        let lyric = Meta::Lyric("{@LATIN}{#Title=Café}{#Karaoke}Hel".to_string());
+        assert_eq!(
+            lyric.display_text(),
+            Some(DisplayText {
+                language: Some("LATIN".to_string()),
+                display: vec![
+                    ("Title".to_string(), "Café".to_string()),
+                    ("Karaoke".to_string(), String::new()),
+                ],
+                text: "Hel".to_string(),
+            })
+        );
+
+        // Braces that aren't RP-026 prefixes are part of the text
+        let text = Meta::Text("{x}{@JP}lo".to_string()).display_text().unwrap();
+        assert_eq!(text.language, None);
+        assert_eq!(text.text, "{x}{@JP}lo");
+
+        assert_eq!(Meta::TrackName("{@JP}".to_string()).display_text(), None);
+    }
+
+    #[test]
+    fn test_file_serde() {
+        use crate::message::MidiMsg;
+        use crate::Channel;
+        use crate::ChannelVoiceMsg;
+
+        // Create a simple MIDI file
+        let mut file = MidiFile::default();
+        // Set the division
+        file.header.division = Division::TicksPerQuarterNote(480);
+
+        file.add_track(Track::default());
+
+        // Add some events to the track
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::TrackName("Test Track".to_string()),
+            },
+            0.0,
+        );
+
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::TimeSignature(FileTimeSignature {
+                    numerator: 4,
+                    denominator: 4,
+                    clocks_per_metronome_tick: 24,
+                    thirty_second_notes_per_24_clocks: 8,
+                }),
+            },
+            0.0,
+        );
+
+        file.extend_track(
+            0,
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch1,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 60,
+                    velocity: 64,
+                },
+            },
+            0.0,
+        );
+
+        file.extend_track(
+            0,
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch1,
+                msg: ChannelVoiceMsg::NoteOff {
+                    note: 60,
+                    velocity: 64,
+                },
+            },
+            1.0,
+        );
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+            2.0,
+        );
+
+        // Convert the file to bytes
+        let bytes = file.to_midi();
+
+        // Assert that we've created a valid MIDI file
+        assert!(bytes.starts_with(b"MThd"));
+        assert!(bytes[14..].starts_with(b"MTrk"));
+
+        let deserialized_file = MidiFile::from_midi(&bytes).unwrap();
+        assert_eq!(deserialized_file.tracks.len(), 1);
+        assert_eq!(deserialized_file.tracks[0].events().len(), 5);
+        assert_eq!(
+            deserialized_file.header.division,
+            Division::TicksPerQuarterNote(480)
+        );
+        assert_eq!(deserialized_file, file);
+    }
+
+    #[test]
+    fn test_file_system_reset() {
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.extend_track(
+            0,
+            MidiMsg::SystemRealTime {
+                msg: crate::SystemRealTimeMsg::SystemReset,
+            },
+            0.0,
+        );
+        let bytes = file.to_midi();
+
+        let deserialized_file = MidiFile::from_midi(&bytes).unwrap();
+        assert_eq!(deserialized_file.tracks.len(), 1);
+        // The system reset message should not be included in the track, since it is not a valid MIDI file message
+        assert_eq!(deserialized_file.tracks[0].events().len(), 0);
+    }
+
+    #[test]
+    fn test_chords() {
+        fn note_on(channel: Channel, note: u8, delta_time: u32) -> TrackEvent {
+            TrackEvent {
+                delta_time,
+                event: MidiMsg::ChannelVoice {
+                    channel,
+                    msg: ChannelVoiceMsg::NoteOn { note, velocity: 64 },
+                },
+                beat_or_frame: 0.0,
+            }
+        }
+
+        let track = Track::Midi(vec![
+            // Three near-simultaneous notes
+            note_on(Channel::Ch1, 60, 96),
+            note_on(Channel::Ch1, 64, 2),
+            note_on(Channel::Ch1, 67, 4),
+            // Same time, but a different channel
+            note_on(Channel::Ch2, 48, 0),
+            // Too late to be part of the chord
+            note_on(Channel::Ch1, 72, 90),
+        ]);
+
+        assert_eq!(track.chords(10), vec![(98, vec![60, 64, 67])]);
+        assert_eq!(track.chords(0), vec![]);
+    }
+
+    #[test]
+    fn test_smpte_offset() {
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.add_track(Track::default());
+        assert_eq!(file.smpte_offset(), None);
+        assert_eq!(file.start_seconds(), 0.0);
+
+        let offset = HighResTimeCode {
+            fractional_frames: 50,
+            frames: 12,
+            seconds: 3,
+            minutes: 2,
+            hours: 1,
+            code_type: TimeCodeType::FPS25,
+        };
+        file.extend_track(
+            1,
+            MidiMsg::Meta {
+                msg: Meta::SmpteOffset(offset),
+            },
+            0.0,
+        );
+        let file = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(file.smpte_offset(), Some(offset));
+        assert_eq!(file.start_seconds(), 3600.0 + 120.0 + 3.0 + 12.5 / 25.0);
+    }
+
+    #[test]
+    fn test_add_tempo_ramp() {
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        let note = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        file.extend_track(0, note.clone(), 0.0);
+        file.extend_track(0, note.clone(), 2.5);
+        file.extend_track(0, note.clone(), 8.0);
+        file.add_tempo_ramp(0, 0.0, 4.0, 120.0, 80.0, 5);
+
+        let events = file.tracks[0].events();
+        assert_eq!(events.len(), 8);
+        let tempos: Vec<(f32, u32)> = events
+            .iter()
+            .filter_map(|e| match e.event {
+                MidiMsg::Meta {
+                    msg: Meta::SetTempo(t),
+                } => Some((e.beat_or_frame, t)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            tempos,
+            vec![
+                (0.0, 500000),
+                (1.0, 545455),
+                (2.0, 600000),
+                (3.0, 666667),
+                (4.0, 750000)
+            ]
+        );
+        // Existing events keep their timing
+        let deltas: Vec<u32> = events.iter().map(|e| e.delta_time).collect();
+        assert_eq!(deltas, vec![0, 0, 96, 96, 48, 48, 96, 384]);
+        assert_eq!(events[1].event, note);
+        assert_eq!(events[4].event, note);
+        assert_eq!(events[7].event, note);
+
+        // Round trip
+        let deserialized = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(deserialized.tracks[0].events()[7].beat_or_frame, 8.0);
+    }
+
+    #[test]
+    fn test_set_tempo_bpm() {
+        let note = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.extend_track(0, note.clone(), 0.0);
+        file.extend_track(0, note.clone(), 4.0);
+        assert_eq!(file.tempo_at_beat(0.0), 120.0);
+
+        file.set_tempo_bpm(0, 2.0, 90.0);
+        file.set_tempo_bpm(0, 0.0, 100.0);
+        let events: Vec<(u32, MidiMsg)> = file.tracks[0]
+            .events()
+            .iter()
+            .map(|e| (e.delta_time, e.event.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    0,
+                    MidiMsg::Meta {
+                        msg: Meta::SetTempo(600_000)
+                    }
+                ),
+                (0, note.clone()),
+                (
+                    192,
+                    MidiMsg::Meta {
+                        msg: Meta::SetTempo(666_667)
+                    }
+                ),
+                (192, note),
+            ]
+        );
+
+        assert_eq!(file.tempo_at_beat(0.0), 100.0);
+        assert_eq!(file.tempo_at_beat(1.99), 100.0);
+        assert!((file.tempo_at_beat(2.0) - 90.0).abs() < 0.001);
+        assert!((file.tempo_at_beat(10.0) - 90.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_sort_track() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.add_track(Track::AlienChunk(vec![1, 2, 3]));
+        file.extend_track(0, note(60), 1.0);
+        file.extend_track(0, end.clone(), 1.5);
+        file.extend_track(0, note(62), 0.5);
+        file.extend_track(0, note(64), 2.0);
+        file.extend_track(0, note(65), 0.5);
+        file.sort_track(0);
+        file.sort_track(1);
+
+        let events: Vec<(u32, f32, MidiMsg)> = file.tracks[0]
+            .events()
+            .iter()
+            .map(|e| (e.delta_time, e.beat_or_frame, e.event.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (48, 0.5, note(62)),
+                (0, 0.5, note(65)),
+                (48, 1.0, note(60)),
+                (96, 2.0, note(64)),
+                (0, 2.0, end),
+            ]
+        );
+        assert_eq!(file.tracks[1], Track::AlienChunk(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_tempo_map() {
+        let tempo = |t| MidiMsg::Meta {
+            msg: Meta::SetTempo(t),
+        };
+        let note = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        let mut file = MidiFile::default();
+        file.header.format = SMFFormat::MultiTrack;
+        file.add_track(Track::default());
+        file.add_track(Track::default());
+        file.extend_track(0, tempo(1_000_000), 2.0);
+        file.extend_track(0, tempo(250_000), 3.0);
+        file.extend_track(1, note.clone(), 0.0);
+        file.extend_track(1, note, 6.0);
+
+        let map = file.tempo_map();
+        assert_eq!(map, TempoMap(vec![(192, 1_000_000), (288, 250_000)]));
+        assert_eq!(map.tempo_at(0), TempoMap::DEFAULT_TEMPO);
+        assert_eq!(map.tempo_at(192), 1_000_000);
+        assert_eq!(map.tempo_at(1000), 250_000);
+
+        // 2 beats at 120 BPM, 1 at 60 BPM, and 3 at 240 BPM
+        assert_eq!(file.tick_to_seconds(1, 0), 0.0);
+        assert_eq!(file.tick_to_seconds(1, 192), 1.0);
+        assert_eq!(file.tick_to_seconds(1, 240), 1.5);
+        assert_eq!(file.tick_to_seconds(1, 576), 2.75);
+
+        // The tracks of a multi-song file each have their own tempo
+        file.header.format = SMFFormat::MultiSong;
+        assert_eq!(file.tick_to_seconds(0, 576), 2.75);
+        assert_eq!(file.tick_to_seconds(1, 576), 3.0);
+
+        // Time code files ignore tempo changes
+        file.header.division = Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 40,
+        };
+        assert_eq!(file.tick_to_seconds(0, 1000), 1.0);
+    }
+
+    #[test]
+    fn test_to_timestamped() {
+        let tempo = |t| MidiMsg::Meta {
+            msg: Meta::SetTempo(t),
+        };
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.add_track(Track::default());
+        file.extend_track(0, tempo(1_000_000), 2.0);
+        file.extend_track(0, tempo(333_333), 3.0);
+        file.extend_track(1, note(60), 0.0);
+        file.extend_track(1, note(62), 2.0);
+        file.extend_track(1, note(64), 2.5);
+        file.extend_track(1, note(65), 3000.0);
+
+        // 2 beats at 120 BPM, 1 at 60 BPM, then 333333 microseconds per beat
+        assert_eq!(
+            file.to_timestamped(),
+            vec![
+                (0, note(60)),
+                (1_000_000, tempo(1_000_000)),
+                (1_000_000, note(62)),
+                (1_500_000, note(64)),
+                (2_000_000, tempo(333_333)),
+                (2_000_000 + 2997 * 333_333, note(65)),
+            ]
+        );
+
+        file.header.division = Division::TimeCode {
+            frames_per_second: TimeCodeType::DF30,
+            ticks_per_frame: 4,
+        };
+        assert_eq!(file.header.division.ticks_to_micros(120), Some(1_001_000));
+
+        // A division of 0 ticks doesn't panic
+        let mut file = MidiFile::default();
+        file.header.division = Division::TicksPerQuarterNote(0);
+        file.add_track(Track::Midi(vec![TrackEvent {
+            delta_time: 2,
+            event: note(60),
+            beat_or_frame: 0.0,
+        }]));
+        let file = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(file.header.division, Division::TicksPerQuarterNote(0));
+        assert_eq!(file.to_timestamped(), vec![(1_000_000, note(60))]);
+        let division = Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 0,
+        };
+        assert_eq!(division.ticks_to_micros(25), Some(1_000_000));
+    }
+
+    #[test]
+    fn test_duration() {
+        let tempo = |t| MidiMsg::Meta {
+            msg: Meta::SetTempo(t),
+        };
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut file = MidiFile::default();
+        assert_eq!(file.duration_ticks(), 0);
+        assert_eq!(file.duration_seconds(), 0.0);
+
+        file.header.format = SMFFormat::MultiTrack;
+        file.add_track(Track::default());
+        file.add_track(Track::default());
+        file.add_track(Track::AlienChunk(vec![1, 2, 3]));
+        file.extend_track(0, tempo(1_000_000), 2.0);
+        file.extend_track(0, end.clone(), 3.0);
+        file.extend_track(1, end, 4.0);
+
+        assert_eq!(file.tracks[0].duration_ticks(), 288);
+        assert_eq!(file.duration_ticks(), 384);
+        assert_eq!(file.duration_beats(), 4.0);
+        // 2 beats at 120 BPM, then 2 at 60 BPM
+        assert_eq!(file.duration_seconds(), 3.0);
+
+        // Each song has its own tempo
+        file.header.format = SMFFormat::MultiSong;
+        assert_eq!(file.duration_seconds(), 2.0);
+
+        file.header.division = Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 4,
+        };
+        assert_eq!(file.duration_beats(), 96.0);
+        assert_eq!(file.duration_seconds(), 3.84);
+
+        // Delta times that sum past u32::MAX saturate rather than overflow
+        let long = |delta_time| TrackEvent {
+            delta_time,
+            event: MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+            beat_or_frame: 0.0,
+        };
+        let track = Track::Midi(vec![long(u32::MAX - 1), long(2)]);
+        assert_eq!(track.duration_ticks(), u32::MAX);
     }
 
-    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
-        match self {
-            Meta::SequenceNumber(n) => {
-                v.push(0x00);
-                push_vlq(2, v);
-                v.extend_from_slice(&n.to_be_bytes());
-            }
-            Meta::Text(s) => {
-                v.push(0x01);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::Copyright(s) => {
-                v.push(0x02);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::TrackName(s) => {
-                v.push(0x03);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::InstrumentName(s) => {
-                v.push(0x04);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::Lyric(s) => {
-                v.push(0x05);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::Marker(s) => {
-                v.push(0x06);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::CuePoint(s) => {
-                v.push(0x07);
-                let bytes = s.as_bytes();
-                push_vlq(bytes.len() as u32, v);
-                v.extend_from_slice(bytes);
-            }
-            Meta::ChannelPrefix(n) => {
-                v.push(0x20);
-                push_vlq(1, v);
-                v.push(*n as u8);
-            }
-            Meta::EndOfTrack => {
-                v.push(0x2F);
-                push_vlq(0, v);
-            }
-            Meta::SetTempo(n) => {
-                v.push(0x51);
-                push_vlq(3, v);
-                v.extend_from_slice(&n.to_be_bytes()[1..]);
-            }
-            Meta::SmpteOffset(t) => {
-                v.push(0x54);
-                push_vlq(5, v);
-                t.extend_midi(v);
-            }
-            Meta::TimeSignature(s) => {
-                v.push(0x58);
-                push_vlq(4, v);
-                s.extend_midi(v);
-            }
-            Meta::KeySignature(k) => {
-                v.push(0x59);
-                push_vlq(2, v);
-                k.extend_midi(v);
-            }
-            Meta::SequencerSpecific(d) => {
-                v.push(0x7F);
-                push_vlq(d.len() as u32, v);
-                v.extend_from_slice(d);
-            }
-            Meta::Unknown { meta_type, data } => {
-                v.push(*meta_type);
-                push_vlq(data.len() as u32, v);
-                v.extend_from_slice(data);
+    #[test]
+    fn test_merge_tracks() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut file = MidiFile::default();
+        file.header.format = SMFFormat::MultiTrack;
+        file.add_track(Track::default());
+        file.add_track(Track::AlienChunk(vec![1, 2, 3]));
+        file.add_track(Track::default());
+        file.extend_track(0, note(60), 0.0);
+        file.extend_track(0, note(62), 1.0);
+        file.extend_track(0, end.clone(), 1.0);
+        file.extend_track(2, note(72), 0.5);
+        file.extend_track(2, note(74), 1.0);
+        file.extend_track(2, end.clone(), 2.0);
+
+        let merged: Vec<(u32, f32, MidiMsg)> = file
+            .merge_tracks()
+            .into_iter()
+            .map(|e| (e.delta_time, e.beat_or_frame, e.event))
+            .collect();
+        assert_eq!(
+            merged,
+            vec![
+                (0, 0.0, note(60)),
+                (48, 0.5, note(72)),
+                (48, 1.0, note(62)),
+                (0, 1.0, note(74)),
+                (96, 2.0, end),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_single_track() {
+        let note = |channel, note| MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let tempo = MidiMsg::Meta {
+            msg: Meta::SetTempo(400_000),
+        };
+        let time_signature = MidiMsg::Meta {
+            msg: Meta::TimeSignature(FileTimeSignature {
+                numerator: 3,
+                denominator: 4,
+                clocks_per_metronome_tick: 24,
+                thirty_second_notes_per_24_clocks: 8,
+            }),
+        };
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut file = MidiFile::default();
+        file.header.format = SMFFormat::MultiTrack;
+        file.header.division = Division::TicksPerQuarterNote(480);
+        file.add_track(Track::default());
+        file.add_track(Track::default());
+        file.add_track(Track::AlienChunk(vec![1, 2, 3]));
+        file.extend_track(1, note(Channel::Ch2, 62), 0.0);
+        file.extend_track(1, end.clone(), 1.0);
+        file.extend_track(0, tempo.clone(), 0.0);
+        file.extend_track(0, time_signature.clone(), 0.0);
+        file.extend_track(0, note(Channel::Ch1, 60), 0.0);
+        file.extend_track(0, end.clone(), 2.0);
+
+        let single = file.to_single_track();
+        assert_eq!(single.header.format, SMFFormat::SingleTrack);
+        assert_eq!(single.header.num_tracks, 1);
+        assert_eq!(single.header.division, file.header.division);
+        assert_eq!(single.tracks.len(), 1);
+        let events: Vec<(u32, MidiMsg)> = single.tracks[0]
+            .events()
+            .iter()
+            .map(|e| (e.delta_time, e.event.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (0, tempo),
+                (0, time_signature),
+                (0, note(Channel::Ch1, 60)),
+                (0, note(Channel::Ch2, 62)),
+                (960, end),
+            ]
+        );
+
+        // Round trip
+        assert_eq!(MidiFile::from_midi(&single.to_midi()).unwrap(), single);
+    }
+
+    #[test]
+    fn test_split_by_channel() {
+        let note = |channel, note| MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let tempo = MidiMsg::Meta {
+            msg: Meta::SetTempo(400_000),
+        };
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM1),
+            },
+        };
+        let all_notes_off = MidiMsg::ChannelMode {
+            channel: Channel::Ch10,
+            msg: ChannelModeMsg::AllNotesOff,
+        };
+        let end = MidiMsg::Meta {
+            msg: Meta::EndOfTrack,
+        };
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.extend_track(0, tempo.clone(), 0.0);
+        file.extend_track(0, sysex.clone(), 0.0);
+        file.extend_track(0, note(Channel::Ch10, 36), 0.0);
+        file.extend_track(0, note(Channel::Ch2, 60), 0.5);
+        file.extend_track(0, all_notes_off.clone(), 1.0);
+        file.extend_track(0, note(Channel::Ch2, 62), 1.5);
+        file.extend_track(0, end.clone(), 2.0);
+
+        let split = file.split_by_channel();
+        assert_eq!(split.header.format, SMFFormat::MultiTrack);
+        assert_eq!(split.header.num_tracks, 3);
+        let events = |track: &Track| -> Vec<(u32, MidiMsg)> {
+            track
+                .events()
+                .iter()
+                .map(|e| (e.delta_time, e.event.clone()))
+                .collect()
+        };
+        assert_eq!(
+            events(&split.tracks[0]),
+            vec![(0, tempo), (0, sysex), (192, end.clone())]
+        );
+        assert_eq!(
+            events(&split.tracks[1]),
+            vec![
+                (48, note(Channel::Ch2, 60)),
+                (96, note(Channel::Ch2, 62)),
+                (48, end.clone())
+            ]
+        );
+        assert_eq!(
+            events(&split.tracks[2]),
+            vec![(0, note(Channel::Ch10, 36)), (96, all_notes_off), (96, end)]
+        );
+
+        // Merging the tracks again gives back the same events
+        assert_eq!(split.to_single_track().tracks[0], file.tracks[0]);
+    }
+
+    #[test]
+    fn test_add_metronome_track() {
+        let mut file = MidiFile::default();
+        file.header.division = Division::TicksPerQuarterNote(96);
+        file.add_metronome_track(
+            Channel::Ch10,
+            76,
+            1,
+            FileTimeSignature {
+                numerator: 4,
+                denominator: 4,
+                clocks_per_metronome_tick: 24,
+                thirty_second_notes_per_24_clocks: 8,
+            },
+        );
+        assert_eq!(file.header.num_tracks, 1);
+
+        let clicks: Vec<(f32, u8)> = file.tracks[0]
+            .events()
+            .iter()
+            .filter_map(|e| match e.event {
+                MidiMsg::ChannelVoice {
+                    channel: Channel::Ch10,
+                    msg: ChannelVoiceMsg::NoteOn { note: 76, velocity },
+                } => Some((e.beat_or_frame, velocity)),
+                _ => None,
+            })
+            .collect();
+        // The downbeat is accented
+        assert_eq!(clicks, vec![(0.0, 127), (1.0, 80), (2.0, 80), (3.0, 80)]);
+
+        let last = file.tracks[0].events().last().unwrap();
+        assert_eq!(
+            last.event,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack
             }
-        }
+        );
+        assert_eq!(last.beat_or_frame, 4.0);
     }
-}
 
-/// A time signature occurring in a Standard Midi File.
-#[derive(Debug, Clone, PartialEq)]
-pub struct FileTimeSignature {
-    /// The numerator of the time signature, as it would be notated.
-    pub numerator: u8,
-    /// The denominator of the time signature, as it would be notated.
-    ///
-    /// This is tranformed from the power-of-two representation used in the file.
-    pub denominator: u16,
-    /// The number of MIDI clocks per metronome tick.
-    pub clocks_per_metronome_tick: u8,
-    /// How many 32nd notes are in a MIDI quarter note, which should usually be 8.
-    pub thirty_second_notes_per_24_clocks: u8,
-}
+    #[test]
+    fn test_performance_only() {
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.add_track(Track::AlienChunk(vec![b'X', b'Y', b'Z', b'W', 0, 0, 0, 0]));
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let text = MidiMsg::Meta {
+            msg: Meta::Text("Hello".to_string()),
+        };
+        let tempo = MidiMsg::Meta {
+            msg: Meta::SetTempo(500000),
+        };
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::Commercial {
+                id: 0x01.into(),
+                data: vec![1, 2, 3],
+            },
+        };
+        file.extend_track(0, text.clone(), 0.0);
+        file.extend_track(0, tempo.clone(), 0.5);
+        file.extend_track(0, note(60), 1.0);
+        file.extend_track(0, sysex, 1.5);
+        file.extend_track(0, text, 2.0);
+        file.extend_track(0, note(64), 3.0);
+        file.extend_track(
+            0,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack,
+            },
+            4.0,
+        );
 
-impl FileTimeSignature {
-    pub(crate) fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
-        if m.len() < 4 {
-            return Err(ParseError::UnexpectedEnd);
-        }
-        Ok(Self {
-            numerator: m[0],
-            denominator: u16::pow(2, m[1] as u32),
-            clocks_per_metronome_tick: m[2],
-            thirty_second_notes_per_24_clocks: m[3],
-        })
+        let performance = file.performance_only();
+        assert_eq!(performance.header.num_tracks, 1);
+        assert_eq!(performance.tracks.len(), 1);
+        let events = performance.tracks[0].events();
+        let msgs: Vec<&MidiMsg> = events.iter().map(|e| &e.event).collect();
+        assert_eq!(
+            msgs,
+            vec![
+                &tempo,
+                &note(60),
+                &note(64),
+                &MidiMsg::Meta {
+                    msg: Meta::EndOfTrack
+                }
+            ]
+        );
+        let deltas: Vec<u32> = events.iter().map(|e| e.delta_time).collect();
+        assert_eq!(deltas, vec![48, 48, 192, 96]);
+
+        let deserialized = MidiFile::from_midi(&performance.to_midi()).unwrap();
+        let beats: Vec<f32> = deserialized.tracks[0]
+            .events()
+            .iter()
+            .map(|e| e.beat_or_frame)
+            .collect();
+        assert_eq!(beats, vec![0.5, 1.0, 3.0, 4.0]);
     }
 
-    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
-        v.push(self.numerator);
-        v.push((self.denominator as f32).log2() as u8);
-        v.push(self.clocks_per_metronome_tick);
-        v.push(self.thirty_second_notes_per_24_clocks);
+    #[test]
+    fn test_longest_gap() {
+        let mut track = Track::default();
+        let mut last_tick = 0;
+        let mut add = |tick: u32, msg: ChannelVoiceMsg| {
+            track.extend(TrackEvent {
+                delta_time: tick - last_tick,
+                event: MidiMsg::ChannelVoice {
+                    channel: Channel::Ch1,
+                    msg,
+                },
+                beat_or_frame: 0.0,
+            });
+            last_tick = tick;
+        };
+        let on = |note| ChannelVoiceMsg::NoteOn {
+            note,
+            velocity: 100,
+        };
+        let off = |note| ChannelVoiceMsg::NoteOn { note, velocity: 0 };
+
+        add(10, on(60));
+        add(20, on(60));
+        add(96, off(60));
+        // The note is still sounding
+        add(100, off(60));
+        // Doesn't count as activity
+        add(
+            200,
+            ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC {
+                    control: 7,
+                    value: 100,
+                },
+            },
+        );
+        add(480, on(64));
+        add(500, on(67));
+        add(
+            576,
+            ChannelVoiceMsg::NoteOff {
+                note: 64,
+                velocity: 0,
+            },
+        );
+        add(700, off(67));
+        add(
+            800,
+            ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC {
+                    control: 7,
+                    value: 50,
+                },
+            },
+        );
+        assert_eq!(track.longest_gap(), Some((100, 380)));
+
+        assert_eq!(Track::default().longest_gap(), None);
     }
-}
 
-/// A key signature occurring in a Standard Midi File.
-#[derive(Debug, Clone, PartialEq)]
-pub struct KeySignature {
-    /// Negative for number of flats, positive for number of sharps
-    pub key: i8,
-    /// 0 for major, 1 for minor
-    pub scale: u8,
-}
+    #[test]
+    fn test_overlapping_notes() {
+        let mut track = Track::default();
+        let mut last_tick = 0;
+        let mut add = |tick: u32, channel: Channel, msg: ChannelVoiceMsg| {
+            track.extend(TrackEvent {
+                delta_time: tick - last_tick,
+                event: MidiMsg::ChannelVoice { channel, msg },
+                beat_or_frame: 0.0,
+            });
+            last_tick = tick;
+        };
+        let on = |note| ChannelVoiceMsg::NoteOn {
+            note,
+            velocity: 100,
+        };
+        let off = |note| ChannelVoiceMsg::NoteOff { note, velocity: 0 };
 
-impl KeySignature {
-    pub(crate) fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
-        if m.len() < 2 {
-            return Err(ParseError::UnexpectedEnd);
-        }
-        Ok(Self {
-            key: m[0] as i8,
-            scale: m[1],
-        })
+        add(0, Channel::Ch1, on(60));
+        // The same note on another channel isn't a retrigger
+        add(10, Channel::Ch2, on(60));
+        // Retriggered without a note off
+        add(20, Channel::Ch1, on(60));
+        add(30, Channel::Ch1, off(60));
+        // Still sounding from the retrigger
+        add(40, Channel::Ch1, on(60));
+        add(50, Channel::Ch1, off(60));
+        add(50, Channel::Ch1, off(60));
+        // Properly ended
+        add(60, Channel::Ch1, on(60));
+        add(70, Channel::Ch1, off(60));
+
+        assert_eq!(
+            track.overlapping_notes(),
+            vec![(Channel::Ch1, 60, 20), (Channel::Ch1, 60, 40)]
+        );
+        assert_eq!(Track::default().overlapping_notes(), vec![]);
     }
 
-    pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
-        v.push(self.key as u8);
-        v.push(self.scale);
+    #[test]
+    fn test_note_event() {
+        let voice = |msg| MidiMsg::RunningChannelVoice {
+            channel: Channel::Ch2,
+            msg,
+        };
+        assert_eq!(
+            note_event(&voice(ChannelVoiceMsg::HighResNoteOn {
+                note: 60,
+                velocity: 1000
+            })),
+            Some(NoteEvent::On {
+                channel: Channel::Ch2,
+                note: 60,
+                velocity: 1000,
+                high_res: true
+            })
+        );
+        assert_eq!(
+            note_event(&voice(ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 0
+            })),
+            Some(NoteEvent::Off {
+                channel: Channel::Ch2,
+                note: 60
+            })
+        );
+        assert_eq!(
+            note_event(&voice(ChannelVoiceMsg::HighResNoteOff {
+                note: 60,
+                velocity: 1000
+            })),
+            Some(NoteEvent::Off {
+                channel: Channel::Ch2,
+                note: 60
+            })
+        );
+        assert_eq!(
+            note_event(&MidiMsg::ChannelMode {
+                channel: Channel::Ch2,
+                msg: ChannelModeMsg::AllSoundOff
+            }),
+            Some(NoteEvent::AllOff {
+                channel: Channel::Ch2
+            })
+        );
+        assert_eq!(
+            note_event(&voice(ChannelVoiceMsg::ProgramChange { program: 1 })),
+            None
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_note_pairs() {
+        let mut track = Track::default();
+        let mut last_tick = 0;
+        let mut add = |tick: u32, channel: Channel, msg: ChannelVoiceMsg| {
+            track.extend(TrackEvent {
+                delta_time: tick - last_tick,
+                event: MidiMsg::ChannelVoice { channel, msg },
+                beat_or_frame: 0.0,
+            });
+            last_tick = tick;
+        };
+        let on = |note, velocity| ChannelVoiceMsg::NoteOn { note, velocity };
+        let off = |note| ChannelVoiceMsg::NoteOff { note, velocity: 0 };
+        let pair = |channel, note, velocity, start_tick, duration_ticks| NotePair {
+            channel,
+            note,
+            velocity,
+            start_tick,
+            duration_ticks,
+        };
+
+        add(0, Channel::Ch1, on(60, 100));
+        add(10, Channel::Ch2, on(64, 90));
+        add(20, Channel::Ch1, off(60));
+        // A note on with velocity 0 is a note off
+        add(30, Channel::Ch2, on(64, 0));
+        // A retrigger: the first note off ends the first note on
+        add(40, Channel::Ch1, on(62, 80));
+        add(50, Channel::Ch1, on(62, 70));
+        add(60, Channel::Ch1, off(62));
+        add(70, Channel::Ch1, off(62));
+        add(
+            80,
+            Channel::Ch3,
+            ChannelVoiceMsg::HighResNoteOn {
+                note: 67,
+                velocity: 0x3FFF,
+            },
+        );
+        // Never ended
+        add(90, Channel::Ch4, on(72, 60));
+        add(
+            100,
+            Channel::Ch3,
+            ChannelVoiceMsg::ProgramChange { program: 1 },
+        );
+
+        assert_eq!(
+            track.note_pairs(),
+            vec![
+                pair(Channel::Ch1, 60, 100, 0, 20),
+                pair(Channel::Ch2, 64, 90, 10, 20),
+                pair(Channel::Ch1, 62, 80, 40, 20),
+                pair(Channel::Ch1, 62, 70, 50, 20),
+                pair(Channel::Ch3, 67, 127, 80, 20),
+                pair(Channel::Ch4, 72, 60, 90, 10),
+            ]
+        );
+        assert_eq!(Track::default().note_pairs(), vec![]);
+    }
 
     #[test]
-    fn test_file_time_signature() {
-        let midi_data = vec![4, 2, 24, 8];
-        let time_sig = FileTimeSignature::from_midi(&midi_data).unwrap();
+    fn test_absolute_track() {
+        let on = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let file = MidiFile::from_messages(
+            &[(0.0, on(60)), (0.25, on(64)), (0.25, on(67)), (1.5, on(72))],
+            96,
+            120.0,
+        );
+        let track = &file.tracks[0];
 
-        assert_eq!(time_sig.numerator, 4);
-        assert_eq!(time_sig.denominator, 4);
-        assert_eq!(time_sig.clocks_per_metronome_tick, 24);
-        assert_eq!(time_sig.thirty_second_notes_per_24_clocks, 8);
+        let absolute = AbsoluteTrack::from_delta(track);
+        let ticks: Vec<u32> = absolute.0.iter().map(|(tick, _)| *tick).collect();
+        assert_eq!(ticks, vec![0, 0, 48, 48, 288, 288]);
+        assert_eq!(absolute.clone().into_delta(&file.header.division), *track);
+
+        // Events are moved into place
+        let mut absolute = absolute;
+        absolute.0.push((48, on(62)));
+        let track = absolute.into_delta(&file.header.division);
+        assert_eq!(track.events()[4].event, on(62));
+        assert_eq!(track.events()[4].delta_time, 0);
+        assert_eq!(track.events()[4].beat_or_frame, 0.5);
+    }
+
+    #[test]
+    fn test_iter_absolute() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let mut track = Track::default();
+        for (delta_time, n) in [(0, 60), (48, 62), (0, 64), (144, 65)].iter() {
+            // `beat_or_frame` is not used
+            track.extend(TrackEvent {
+                delta_time: *delta_time,
+                event: note(*n),
+                beat_or_frame: 100.0,
+            });
+        }
+        let division = Division::TicksPerQuarterNote(96);
+        let events: Vec<(f32, &MidiMsg)> = track.iter_absolute(&division).collect();
+        assert_eq!(
+            events,
+            vec![
+                (0.0, &note(60)),
+                (0.5, &note(62)),
+                (0.5, &note(64)),
+                (2.0, &note(65))
+            ]
+        );
+        assert_eq!(
+            Track::AlienChunk(vec![1, 2, 3])
+                .iter_absolute(&division)
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_serialized_len() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        // Delta times of several VLQ lengths
+        let mut file = MidiFile::from_messages(
+            &[(0.0, note(60)), (1.0, note(64)), (100.0, note(67))],
+            960,
+            120.0,
+        );
+        file.extend_track(
+            0,
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalNonRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM1),
+                },
+            },
+            20000.0,
+        );
+        // Skipped when serializing
+        file.extend_track(
+            0,
+            MidiMsg::SystemRealTime {
+                msg: crate::SystemRealTimeMsg::SystemReset,
+            },
+            20000.0,
+        );
+        file.add_track(Track::AlienChunk(vec![
+            b'A', b'B', b'C', b'D', 0, 0, 0, 1, 0,
+        ]));
+        file.add_metronome_track(
+            Channel::Ch10,
+            76,
+            2,
+            FileTimeSignature {
+                numerator: 3,
+                denominator: 4,
+                clocks_per_metronome_tick: 24,
+                thirty_second_notes_per_24_clocks: 8,
+            },
+        );
+        assert_eq!(file.serialized_len(), file.to_midi().len());
 
-        let mut output = Vec::new();
-        time_sig.extend_midi(&mut output);
-        assert_eq!(output, midi_data);
+        assert_eq!(MidiFile::default().serialized_len(), 14);
     }
 
     #[test]
-    fn test_file_time_signature_error() {
-        let midi_data = vec![4, 2, 24];
-        assert!(matches!(
-            FileTimeSignature::from_midi(&midi_data),
-            Err(ParseError::UnexpectedEnd)
-        ));
+    fn test_to_midi_with_running_status() {
+        let note = |channel, note| MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let file = MidiFile::from_messages(
+            &[
+                (0.0, note(Channel::Ch1, 60)),
+                (0.0, note(Channel::Ch1, 64)),
+                (0.0, note(Channel::Ch2, 67)),
+                (0.0, note(Channel::Ch2, 72)),
+            ],
+            96,
+            120.0,
+        );
+        let mut midi = file.to_midi_with_running_status();
+        let track = midi.split_off(22);
+        // Same header, but a track chunk two bytes shorter
+        assert_eq!(midi[..18], file.to_midi()[..18]);
+        assert_eq!(midi[18..], [0, 0, 0, 25]);
+        assert_eq!(
+            track,
+            vec![
+                0x00, 0xFF, 0x51, 0x03, 0x07, 0xA1, 0x20, // Tempo
+                0x00, 0x90, 60, 100, //
+                0x00, 64, 100, // Running status
+                0x00, 0x91, 67, 100, //
+                0x00, 72, 100, // Running status
+                0x00, 0xFF, 0x2F, 0x00, // End of track
+            ]
+        );
+        assert_eq!(
+            MidiFile::from_midi(&file.to_midi_with_running_status()).unwrap(),
+            MidiFile::from_midi(&file.to_midi()).unwrap()
+        );
     }
 
     #[test]
-    fn test_key_signature() {
-        let midi_data = vec![2, 0];
-        let key_sig = KeySignature::from_midi(&midi_data).unwrap();
+    fn test_text_encoding() {
+        let meta = |msg| MidiMsg::Meta { msg };
+        let mut file = MidiFile::default();
+        file.add_track(Track::default());
+        file.extend_track(0, meta(Meta::TrackName("Café".to_string())), 0.0);
+        file.extend_track(0, meta(Meta::Lyric("5 €".to_string())), 0.0);
+        file.extend_track(0, meta(Meta::EndOfTrack), 0.0);
 
-        assert_eq!(key_sig.key, 2);
-        assert_eq!(key_sig.scale, 0);
+        let options = SerializeOptions {
+            text_encoding: TextEncoding::Latin1,
+            ..Default::default()
+        };
+        let latin1 = file.to_midi_with_options(&options);
+        assert_eq!(
+            latin1[22..],
+            [
+                0x00, 0xFF, 0x03, 0x04, b'C', b'a', b'f', 0xE9, // Track name
+                0x00, 0xFF, 0x05, 0x03, b'5', b' ', b'?', // Lyric
+                0x00, 0xFF, 0x2F, 0x00, // End of track
+            ]
+        );
 
-        let mut output = Vec::new();
-        key_sig.extend_midi(&mut output);
-        assert_eq!(output, midi_data);
-    }
+        let decoded =
+            MidiFile::from_midi_with_text_encoding(&latin1, TextEncoding::Latin1).unwrap();
+        assert_eq!(
+            decoded.tracks[0].events()[0].event,
+            meta(Meta::TrackName("Café".to_string()))
+        );
+        assert_eq!(
+            decoded.tracks[0].events()[1].event,
+            meta(Meta::Lyric("5 ?".to_string()))
+        );
+        // Not valid UTF-8: the bytes are kept, and written back unchanged
+        let utf8 = MidiFile::from_midi(&latin1).unwrap();
+        let raw = Meta::RawText {
+            meta_type: 0x03,
+            data: vec![b'C', b'a', b'f', 0xE9],
+        };
+        assert_eq!(utf8.tracks[0].events()[0].event, meta(raw.clone()));
+        assert_eq!(utf8.to_midi(), latin1);
+        assert_eq!(
+            raw.decode_raw_text(TextEncoding::Utf8),
+            Meta::TrackName("Caf\u{FFFD}".to_string())
+        );
+        assert_eq!(
+            raw.decode_raw_text(TextEncoding::Latin1),
+            Meta::TrackName("Café".to_string())
+        );
 
-    #[test]
-    fn test_key_signature_error() {
-        let midi_data = vec![2];
-        assert!(matches!(
-            KeySignature::from_midi(&midi_data),
-            Err(ParseError::UnexpectedEnd)
-        ));
+        // The default options give the same result as `to_midi`
+        assert_eq!(
+            file.to_midi_with_options(&SerializeOptions::default()),
+            file.to_midi()
+        );
     }
 
     #[test]
-    fn test_file_serde() {
-        use crate::message::MidiMsg;
-        use crate::Channel;
-        use crate::ChannelVoiceMsg;
+    fn test_windows_1252() {
+        let bytes = vec![0x80, b' ', 0x99, b' ', 0xA9, b' ', 0x81];
+        let s = TextEncoding::Windows1252.decode(&bytes);
+        assert_eq!(s, "€ ™ © \u{81}");
+        assert_eq!(TextEncoding::Windows1252.encode(&s), bytes);
+        assert_eq!(TextEncoding::Windows1252.encode("Ā"), vec![b'?']);
 
-        // Create a simple MIDI file
+        // A Windows-1252 copyright notice round-trips byte for byte
         let mut file = MidiFile::default();
-        // Set the division
-        file.header.division = Division::TicksPerQuarterNote(480);
-
         file.add_track(Track::default());
-
-        // Add some events to the track
         file.extend_track(
             0,
             MidiMsg::Meta {
-                msg: Meta::TrackName("Test Track".to_string()),
+                msg: Meta::Copyright("Copyright © 1998 Foo™".to_string()),
             },
             0.0,
         );
+        let options = SerializeOptions {
+            text_encoding: TextEncoding::Windows1252,
+            ..Default::default()
+        };
+        let midi = file.to_midi_with_options(&options);
+        assert_eq!(midi[midi.len() - 1], 0x99);
+        let decoded =
+            MidiFile::from_midi_with_text_encoding(&midi, TextEncoding::Windows1252).unwrap();
+        assert_eq!(decoded, file);
+        assert_eq!(decoded.to_midi_with_options(&options), midi);
+    }
 
-        file.extend_track(
+    #[test]
+    fn test_fingerprint() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let msgs = [(0.5, note(60)), (1.0, note(64)), (2.25, note(67))];
+        let serialized_96 = MidiFile::from_messages(&msgs, 96, 120.0).to_midi();
+        let serialized_480 = MidiFile::from_messages(&msgs, 480, 120.0).to_midi();
+        assert_ne!(serialized_96, serialized_480);
+
+        let file_96 = MidiFile::from_midi(&serialized_96).unwrap();
+        let mut file_480 = MidiFile::from_midi(&serialized_480).unwrap();
+        assert_eq!(file_96.fingerprint(), file_480.fingerprint());
+
+        // Names don't matter
+        file_480.extend_track(
             0,
             MidiMsg::Meta {
-                msg: Meta::TimeSignature(FileTimeSignature {
-                    numerator: 4,
-                    denominator: 4,
-                    clocks_per_metronome_tick: 24,
-                    thirty_second_notes_per_24_clocks: 8,
-                }),
+                msg: Meta::TrackName("Piano".to_string()),
             },
-            0.0,
+            2.5,
         );
+        assert_eq!(file_96.fingerprint(), file_480.fingerprint());
 
-        file.extend_track(
-            0,
-            MidiMsg::ChannelVoice {
-                channel: Channel::Ch1,
-                msg: ChannelVoiceMsg::NoteOn {
-                    note: 60,
-                    velocity: 64,
-                },
+        // Notes do
+        let different = MidiFile::from_messages(
+            &[(0.5, note(60)), (1.0, note(64)), (2.25, note(68))],
+            96,
+            120.0,
+        );
+        assert_ne!(file_96.fingerprint(), different.fingerprint());
+        // As does timing
+        let different = MidiFile::from_messages(
+            &[(0.5, note(60)), (1.0, note(64)), (2.5, note(67))],
+            96,
+            120.0,
+        );
+        assert_ne!(file_96.fingerprint(), different.fingerprint());
+    }
+
+    #[test]
+    fn test_notes_at_bar_boundaries() {
+        let meta = |msg| MidiMsg::Meta { msg };
+        let on = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
             },
-            0.0,
+        };
+        let off = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOff { note, velocity: 0 },
+        };
+        let time_signature = MidiMsg::Meta {
+            msg: Meta::TimeSignature(FileTimeSignature {
+                numerator: 4,
+                denominator: 4,
+                clocks_per_metronome_tick: 24,
+                thirty_second_notes_per_24_clocks: 8,
+            }),
+        };
+        // At 60 bpm, one second is one beat
+        let mut file = MidiFile::from_messages(
+            &[
+                (0.0, time_signature),
+                (0.0, on(60)),
+                (2.0, on(64)),
+                // Ends on the bar line, so it's sounding at the end of the first bar
+                (4.0, off(60)),
+                // Starts on the bar line, so it isn't
+                (4.0, on(67)),
+                (6.0, off(64)),
+                (9.0, off(67)),
+            ],
+            96,
+            60.0,
+        );
+        assert_eq!(
+            file.notes_at_bar_boundaries(),
+            vec![
+                (0, vec![(Channel::Ch1, 60), (Channel::Ch1, 64)]),
+                (1, vec![(Channel::Ch1, 67)]),
+            ]
         );
 
-        file.extend_track(
-            0,
-            MidiMsg::ChannelVoice {
-                channel: Channel::Ch1,
-                msg: ChannelVoiceMsg::NoteOff {
-                    note: 60,
-                    velocity: 64,
+        file.header.division = Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 40,
+        };
+        assert_eq!(file.notes_at_bar_boundaries(), vec![]);
+
+        // A denominator of 0 is treated as 1, so a 4/0 bar is 16 beats long
+        let file = MidiFile::from_messages(
+            &[
+                (
+                    0.0,
+                    meta(Meta::TimeSignature(FileTimeSignature {
+                        numerator: 4,
+                        denominator: 0,
+                        clocks_per_metronome_tick: 24,
+                        thirty_second_notes_per_24_clocks: 8,
+                    })),
+                ),
+                (0.0, on(60)),
+                (17.0, off(60)),
+            ],
+            96,
+            60.0,
+        );
+        assert_eq!(
+            file.notes_at_bar_boundaries(),
+            vec![(0, vec![(Channel::Ch1, 60)])]
+        );
+    }
+
+    #[test]
+    fn test_slice() {
+        let on = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let off = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOff { note, velocity: 0 },
+        };
+        // At 60 bpm, one second is one beat
+        let file = MidiFile::from_messages(
+            &[(0.0, on(60)), (3.0, on(64)), (4.0, off(64)), (8.0, off(60))],
+            96,
+            60.0,
+        );
+        let sliced = file.slice(2.0, 6.0);
+        assert_eq!(sliced.header.num_tracks, 1);
+        let events: Vec<(u32, f32, MidiMsg)> = sliced.tracks[0]
+            .events()
+            .iter()
+            .map(|e| (e.delta_time, e.beat_or_frame, e.event.clone()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (
+                    0,
+                    0.0,
+                    MidiMsg::Meta {
+                        msg: Meta::SetTempo(1_000_000)
+                    }
+                ),
+                // The sustained note is started again
+                (0, 0.0, on(60)),
+                (96, 1.0, on(64)),
+                (96, 2.0, off(64)),
+                // And ended at the end of the slice
+                (192, 4.0, off(60)),
+                (
+                    0,
+                    4.0,
+                    MidiMsg::Meta {
+                        msg: Meta::EndOfTrack
+                    }
+                ),
+            ]
+        );
+
+        // Nothing but the sustained note
+        let sliced = file.slice(5.0, 7.0);
+        let events: Vec<MidiMsg> = sliced.tracks[0]
+            .events()
+            .iter()
+            .map(|e| e.event.clone())
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                MidiMsg::Meta {
+                    msg: Meta::SetTempo(1_000_000)
                 },
+                on(60),
+                off(60),
+                MidiMsg::Meta {
+                    msg: Meta::EndOfTrack
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_messages() {
+        let note = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
             },
-            1.0,
+        };
+        let file = MidiFile::from_messages(
+            &[(0.5, note(60)), (1.0, note(64)), (2.25, note(67))],
+            480,
+            120.0,
         );
-        file.extend_track(
-            0,
+        assert_eq!(file.header.format, SMFFormat::SingleTrack);
+        assert_eq!(file.header.num_tracks, 1);
+        assert_eq!(file.header.division, Division::TicksPerQuarterNote(480));
+
+        let events = file.tracks[0].events();
+        assert_eq!(events.len(), 5);
+        assert_eq!(
+            events[0].event,
             MidiMsg::Meta {
-                msg: Meta::EndOfTrack,
+                msg: Meta::SetTempo(500000)
+            }
+        );
+        assert_eq!(
+            events[4].event,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack
+            }
+        );
+
+        let deserialized = MidiFile::from_midi(&file.to_midi()).unwrap();
+        let notes: Vec<(f32, &MidiMsg)> = deserialized.tracks[0]
+            .events()
+            .iter()
+            .filter(|e| e.event.is_channel_voice())
+            .map(|e| (e.beat_or_frame, &e.event))
+            .collect();
+        assert_eq!(
+            notes,
+            vec![(1.0, &note(60)), (2.0, &note(64)), (4.5, &note(67))]
+        );
+        let deltas: Vec<u32> = events.iter().map(|e| e.delta_time).collect();
+        assert_eq!(deltas, vec![0, 480, 480, 1200, 0]);
+    }
+
+    #[test]
+    fn test_resolve_channel_prefix() {
+        let event = |delta_time, event| TrackEvent {
+            delta_time,
+            event,
+            beat_or_frame: 0.0,
+        };
+        let meta = |msg| MidiMsg::Meta { msg };
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::Commercial {
+                id: 0x41.into(),
+                data: vec![0x10],
             },
-            2.0,
+        };
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        let track = Track::Midi(vec![
+            event(0, meta(Meta::TrackName("Track".to_string()))),
+            event(10, meta(Meta::ChannelPrefix(Channel::Ch3))),
+            event(5, meta(Meta::InstrumentName("Organ".to_string()))),
+            event(0, sysex.clone()),
+            event(5, note_on.clone()),
+            event(5, meta(Meta::Lyric("La".to_string()))),
+            event(0, meta(Meta::EndOfTrack)),
+        ]);
+        assert_eq!(
+            track.event_channels(),
+            vec![
+                None,
+                Some(Channel::Ch3),
+                Some(Channel::Ch3),
+                Some(Channel::Ch3),
+                Some(Channel::Ch1),
+                None,
+                None
+            ]
         );
 
-        // Convert the file to bytes
-        let bytes = file.to_midi();
+        assert_eq!(
+            track.resolve_channel_prefix(),
+            Track::Midi(vec![
+                event(0, meta(Meta::TrackName("Track".to_string()))),
+                event(15, meta(Meta::ChannelPrefix(Channel::Ch3))),
+                event(0, meta(Meta::InstrumentName("Organ".to_string()))),
+                event(0, meta(Meta::ChannelPrefix(Channel::Ch3))),
+                event(0, sysex),
+                event(5, note_on),
+                event(5, meta(Meta::Lyric("La".to_string()))),
+                event(0, meta(Meta::EndOfTrack)),
+            ])
+        );
+    }
 
-        // Assert that we've created a valid MIDI file
-        assert!(bytes.starts_with(b"MThd"));
-        assert!(bytes[14..].starts_with(b"MTrk"));
+    #[test]
+    fn test_validate_sysex() {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(b"MThd");
+        bytes.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&[0, 0, 0, 18]);
+        bytes.extend_from_slice(&[
+            0x00, 0xF0, 0x03, 0x41, 0x10, 0xF7, // A valid sysex
+            0x00, 0xF0, 0x05, 0x41, 0x10, 0xF7, 0x00, 0x00, // Declared length is too long
+            0x00, 0xFF, 0x2F, 0x00,
+        ]);
 
-        let deserialized_file = MidiFile::from_midi(&bytes).unwrap();
-        assert_eq!(deserialized_file.tracks.len(), 1);
-        assert_eq!(deserialized_file.tracks[0].events().len(), 5);
+        let file = MidiFile::from_midi(&bytes).unwrap();
+        let track = &file.tracks[0];
+        assert_eq!(track.validate_sysex(), vec![1]);
         assert_eq!(
-            deserialized_file.header.division,
-            Division::TicksPerQuarterNote(480)
+            track.events()[1].event,
+            MidiMsg::Invalid {
+                bytes: vec![0x41, 0x10, 0xF7, 0x00, 0x00],
+                error: ParseError::Invalid("Invalid system exclusive message"),
+            }
         );
-        assert_eq!(deserialized_file, file);
+        assert_eq!(
+            track.events()[2].event,
+            MidiMsg::Meta {
+                msg: Meta::EndOfTrack
+            }
+        );
+        assert_eq!(file.performance_only().tracks[0].validate_sysex(), vec![]);
     }
 
     #[test]
-    fn test_file_system_reset() {
+    fn test_sysex_off_by_one_length() {
+        let file_with_sysex = |sysex: &[u8]| {
+            let mut bytes = vec![];
+            bytes.extend_from_slice(b"MThd");
+            bytes.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+            bytes.extend_from_slice(b"MTrk");
+            bytes.extend_from_slice(&(sysex.len() as u32 + 8).to_be_bytes());
+            bytes.extend_from_slice(sysex);
+            bytes.extend_from_slice(&[0x00, 0x90, 0x3C, 0x40, 0x00, 0xFF, 0x2F, 0x00]);
+            MidiFile::from_midi(&bytes)
+        };
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 0x3C,
+                velocity: 0x40,
+            },
+        };
+
+        // The declared length includes the 0xF0, so it covers the delta time of the next event
+        let file = file_with_sysex(&[0x00, 0xF0, 0x06, 0x7E, 0x7F, 0x09, 0x01, 0xF7]).unwrap();
+        let events = file.tracks[0].events();
+        assert_eq!(file.tracks[0].validate_sysex(), vec![0]);
+        assert_eq!(
+            events[0].event,
+            MidiMsg::Invalid {
+                bytes: vec![0x7E, 0x7F, 0x09, 0x01, 0xF7],
+                error: ParseError::Invalid("Invalid system exclusive message"),
+            }
+        );
+        assert_eq!(events[1].event, note_on);
+        assert_eq!(events[1].delta_time, 0);
+        assert_eq!(events.len(), 3);
+
+        // The declared length does not include the 0xF7
+        let file = file_with_sysex(&[0x00, 0xF0, 0x04, 0x7E, 0x7F, 0x09, 0x01, 0xF7]).unwrap();
+        let events = file.tracks[0].events();
+        assert_eq!(file.tracks[0].validate_sysex(), vec![0]);
+        assert_eq!(events[1].event, note_on);
+        assert_eq!(events[1].delta_time, 0);
+        assert_eq!(events.len(), 3);
+
+        // A correct length is not affected
+        let file = file_with_sysex(&[0x00, 0xF0, 0x05, 0x7E, 0x7F, 0x09, 0x01, 0xF7]).unwrap();
+        assert_eq!(file.tracks[0].validate_sysex(), vec![]);
+
+        // Other length errors still use the declared length
+        let file =
+            file_with_sysex(&[0x00, 0xF0, 0x06, 0x41, 0x10, 0xF7, 0x00, 0x00, 0x00]).unwrap();
+        assert_eq!(file.tracks[0].validate_sysex(), vec![0]);
+        assert_eq!(file.tracks[0].events()[1].event, note_on);
+    }
+
+    #[test]
+    fn test_split_sysex() {
+        let file_with_events = |events: &[u8]| {
+            let mut bytes = vec![];
+            bytes.extend_from_slice(b"MThd");
+            bytes.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0, 96]);
+            bytes.extend_from_slice(b"MTrk");
+            bytes.extend_from_slice(&(events.len() as u32 + 4).to_be_bytes());
+            bytes.extend_from_slice(events);
+            bytes.extend_from_slice(&[0x00, 0xFF, 0x2F, 0x00]);
+            MidiFile::from_midi(&bytes).unwrap()
+        };
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 0x3C,
+                velocity: 0x40,
+            },
+        };
+
+        // A GM1 System On message, split into two packets with a note in between
+        let file = file_with_events(&[
+            0x00, 0xF0, 0x03, 0x7E, 0x7F, 0x09, // First packet
+            0x10, 0x90, 0x3C, 0x40, //
+            0x20, 0xF7, 0x02, 0x01, 0xF7, // Final packet
+        ]);
+        let events: Vec<(u32, MidiMsg)> = file.tracks[0]
+            .events()
+            .iter()
+            .map(|e| (e.delta_time, e.event.clone()))
+            .collect();
+        let expected = vec![
+            (0x10, note_on),
+            (
+                0x20,
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalNonRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM1),
+                    },
+                },
+            ),
+            (
+                0,
+                MidiMsg::Meta {
+                    msg: Meta::EndOfTrack,
+                },
+            ),
+        ];
+        assert_eq!(events, expected);
+        assert_eq!(file.tracks[0].events()[1].beat_or_frame, 0.5);
+
+        // The reassembled message is written as a single event
+        let deserialized = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(deserialized, file);
+
+        // A split message that is never finished
+        let file = file_with_events(&[0x00, 0xF0, 0x03, 0x7E, 0x7F, 0x09]);
+        assert_eq!(file.tracks[0].validate_sysex(), vec![0]);
+        assert_eq!(
+            file.tracks[0].events()[0].event,
+            MidiMsg::Invalid {
+                bytes: vec![0x7E, 0x7F, 0x09],
+                error: ParseError::NoEndOfSystemExclusiveFlag,
+            }
+        );
+
+        let mut ctx = ReceiverContext::new();
+        assert_eq!(ctx.push_sysex_packet(&[0xF0, 0x7E, 0x7F]), None);
+        assert!(ctx.has_pending_sysex());
+        assert_eq!(ctx.push_sysex_packet(&[0xF7, 0x09]), None);
+        assert_eq!(
+            ctx.push_sysex_packet(&[0xF7, 0x01, 0xF7]),
+            Some(vec![0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7])
+        );
+        assert!(!ctx.has_pending_sysex());
+        // Continuations without a first packet are ignored
+        assert_eq!(ctx.push_sysex_packet(&[0xF7, 0x01, 0xF7]), None);
+    }
+
+    #[test]
+    fn test_sequence_numbers() {
         let mut file = MidiFile::default();
-        file.add_track(Track::default());
-        file.extend_track(
-            0,
-            MidiMsg::SystemRealTime {
-                msg: crate::SystemRealTimeMsg::SystemReset,
+        file.header.format = SMFFormat::MultiSong;
+        let note = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
             },
-            0.0,
+        };
+        for (track_num, sequence_number) in [Some(3), None, Some(1)].iter().enumerate() {
+            file.add_track(Track::default());
+            if let Some(n) = sequence_number {
+                file.extend_track(
+                    track_num,
+                    MidiMsg::Meta {
+                        msg: Meta::SequenceNumber(*n),
+                    },
+                    0.0,
+                );
+            }
+            file.extend_track(track_num, note.clone(), 0.0);
+            file.extend_track(track_num, note.clone(), 1.0);
+        }
+        assert_eq!(file.sequence_numbers(), vec![Some(3), None, Some(1)]);
+
+        // Inserting events at the start of a track keeps the sequence number first
+        file.add_tempo_ramp(0, 0.0, 1.0, 120.0, 100.0, 2);
+        file.add_tempo_ramp(1, 0.0, 1.0, 120.0, 100.0, 2);
+        assert_eq!(
+            file.tracks[0].events()[1].event,
+            MidiMsg::Meta {
+                msg: Meta::SetTempo(500000)
+            }
         );
-        let bytes = file.to_midi();
+        assert_eq!(
+            file.tracks[1].events()[0].event,
+            MidiMsg::Meta {
+                msg: Meta::SetTempo(500000)
+            }
+        );
+        assert_eq!(file.sequence_numbers(), vec![Some(3), None, Some(1)]);
 
-        let deserialized_file = MidiFile::from_midi(&bytes).unwrap();
-        assert_eq!(deserialized_file.tracks.len(), 1);
-        // The system reset message should not be included in the track, since it is not a valid MIDI file message
-        assert_eq!(deserialized_file.tracks[0].events().len(), 0);
+        let deserialized = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(deserialized.header.format, SMFFormat::MultiSong);
+        assert_eq!(
+            deserialized.sequence_numbers(),
+            vec![Some(3), None, Some(1)]
+        );
+    }
+
+    #[test]
+    fn test_assign_ports() {
+        let mut file = MidiFile::default();
+        let note = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        for (track_num, port) in [Some(0), Some(1), None].iter().enumerate() {
+            file.add_track(Track::default());
+            file.extend_track(
+                track_num,
+                MidiMsg::Meta {
+                    msg: Meta::TrackName("Track".to_string()),
+                },
+                0.0,
+            );
+            if let Some(port) = port {
+                file.extend_track(
+                    track_num,
+                    MidiMsg::Meta {
+                        msg: Meta::MidiPort(*port),
+                    },
+                    0.0,
+                );
+            }
+            file.extend_track(track_num, note.clone(), 1.0);
+        }
+        assert_eq!(file.assign_ports(), vec![Some(0), Some(1), None]);
+
+        let deserialized = MidiFile::from_midi(&file.to_midi()).unwrap();
+        assert_eq!(deserialized.assign_ports(), vec![Some(0), Some(1), None]);
+    }
+
+    #[test]
+    fn test_delta_ticks() {
+        let division = Division::TicksPerQuarterNote(480);
+        assert_eq!(division.delta_ticks(1.0, 2.5), 720);
+        assert_eq!(division.delta_ticks(2.0, 2.0), 0);
+        // Backward gaps can't be represented
+        assert_eq!(division.delta_ticks(2.5, 1.0), 0);
+
+        let division = Division::TimeCode {
+            frames_per_second: TimeCodeType::FPS25,
+            ticks_per_frame: 40,
+        };
+        assert_eq!(division.delta_ticks(10.0, 12.0), 80);
+        assert_eq!(division.delta_ticks(12.0, 10.0), 0);
+    }
+
+    #[test]
+    fn test_df30_ticks_to_seconds() {
+        let division = Division::TimeCode {
+            frames_per_second: TimeCodeType::DF30,
+            ticks_per_frame: 4,
+        };
+        // One nominal second of frames
+        assert_eq!(division.ticks_to_beats_or_frames(120), 30.0);
+        assert!((division.ticks_to_seconds(120).unwrap() - 1.001).abs() < 1e-5);
+        // One nominal hour of frames runs 3.6 seconds long
+        assert_eq!(division.beat_or_frame_to_tick(108000.0), 432000);
+        assert!((division.ticks_to_seconds(432000).unwrap() - 3603.6).abs() < 1e-2);
+
+        let division = Division::TimeCode {
+            frames_per_second: TimeCodeType::NDF30,
+            ticks_per_frame: 4,
+        };
+        assert_eq!(division.ticks_to_seconds(120), Some(1.0));
+        assert_eq!(Division::TicksPerQuarterNote(96).ticks_to_seconds(96), None);
     }
 }
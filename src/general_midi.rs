@@ -232,6 +232,75 @@ pub enum GMPercussionMap {
     OpenTriangle = 81,
 }
 
+impl GMPercussionMap {
+    /// The percussion sound played by the given note number on channel 10, if General MIDI defines one.
+    pub fn from_note(note: u8) -> Option<Self> {
+        PERCUSSION_SOUNDS
+            .get((note as usize).checked_sub(Self::AcousticBassDrum as usize)?)
+            .copied()
+    }
+}
+
+static PERCUSSION_SOUNDS: &[GMPercussionMap] = &[
+    GMPercussionMap::AcousticBassDrum,
+    GMPercussionMap::BassDrum1,
+    GMPercussionMap::SideStick,
+    GMPercussionMap::AcousticSnare,
+    GMPercussionMap::HandClap,
+    GMPercussionMap::ElectricSnare,
+    GMPercussionMap::LowFloorTom,
+    GMPercussionMap::ClosedHiHat,
+    GMPercussionMap::HighFloorTom,
+    GMPercussionMap::PedalHiHat,
+    GMPercussionMap::LowTom,
+    GMPercussionMap::OpenHiHat,
+    GMPercussionMap::LowMidTom,
+    GMPercussionMap::HiMidTom,
+    GMPercussionMap::CrashCymbal1,
+    GMPercussionMap::HighTom,
+    GMPercussionMap::RideCymbal1,
+    GMPercussionMap::ChineseCymbal,
+    GMPercussionMap::RideBell,
+    GMPercussionMap::Tambourine,
+    GMPercussionMap::SplashCymbal,
+    GMPercussionMap::Cowbell,
+    GMPercussionMap::CrashCymbal2,
+    GMPercussionMap::Vibraslap,
+    GMPercussionMap::RideCymbal2,
+    GMPercussionMap::HiBongo,
+    GMPercussionMap::LowBongo,
+    GMPercussionMap::MuteHiConga,
+    GMPercussionMap::OpenHiConga,
+    GMPercussionMap::LowConga,
+    GMPercussionMap::HighTimbale,
+    GMPercussionMap::LowTimbale,
+    GMPercussionMap::HighAgogo,
+    GMPercussionMap::LowAgogo,
+    GMPercussionMap::Cabasa,
+    GMPercussionMap::Maracas,
+    GMPercussionMap::ShortWhistle,
+    GMPercussionMap::LongWhistle,
+    GMPercussionMap::ShortGuiro,
+    GMPercussionMap::LongGuiro,
+    GMPercussionMap::Claves,
+    GMPercussionMap::HiWoodBlock,
+    GMPercussionMap::LowWoodBlock,
+    GMPercussionMap::MuteCuica,
+    GMPercussionMap::OpenCuica,
+    GMPercussionMap::MuteTriangle,
+    GMPercussionMap::OpenTriangle,
+];
+
+/// What a note played on a General MIDI device sounds like. See [`ChannelVoiceMsg::gm_note`](crate::ChannelVoiceMsg::gm_note).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GMNote {
+    /// A note on the percussion channel, [`Channel::Ch10`](crate::Channel::Ch10), which plays a percussion sound.
+    Percussion(GMPercussionMap),
+    /// A note on any other channel, which plays the given pitch with the instrument chosen by the last
+    /// [`ProgramChange`](crate::ChannelVoiceMsg::ProgramChange), i.e. a [`GMSoundSet`] sound.
+    Melodic(u8),
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -241,6 +310,27 @@ mod tests {
     #[cfg(feature = "std")]
     use strum::IntoEnumIterator;
 
+    #[test]
+    fn test_percussion_from_note() {
+        assert_eq!(GMPercussionMap::from_note(34), None);
+        assert_eq!(
+            GMPercussionMap::from_note(35),
+            Some(GMPercussionMap::AcousticBassDrum)
+        );
+        assert_eq!(
+            GMPercussionMap::from_note(58),
+            Some(GMPercussionMap::Vibraslap)
+        );
+        assert_eq!(
+            GMPercussionMap::from_note(81),
+            Some(GMPercussionMap::OpenTriangle)
+        );
+        assert_eq!(GMPercussionMap::from_note(82), None);
+        for note in 35..82 {
+            assert_eq!(GMPercussionMap::from_note(note).unwrap() as u8, note);
+        }
+    }
+
     #[cfg(feature = "std")]
     #[test]
     fn gm_iter() {
@@ -179,6 +179,12 @@ pub use file::*;
 
 mod message;
 pub use message::*;
+mod encoder;
+pub use encoder::*;
+#[cfg(feature = "codec")]
+mod codec;
+#[cfg(feature = "codec")]
+pub use codec::*;
 
 // A helper used in tests
 #[cfg(test)]
@@ -2,8 +2,8 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use super::{
-    ChannelModeMsg, ChannelVoiceMsg, ParseError, ReceiverContext, SystemCommonMsg,
-    SystemRealTimeMsg,
+    ChannelModeMsg, ChannelVoiceMsg, ControlChange, MidiEncoder, ParseError, ReceiverContext,
+    SystemCommonMsg, SystemRealTimeMsg,
 };
 
 #[cfg(feature = "sysex")]
@@ -98,6 +98,19 @@ impl MidiMsg {
         Self::_from_midi_with_context(m, ctx, true)
     }
 
+    /// Like [`MidiMsg::from_midi_with_context`], but Ok results return the slice of the input that
+    /// was consumed instead of its length.
+    ///
+    /// This allows the original bytes of a message to be forwarded unchanged, alongside its
+    /// parsed form, without the differences that reserializing it could introduce.
+    pub fn from_midi_with_bytes<'a>(
+        m: &'a [u8],
+        ctx: &mut ReceiverContext,
+    ) -> Result<(Self, &'a [u8]), ParseError> {
+        let (msg, len) = Self::from_midi_with_context(m, ctx)?;
+        Ok((msg, &m[..len]))
+    }
+
     /// Like [`MidiMsg::from_midi_with_context`] but does not turn multiple related consecutive messages
     /// into one `MidiMsg`.
     pub fn from_midi_with_context_no_extensions(
@@ -107,6 +120,26 @@ impl MidiMsg {
         Self::_from_midi_with_context(m, ctx, false)
     }
 
+    /// Turn all of the bytes delivered to a MIDI input callback into `MidiMsg`s, given a
+    /// [`ReceiverContext`](crate::ReceiverContext).
+    ///
+    /// Callbacks such as [midir](https://github.com/Boddlnagg/midir)'s `|stamp, bytes, _|` may be handed
+    /// several messages, or a complete system exclusive message, at once. Every message in `bytes` is
+    /// returned, in order. An error is returned if any of them could not be parsed.
+    pub fn from_callback_bytes(
+        bytes: &[u8],
+        ctx: &mut ReceiverContext,
+    ) -> Result<Vec<Self>, ParseError> {
+        let mut msgs = vec![];
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (msg, len) = Self::from_midi_with_context(&bytes[offset..], ctx)?;
+            msgs.push(msg);
+            offset += len;
+        }
+        Ok(msgs)
+    }
+
     fn _from_midi_with_context(
         m: &[u8],
         ctx: &mut ReceiverContext,
@@ -193,9 +226,6 @@ impl MidiMsg {
                     if let Some(p) = &ctx.previous_channel_message {
                         match p {
                             Self::ChannelVoice {channel, msg: prev_msg} => {
-                                if m.len() < 2 {
-                                    return Err(ParseError::UnexpectedEnd);
-                                }
                                 match prev_msg {
                                     // See A-2 of the MIDI spec
                                     ChannelVoiceMsg::ControlChange { .. } => {
@@ -289,6 +319,8 @@ impl MidiMsg {
             }
         }
 
+        ctx.update_bank_select(&midi_msg);
+        ctx.update_parameter(&midi_msg);
         Ok((midi_msg, len))
     }
 
@@ -302,6 +334,21 @@ impl MidiMsg {
         r
     }
 
+    /// Like [`MidiMsg::messages_to_midi`], but using running status: consecutive channel
+    /// messages with the same status byte only have it written once. See [`MidiEncoder`].
+    ///
+    /// System real-time messages may be interleaved without affecting the running status. All
+    /// other system messages, including system exclusive messages, reset it, so the channel
+    /// message that follows is always written with its status byte.
+    pub fn messages_to_midi_running(msgs: &[Self]) -> Vec<u8> {
+        let mut r: Vec<u8> = vec![];
+        let mut encoder = MidiEncoder::new();
+        for m in msgs.iter() {
+            encoder.extend_midi(m, &mut r);
+        }
+        r
+    }
+
     /// Given a `Vec<u8>`, append this `MidiMsg` to it.
     pub fn extend_midi(&self, v: &mut Vec<u8>) {
         match self {
@@ -337,6 +384,15 @@ impl MidiMsg {
         }
     }
 
+    /// Returns true if this message serializes to the same bytes as `other`.
+    ///
+    /// This differs from `==`: messages that are represented differently may serialize identically
+    /// (e.g. a `ControlChange::CC` and its named equivalent), and messages that are equivalent
+    /// musically may not (e.g. a `NoteOn` with a velocity of 0 and a `NoteOff`).
+    pub fn serializes_same_as(&self, other: &MidiMsg) -> bool {
+        self.to_midi() == other.to_midi()
+    }
+
     /// Returns true if this message is a channel voice message.
     pub fn is_channel_voice(&self) -> bool {
         matches!(
@@ -414,6 +470,62 @@ impl MidiMsg {
     pub fn is_invalid(&self) -> bool {
         matches!(self, Self::Invalid { .. })
     }
+
+    /// The channel of a channel voice or channel mode message, including running status messages.
+    /// `None` for any other message.
+    pub fn channel(&self) -> Option<Channel> {
+        match self {
+            Self::ChannelVoice { channel, .. }
+            | Self::RunningChannelVoice { channel, .. }
+            | Self::ChannelMode { channel, .. }
+            | Self::RunningChannelMode { channel, .. } => Some(*channel),
+            _ => None,
+        }
+    }
+
+    /// The note number of a note on or note off message, including [`ChannelVoiceMsg::HighResNoteOn`] and
+    /// [`ChannelVoiceMsg::HighResNoteOff`] and running status messages. `None` for any other message.
+    pub fn note(&self) -> Option<u8> {
+        match self {
+            Self::ChannelVoice { msg, .. } | Self::RunningChannelVoice { msg, .. } => match msg {
+                ChannelVoiceMsg::NoteOn { note, .. }
+                | ChannelVoiceMsg::NoteOff { note, .. }
+                | ChannelVoiceMsg::HighResNoteOn { note, .. }
+                | ChannelVoiceMsg::HighResNoteOff { note, .. } => Some(*note),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The velocity of a note on or note off message, as it is stored in the message: 0-127 for
+    /// `NoteOn` and `NoteOff`, and 14 bits for [`ChannelVoiceMsg::HighResNoteOn`] and
+    /// [`ChannelVoiceMsg::HighResNoteOff`]. `None` for any other message. See [`MidiMsg::note`].
+    pub fn velocity(&self) -> Option<u16> {
+        match self {
+            Self::ChannelVoice { msg, .. } | Self::RunningChannelVoice { msg, .. } => match msg {
+                ChannelVoiceMsg::NoteOn { velocity, .. }
+                | ChannelVoiceMsg::NoteOff { velocity, .. } => Some(*velocity as u16),
+                ChannelVoiceMsg::HighResNoteOn { velocity, .. }
+                | ChannelVoiceMsg::HighResNoteOff { velocity, .. } => Some(*velocity),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// This message, sent on `channel` instead if it is a channel voice or channel mode message (see
+    /// [`MidiMsg::channel`]). Any other message is returned unchanged.
+    pub fn with_channel(mut self, channel: Channel) -> Self {
+        match &mut self {
+            Self::ChannelVoice { channel: c, .. }
+            | Self::RunningChannelVoice { channel: c, .. }
+            | Self::ChannelMode { channel: c, .. }
+            | Self::RunningChannelMode { channel: c, .. } => *c = channel,
+            _ => (),
+        }
+        self
+    }
 }
 
 impl From<&MidiMsg> for Vec<u8> {
@@ -444,6 +556,47 @@ pub fn next_message(m: &[u8]) -> Option<usize> {
     None
 }
 
+/// Merge two control change messages that form the most and least significant bytes of a 14-bit control,
+/// e.g. Volume (CC 7) and Volume LSB (CC 39), into a single high resolution [`ControlChange`].
+/// This is what a [`ReceiverContext`] with [`complex_cc`](ReceiverContext::complex_cc) does when parsing, for when
+/// the messages have already been parsed separately.
+///
+/// Returns `None` unless `msb` is a control change of a control from 0-31 and `lsb` is a control change of the
+/// matching LSB control (32 higher) on the same channel.
+pub fn merge_high_res_cc(msb: &MidiMsg, lsb: &MidiMsg) -> Option<MidiMsg> {
+    let cc = |m: &MidiMsg| match m {
+        MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange { control },
+        }
+        | MidiMsg::RunningChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange { control },
+        } => Some((*channel, control.control_number(), control.value())),
+        _ => None,
+    };
+    let (channel, msb_control, msb_value) = cc(msb)?;
+    let (lsb_channel, lsb_control, lsb_value) = cc(lsb)?;
+    if channel != lsb_channel || msb_control >= 32 || lsb_control != msb_control + 32 {
+        return None;
+    }
+    let msg = ChannelVoiceMsg::ControlChange {
+        control: ControlChange::CC {
+            control: msb_control,
+            value: msb_value,
+        }
+        .to_complex(),
+    }
+    .maybe_extend(&ChannelVoiceMsg::ControlChange {
+        control: ControlChange::CC {
+            control: lsb_control,
+            value: lsb_value,
+        },
+    })
+    .ok()?;
+    Some(MidiMsg::ChannelVoice { channel, msg })
+}
+
 fn upper_bit_set(x: u8) -> bool {
     x & 0b10000000 != 0
 }
@@ -475,6 +628,11 @@ pub enum Channel {
 }
 
 impl Channel {
+    /// Whether this is channel 10, which General MIDI reserves for percussion. See [`GMPercussionMap`](crate::GMPercussionMap).
+    pub fn is_gm_drum(&self) -> bool {
+        *self == Channel::Ch10
+    }
+
     pub fn from_u8(x: u8) -> Self {
         match x {
             0 => Self::Ch1,
@@ -495,18 +653,107 @@ impl Channel {
             _ => Self::Ch16,
         }
     }
+
+    /// The 0-based number of the channel, as it is sent in a status byte: 0 for `Ch1`, up to 15 for `Ch16`.
+    /// The inverse of [`Channel::from_u8`].
+    pub fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    /// The 1-based number of the channel, as it is usually displayed: 1 for `Ch1`, up to 16 for `Ch16`.
+    pub fn number(self) -> u8 {
+        self as u8 + 1
+    }
+
+    /// All 16 channels, from `Ch1` to `Ch16`.
+    pub fn all() -> impl Iterator<Item = Channel> {
+        (0..16).map(Self::from_u8)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::Channel::*;
+    use crate::{ControlChange, Parameter};
 
     #[test]
     fn test_ch() {
         assert_eq!(Ch1, Channel::from_u8(0));
         assert_eq!(Ch2, Channel::from_u8(1));
         assert_eq!(Ch16, Channel::from_u8(255));
+        assert_eq!(Ch1.to_u8(), 0);
+        assert_eq!(Ch16.to_u8(), 15);
+        assert_eq!(Ch1.number(), 1);
+        assert_eq!(Ch10.number(), 10);
+
+        let all: Vec<Channel> = Channel::all().collect();
+        assert_eq!(all.len(), 16);
+        assert_eq!(all[0], Ch1);
+        assert_eq!(all[15], Ch16);
+        assert!(Channel::all().all(|c| Channel::from_u8(c.to_u8()) == c));
+    }
+
+    #[test]
+    fn test_channel() {
+        let note_on = MidiMsg::RunningChannelVoice {
+            channel: Ch3,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 100,
+            },
+        };
+        assert_eq!(note_on.channel(), Some(Ch3));
+        assert_eq!(
+            note_on.with_channel(Ch10),
+            MidiMsg::RunningChannelVoice {
+                channel: Ch10,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 60,
+                    velocity: 100,
+                },
+            }
+        );
+
+        let reset = MidiMsg::ChannelMode {
+            channel: Ch1,
+            msg: ChannelModeMsg::ResetAllControllers,
+        };
+        assert_eq!(reset.with_channel(Ch2).channel(), Some(Ch2));
+
+        let clock = MidiMsg::SystemRealTime {
+            msg: SystemRealTimeMsg::TimingClock,
+        };
+        assert_eq!(clock.channel(), None);
+        assert_eq!(clock.clone().with_channel(Ch2), clock);
+    }
+
+    #[test]
+    fn test_note_and_velocity() {
+        let msg = |msg| MidiMsg::ChannelVoice { channel: Ch1, msg };
+        let note_off = msg(ChannelVoiceMsg::NoteOff {
+            note: 60,
+            velocity: 64,
+        });
+        assert_eq!(note_off.note(), Some(60));
+        assert_eq!(note_off.velocity(), Some(64));
+
+        let high_res = MidiMsg::RunningChannelVoice {
+            channel: Ch1,
+            msg: ChannelVoiceMsg::HighResNoteOn {
+                note: 62,
+                velocity: 0x3FFF,
+            },
+        };
+        assert_eq!(high_res.note(), Some(62));
+        assert_eq!(high_res.velocity(), Some(0x3FFF));
+
+        let pressure = msg(ChannelVoiceMsg::PolyPressure {
+            note: 60,
+            pressure: 10,
+        });
+        assert_eq!(pressure.note(), None);
+        assert_eq!(pressure.velocity(), None);
     }
 
     #[test]
@@ -666,6 +913,260 @@ mod tests {
         assert_eq!(msg4, simple_cc_lsb);
     }
 
+    #[test]
+    fn test_one_byte_running_status() {
+        let program = |program| MidiMsg::ChannelVoice {
+            channel: Channel::Ch3,
+            msg: ChannelVoiceMsg::ProgramChange { program },
+        };
+        let pressure = |pressure| MidiMsg::ChannelVoice {
+            channel: Channel::Ch3,
+            msg: ChannelVoiceMsg::ChannelPressure { pressure },
+        };
+
+        let mut ctx = ReceiverContext::new();
+        // A program change followed by two running status program bytes
+        let midi = [0xC2, 5, 6, 7];
+        assert_eq!(
+            MidiMsg::from_midi_with_context(&midi, &mut ctx),
+            Ok((program(5), 2))
+        );
+        // Each running status byte is a whole message, including the last one
+        assert_eq!(
+            MidiMsg::from_midi_with_context(&midi[2..], &mut ctx),
+            Ok((program(6), 1))
+        );
+        assert_eq!(
+            MidiMsg::from_midi_with_context(&midi[3..], &mut ctx),
+            Ok((program(7), 1))
+        );
+
+        let midi = [0xD2, 0x10, 0x20, 0x30];
+        assert_eq!(
+            MidiMsg::from_callback_bytes(&midi, &mut ctx),
+            Ok(vec![pressure(0x10), pressure(0x20), pressure(0x30)])
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "sysex")]
+    fn test_messages_to_midi_running() {
+        let note_on = |note| MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note,
+                velocity: 100,
+            },
+        };
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::NonCommercial { data: vec![1, 2] },
+        };
+        let clock = MidiMsg::SystemRealTime {
+            msg: SystemRealTimeMsg::TimingClock,
+        };
+        let msgs = [
+            note_on(60),
+            note_on(62),
+            // A sysex resets the running status
+            sysex,
+            note_on(64),
+            // A real-time message does not
+            clock,
+            note_on(65),
+        ];
+        let midi = MidiMsg::messages_to_midi_running(&msgs);
+        assert_eq!(
+            midi,
+            vec![0x90, 60, 100, 62, 100, 0xF0, 0x7D, 1, 2, 0xF7, 0x90, 64, 100, 0xF8, 65, 100]
+        );
+
+        let mut ctx = ReceiverContext::new();
+        let mut offset = 0;
+        let mut read = vec![];
+        while offset < midi.len() {
+            let (msg, len) = MidiMsg::from_midi_with_context(&midi[offset..], &mut ctx).unwrap();
+            read.push(msg);
+            offset += len;
+        }
+        assert_eq!(read, msgs);
+    }
+
+    #[test]
+    fn test_bank_select_msb_only() {
+        // Bank Select MSB 3, Program Change 5, on channel 2
+        let midi = vec![0xB1, 0x00, 0x03, 0xC1, 0x05];
+        let mut ctx = ReceiverContext::new().complex_cc();
+        let (msg1, len1) = MidiMsg::from_midi_with_context(&midi, &mut ctx).unwrap();
+        assert_eq!(len1, 3);
+        assert_eq!(
+            msg1,
+            MidiMsg::ChannelVoice {
+                channel: Ch2,
+                msg: ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::BankSelect(3 << 7),
+                },
+            }
+        );
+        let (msg2, len2) = MidiMsg::from_midi_with_context(&midi[len1..], &mut ctx).unwrap();
+        assert_eq!(len2, 2);
+        assert_eq!(
+            msg2,
+            MidiMsg::ChannelVoice {
+                channel: Ch2,
+                msg: ChannelVoiceMsg::ProgramChange { program: 5 },
+            }
+        );
+        assert_eq!(ctx.bank_select(Ch2), Some(3 << 7));
+        assert_eq!(ctx.bank_select(Ch1), None);
+
+        // The bank is also tracked for simple CCs, including a following LSB
+        let midi = vec![0xB1, 0x00, 0x03, 0xC1, 0x05, 0xB1, 0x20, 0x01];
+        let mut ctx = ReceiverContext::new();
+        let (_, len1) = MidiMsg::from_midi_with_context(&midi, &mut ctx).unwrap();
+        assert_eq!(ctx.bank_select(Ch2), Some(3 << 7));
+        let (_, len2) = MidiMsg::from_midi_with_context(&midi[len1..], &mut ctx).unwrap();
+        MidiMsg::from_midi_with_context(&midi[len1 + len2..], &mut ctx).unwrap();
+        assert_eq!(ctx.bank_select(Ch2), Some((3 << 7) + 1));
+    }
+
+    #[test]
+    fn test_parameter_data_increment() {
+        let cc = |control| {
+            MidiMsg::ChannelVoice {
+                channel: Ch3,
+                msg: ChannelVoiceMsg::ControlChange { control },
+            }
+            .to_midi()
+        };
+        let read_all = |midi: &[u8], ctx: &mut ReceiverContext| {
+            let mut offset = 0;
+            while offset < midi.len() {
+                let (_, len) = MidiMsg::from_midi_with_context(&midi[offset..], ctx).unwrap();
+                offset += len;
+            }
+        };
+
+        for mut ctx in [ReceiverContext::new(), ReceiverContext::new().complex_cc()] {
+            // Select Modulation Depth Range and set it to 0x200
+            let mut midi = cc(ControlChange::Parameter(Parameter::ModulationDepthRange));
+            read_all(&midi, &mut ctx);
+            assert_eq!(
+                ctx.selected_parameter(Ch3),
+                Some(Parameter::ModulationDepthRange)
+            );
+            assert_eq!(ctx.parameter_value(Ch3), None);
+            midi = cc(ControlChange::DataEntry(0x200));
+            read_all(&midi, &mut ctx);
+            assert_eq!(ctx.parameter_value(Ch3), Some(0x200));
+
+            // The value of increment and decrement messages is ignored
+            midi = cc(ControlChange::DataIncrement(0));
+            midi.extend(cc(ControlChange::DataIncrement(5)));
+            midi.extend(cc(ControlChange::DataIncrement(0)));
+            midi.extend(cc(ControlChange::DataDecrement(0)));
+            read_all(&midi, &mut ctx);
+            assert_eq!(ctx.parameter_value(Ch3), Some(0x202));
+            assert_eq!(ctx.parameter_value(Ch1), None);
+
+            // Coarse tuning is stepped by its MSB, and is clamped
+            midi = cc(ControlChange::Parameter(Parameter::CoarseTuningEntry(63)));
+            midi.extend(cc(ControlChange::DataIncrement(0)));
+            read_all(&midi, &mut ctx);
+            assert_eq!(ctx.selected_parameter(Ch3), Some(Parameter::CoarseTuning));
+            assert_eq!(ctx.parameter_value(Ch3), Some(0x3F80));
+            midi = cc(ControlChange::DataIncrement(0));
+            read_all(&midi, &mut ctx);
+            assert_eq!(ctx.parameter_value(Ch3), Some(0x3F80));
+
+            // Selecting the null parameter stops tracking
+            midi = cc(ControlChange::Parameter(Parameter::Null));
+            midi.extend(cc(ControlChange::DataEntry(0x100)));
+            read_all(&midi, &mut ctx);
+            assert_eq!(ctx.selected_parameter(Ch3), Some(Parameter::Null));
+            assert_eq!(ctx.parameter_value(Ch3), None);
+        }
+    }
+
+    #[test]
+    fn test_from_midi_with_bytes() {
+        // A Volume MSB without an LSB, followed by a note
+        let midi = vec![0xB0, 0x07, 0x10, 0x90, 0x3C, 0x40];
+        let mut ctx = ReceiverContext::new().complex_cc();
+        let (msg, bytes) = MidiMsg::from_midi_with_bytes(&midi, &mut ctx).unwrap();
+        assert_eq!(
+            msg,
+            MidiMsg::ChannelVoice {
+                channel: Ch1,
+                msg: ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::Volume(0x10 << 7),
+                },
+            }
+        );
+        assert_eq!(bytes, &midi[..3]);
+        // Reserializing adds an LSB
+        assert_eq!(msg.to_midi(), vec![0xB0, 0x07, 0x10, 0x27, 0x00]);
+
+        let (_, bytes) = MidiMsg::from_midi_with_bytes(&midi[3..], &mut ctx).unwrap();
+        assert_eq!(bytes, &midi[3..]);
+    }
+
+    #[test]
+    fn test_serializes_same_as() {
+        let cc = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC {
+                    control: 80,
+                    value: 10,
+                },
+            },
+        };
+        let gp5 = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::ControlChange {
+                control: ControlChange::GeneralPurpose5(10),
+            },
+        };
+        assert_ne!(cc, gp5);
+        assert!(cc.serializes_same_as(&gp5));
+
+        // The channel of a running status message is not serialized
+        let running_ch1 = MidiMsg::RunningChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 0,
+            },
+        };
+        let running_ch2 = MidiMsg::RunningChannelVoice {
+            channel: Channel::Ch2,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 0,
+            },
+        };
+        assert_ne!(running_ch1, running_ch2);
+        assert!(running_ch1.serializes_same_as(&running_ch2));
+
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 60,
+                velocity: 0,
+            },
+        };
+        let note_off = MidiMsg::ChannelVoice {
+            channel: Channel::Ch1,
+            msg: ChannelVoiceMsg::NoteOff {
+                note: 60,
+                velocity: 0,
+            },
+        };
+        assert!(!note_on.serializes_same_as(&note_off));
+        assert!(!note_on.serializes_same_as(&running_ch1));
+        assert!(note_on.serializes_same_as(&note_on.clone()));
+    }
+
     #[test]
     fn test_next_message() {
         let mut midi = vec![];
@@ -692,4 +1193,122 @@ mod tests {
         assert_eq!(next_message(&midi[1..]), Some(first_message_len - 1));
         assert_eq!(next_message(&midi[first_message_len..]), None);
     }
+
+    #[test]
+    fn test_merge_high_res_cc() {
+        let cc = |channel, control, value| MidiMsg::ChannelVoice {
+            channel,
+            msg: ChannelVoiceMsg::ControlChange {
+                control: ControlChange::CC { control, value },
+            },
+        };
+
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch3, 7, 0x40), &cc(Ch3, 39, 0x01)),
+            Some(MidiMsg::ChannelVoice {
+                channel: Ch3,
+                msg: ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::Volume(0x2001)
+                }
+            })
+        );
+        // Undefined controls are merged too
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch1, 3, 0x7F), &cc(Ch1, 35, 0x7F)),
+            Some(MidiMsg::ChannelVoice {
+                channel: Ch1,
+                msg: ChannelVoiceMsg::ControlChange {
+                    control: ControlChange::CCHighRes {
+                        control1: 3,
+                        control2: 35,
+                        value: 0x3FFF
+                    }
+                }
+            })
+        );
+
+        // Mismatched pairs
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch3, 7, 0x40), &cc(Ch3, 40, 0x01)),
+            None
+        );
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch3, 7, 0x40), &cc(Ch4, 39, 0x01)),
+            None
+        );
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch3, 39, 0x01), &cc(Ch3, 7, 0x40)),
+            None
+        );
+        assert_eq!(
+            merge_high_res_cc(&cc(Ch3, 64, 0x40), &cc(Ch3, 96, 0x01)),
+            None
+        );
+        assert_eq!(
+            merge_high_res_cc(
+                &MidiMsg::SystemRealTime {
+                    msg: SystemRealTimeMsg::TimingClock
+                },
+                &cc(Ch3, 39, 0x01)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_from_callback_bytes() {
+        let note_on = MidiMsg::ChannelVoice {
+            channel: Channel::Ch2,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 0x40,
+                velocity: 0x50,
+            },
+        };
+        let running_note_on = MidiMsg::RunningChannelVoice {
+            channel: Channel::Ch2,
+            msg: ChannelVoiceMsg::NoteOn {
+                note: 0x41,
+                velocity: 0x50,
+            },
+        };
+        let clock = MidiMsg::SystemRealTime {
+            msg: SystemRealTimeMsg::TimingClock,
+        };
+        let mut midi = vec![];
+        note_on.extend_midi(&mut midi);
+        running_note_on.extend_midi(&mut midi);
+        clock.extend_midi(&mut midi);
+        #[cfg(feature = "sysex")]
+        let sysex = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::NonCommercial {
+                data: vec![0x01, 0x02, 0x03],
+            },
+        };
+        #[cfg(feature = "sysex")]
+        sysex.extend_midi(&mut midi);
+
+        let mut ctx = ReceiverContext::new();
+        let msgs = MidiMsg::from_callback_bytes(&midi, &mut ctx).expect("Not an error");
+        // Running status messages are read as regular channel messages
+        let mut expected = vec![
+            note_on.clone(),
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 0x41,
+                    velocity: 0x50,
+                },
+            },
+            clock,
+        ];
+        #[cfg(feature = "sysex")]
+        expected.push(sysex);
+        assert_eq!(msgs, expected);
+
+        assert_eq!(MidiMsg::from_callback_bytes(&[], &mut ctx), Ok(vec![]));
+        assert_eq!(
+            MidiMsg::from_callback_bytes(&[0x91, 0x40], &mut ctx),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
 }
@@ -33,6 +33,15 @@ pub enum ParseError {
     UndefinedSystemRealTimeMessage(u8),
     /// Encountered an undefined system exclusive message
     UndefinedSystemExclusiveMessage(Option<u8>),
+    /// The output buffer was too small to hold the serialized message.
+    BufferTooSmall,
+    /// The checksum of a system exclusive message did not match its contents.
+    ChecksumMismatch {
+        /// The checksum computed from the message.
+        computed: u8,
+        /// The checksum that was received.
+        received: u8,
+    },
 }
 
 #[cfg(feature = "std")]
@@ -92,6 +101,14 @@ impl fmt::Display for ParseError {
                     )
                 }
             }
+            Self::BufferTooSmall => {
+                write!(f, "The output buffer was too small to hold the message")
+            }
+            Self::ChecksumMismatch { computed, received } => write!(
+                f,
+                "Checksum mismatch: computed {:#04x} but received {:#04x}",
+                computed, received
+            ),
         }
     }
 }
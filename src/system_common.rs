@@ -28,6 +28,13 @@ pub enum SystemCommonMsg {
     SongSelect(u8),
     /// Request that the oscillators of an analog synth be tuned.
     TuneRequest,
+    /// The undefined system common status byte 0xF4.
+    ///
+    /// This is reserved by the spec, but some hardware uses it anyway. It is treated as a
+    /// message without any data bytes.
+    UndefinedF4,
+    /// The undefined system common status byte 0xF5. See [`SystemCommonMsg::UndefinedF4`].
+    UndefinedF5,
 }
 
 impl SystemCommonMsg {
@@ -74,6 +81,8 @@ impl SystemCommonMsg {
                 v.push(to_u7(*song));
             }
             SystemCommonMsg::TuneRequest => v.push(0xF6),
+            SystemCommonMsg::UndefinedF4 => v.push(0xF4),
+            SystemCommonMsg::UndefinedF5 => v.push(0xF5),
         }
     }
 
@@ -108,7 +117,12 @@ impl SystemCommonMsg {
             }
             Some(0xF2) => Ok((Self::SongPosition(u14_from_midi(&m[1..])?), 3)),
             Some(0xF3) => Ok((Self::SongSelect(u7_from_midi(&m[1..])?), 2)),
-            Some(0xF6) => Ok((Self::TuneRequest, 1)),
+            Some(0xF4) => Ok((Self::UndefinedF4, 1)),
+            Some(0xF5) => Ok((Self::UndefinedF5, 1)),
+            Some(0xF6) => {
+                ctx.tune_requested = true;
+                Ok((Self::TuneRequest, 1))
+            }
             Some(0xF7) => Err(ParseError::UnexpectedEndOfSystemExclusiveFlag),
             Some(x) => Err(ParseError::UndefinedSystemCommonMessage(*x)),
             _ => panic!("Should not be reachable"),
@@ -260,4 +274,43 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn test_tune_requested() {
+        let mut ctx = ReceiverContext::new();
+        assert!(!ctx.tune_requested());
+        MidiMsg::from_midi_with_context(&[0xF8], &mut ctx).unwrap();
+        assert!(!ctx.tune_requested());
+
+        MidiMsg::from_midi_with_context(&[0xF6], &mut ctx).unwrap();
+        assert!(ctx.tune_requested());
+        // The flag stays set until it is cleared
+        MidiMsg::from_midi_with_context(&[0x90, 0x40, 0x7F], &mut ctx).unwrap();
+        assert!(ctx.tune_requested());
+        ctx.clear_tune_request();
+        assert!(!ctx.tune_requested());
+    }
+
+    #[test]
+    fn serde_undefined() {
+        for (msg, b) in [
+            (SystemCommonMsg::UndefinedF4, 0xF4),
+            (SystemCommonMsg::UndefinedF5, 0xF5),
+        ] {
+            let undefined = MidiMsg::SystemCommon { msg };
+            assert_eq!(undefined.to_midi(), vec![b]);
+            test_serialization(undefined, &mut ReceiverContext::new());
+        }
+
+        // Distinct from the undefined real-time messages
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF9]),
+            Ok((
+                MidiMsg::SystemRealTime {
+                    msg: SystemRealTimeMsg::UndefinedF9,
+                },
+                1
+            ))
+        );
+    }
 }
@@ -1,6 +1,7 @@
 use crate::message::Channel;
 use crate::parse_error::*;
 use crate::util::*;
+use alloc::vec;
 use alloc::vec::Vec;
 
 /// Allows for the selection of the destination of a channel pressure/poly key pressure message.
@@ -15,6 +16,24 @@ pub struct ControllerDestination {
 }
 
 impl ControllerDestination {
+    /// The routing of channel pressure or poly key pressure on `channel` defined by General MIDI 2, for when no
+    /// destination has been configured: every [`ControlledParameter`] is listed, with a range that leaves it
+    /// unaffected by the pressure. I.e. pitch, filter cutoff and amplitude are given their center value of `0x40`
+    /// (no change), and the LFO depths are given `0x00`.
+    pub fn gm2_default(channel: Channel) -> Self {
+        Self {
+            channel,
+            param_ranges: vec![
+                (ControlledParameter::PitchControl, 0x40),
+                (ControlledParameter::FilterCutoffControl, 0x40),
+                (ControlledParameter::AmplitudeControl, 0x40),
+                (ControlledParameter::LFOPitchDepth, 0x00),
+                (ControlledParameter::LFOFilterDepth, 0x00),
+                (ControlledParameter::LFOAmplitudeDepth, 0x00),
+            ],
+        }
+    }
+
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         v.push(self.channel as u8);
         for (p, r) in self.param_ranges.iter() {
@@ -23,9 +42,16 @@ impl ControllerDestination {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("ControllerDestination"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let channel = Channel::from_u8(u7_from_midi(m)?);
+        let (param_ranges, len) = param_ranges_from_midi(&m[1..])?;
+        Ok((
+            Self {
+                channel,
+                param_ranges,
+            },
+            len + 1,
+        ))
     }
 }
 
@@ -57,13 +83,34 @@ impl ControlChangeControllerDestination {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented(
-            "ControlChangeControllerDestination",
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let channel = Channel::from_u8(u7_from_midi(m)?);
+        let control_number = u7_from_midi(&m[1..])?;
+        let (param_ranges, len) = param_ranges_from_midi(&m[2..])?;
+        Ok((
+            Self {
+                channel,
+                control_number,
+                param_ranges,
+            },
+            len + 2,
         ))
     }
 }
+
+/// Parse (ControlledParameter, range) pairs until the end of `m`.
+fn param_ranges_from_midi(m: &[u8]) -> Result<(Vec<(ControlledParameter, u8)>, usize), ParseError> {
+    let mut param_ranges = vec![];
+    let mut i = 0;
+    while i < m.len() {
+        let param = ControlledParameter::from_midi(&m[i..])?;
+        let range = u7_from_midi(&m[i + 1..])?;
+        param_ranges.push((param, range));
+        i += 2;
+    }
+    Ok((param_ranges, i))
+}
+
 /// The parameters that can be controlled by [`ControllerDestination`] or
 /// [`ControlChangeControllerDestination`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -76,6 +123,20 @@ pub enum ControlledParameter {
     LFOAmplitudeDepth = 5,
 }
 
+impl ControlledParameter {
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        match u7_from_midi(m)? {
+            0 => Ok(Self::PitchControl),
+            1 => Ok(Self::FilterCutoffControl),
+            2 => Ok(Self::AmplitudeControl),
+            3 => Ok(Self::LFOPitchDepth),
+            4 => Ok(Self::LFOFilterDepth),
+            5 => Ok(Self::LFOAmplitudeDepth),
+            _ => Err(ParseError::Invalid("Unknown ControlledParameter")),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::*;
@@ -107,4 +168,81 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn deserialize_controller_destination() {
+        let mut ctx = ReceiverContext::new();
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::ChannelPressureControllerDestination(
+                        ControllerDestination {
+                            channel: Channel::Ch3,
+                            param_ranges: vec![
+                                (ControlledParameter::AmplitudeControl, 0x7F),
+                                (ControlledParameter::LFOPitchDepth, 0x10),
+                            ],
+                        },
+                    ),
+                },
+            },
+            &mut ctx,
+        );
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::PolyphonicKeyPressureControllerDestination(
+                        ControllerDestination {
+                            channel: Channel::Ch16,
+                            param_ranges: vec![],
+                        },
+                    ),
+                },
+            },
+            &mut ctx,
+        );
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::Device(5),
+                    msg: UniversalRealTimeMsg::ControlChangeControllerDestination(
+                        ControlChangeControllerDestination {
+                            channel: Channel::Ch2,
+                            control_number: 0x50,
+                            param_ranges: vec![
+                                (ControlledParameter::PitchControl, 0x42),
+                                (ControlledParameter::FilterCutoffControl, 0x60),
+                            ],
+                        },
+                    ),
+                },
+            },
+            &mut ctx,
+        );
+
+        // A parameter without a range
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x09, 0x01, 0x00, 0x02, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_gm2_default() {
+        let default = ControllerDestination::gm2_default(Channel::Ch4);
+        assert_eq!(default.channel, Channel::Ch4);
+        assert_eq!(
+            default.param_ranges,
+            vec![
+                (ControlledParameter::PitchControl, 0x40),
+                (ControlledParameter::FilterCutoffControl, 0x40),
+                (ControlledParameter::AmplitudeControl, 0x40),
+                (ControlledParameter::LFOPitchDepth, 0),
+                (ControlledParameter::LFOFilterDepth, 0),
+                (ControlledParameter::LFOAmplitudeDepth, 0),
+            ]
+        );
+    }
 }
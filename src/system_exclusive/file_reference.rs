@@ -2,6 +2,7 @@ use crate::parse_error::*;
 use crate::util::*;
 use alloc::vec::Vec;
 use bstr::BString;
+use micromath::F32Ext;
 
 /// The set of messages used for accessing files on a shared file system or network
 /// so they can be used to play sounds without transferring the file contents.
@@ -45,6 +46,32 @@ pub enum FileReferenceMsg {
 }
 
 impl FileReferenceMsg {
+    /// An [`Open`](Self::Open) message, checking that `url` can be sent without being altered.
+    ///
+    /// Returns [`ParseError::Invalid`] if `url` is longer than 260 characters, or contains characters
+    /// that are not printable ASCII, which would otherwise be truncated or corrupted when serialized.
+    pub fn open_checked(
+        ctx: u16,
+        file_type: FileReferenceType,
+        url: &str,
+    ) -> Result<Self, ParseError> {
+        if url.len() > 260 {
+            return Err(ParseError::Invalid(
+                "A FileReferenceMsg url may not be longer than 260 characters",
+            ));
+        }
+        if !url.bytes().all(|b| b.is_ascii_graphic() || b == b' ') {
+            return Err(ParseError::Invalid(
+                "A FileReferenceMsg url may only contain printable ASCII characters",
+            ));
+        }
+        Ok(Self::Open {
+            ctx,
+            file_type,
+            url: BString::from(url),
+        })
+    }
+
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         match self {
             Self::Open {
@@ -86,9 +113,62 @@ impl FileReferenceMsg {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("FileReferenceMsg"))
+    /// Parse a file reference message, starting from its sub-ID #2.
+    ///
+    /// A `SelectContents` message does not say what type of file it refers to, so its map is read
+    /// as described in [`SelectMap`].
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        if m.len() < 5 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let ctx = u14_from_midi(&m[1..])?;
+        let len = u14_from_midi(&m[3..])? as usize;
+        if m.len() < 5 + len {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let body = &m[5..5 + len];
+        let msg = match m[0] {
+            0x01 => {
+                let (file_type, url, _) = Self::file_from_midi(body)?;
+                Self::Open {
+                    ctx,
+                    file_type,
+                    url,
+                }
+            }
+            0x02 => Self::SelectContents {
+                ctx,
+                map: SelectMap::from_midi(body, None)?,
+            },
+            0x03 => {
+                let (file_type, url, url_len) = Self::file_from_midi(body)?;
+                Self::OpenSelectContents {
+                    ctx,
+                    file_type,
+                    url,
+                    map: SelectMap::from_midi(&body[url_len..], Some(file_type))?,
+                }
+            }
+            0x04 => Self::Close { ctx },
+            _ => return Err(ParseError::Invalid("Unknown FileReferenceMsg")),
+        };
+        Ok((msg, 5 + len))
+    }
+
+    /// Read a file type and a null terminated url, returning the number of bytes read
+    fn file_from_midi(m: &[u8]) -> Result<(FileReferenceType, BString, usize), ParseError> {
+        let file_type = FileReferenceType::from_midi(m)?;
+        let url_len = m[4..]
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(ParseError::Invalid(
+                "FileReferenceMsg url is not null terminated",
+            ))?;
+        Ok((
+            file_type,
+            BString::from(&m[4..4 + url_len]),
+            4 + url_len + 1,
+        ))
     }
 }
 
@@ -108,6 +188,16 @@ impl FileReferenceType {
             Self::WAV => b"WAV ".iter().for_each(|c| v.push(*c)),
         }
     }
+
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        match m.get(0..4) {
+            Some(b"DLS ") => Ok(Self::DLS),
+            Some(b"SF2 ") => Ok(Self::SF2),
+            Some(b"WAV ") => Ok(Self::WAV),
+            Some(_) => Err(ParseError::Invalid("Unknown FileReferenceType")),
+            None => Err(ParseError::UnexpectedEnd),
+        }
+    }
 }
 
 /// How to map a `DLS` or `SF2` file for MIDI reference. Used by [`SelectMap`].
@@ -159,6 +249,22 @@ impl SoundFileMap {
         v.push(flags);
         push_u7(self.volume, v);
     }
+
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        if m.len() < 8 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let flags = u7_from_midi(&m[6..])?;
+        Ok(Self {
+            dst_bank: u14_from_midi(m)?,
+            dst_prog: u7_from_midi(&m[2..])?,
+            src_bank: u14_from_midi(&m[3..])?,
+            src_prog: u7_from_midi(&m[5..])?,
+            src_drum: flags & 0b01 != 0,
+            dst_drum: flags & 0b10 != 0,
+            volume: u7_from_midi(&m[7..])?,
+        })
+    }
 }
 
 /// How to map a `WAV` file for MIDI reference. Used by [`SelectMap`].
@@ -175,13 +281,28 @@ pub struct WAVMap {
     /// Highest MIDI note that plays
     pub hikey: u8,
     /// Fine tuning offset -8192-8191, representing the fractional cents to shift
-    /// in 1/8192ths of a cent
+    /// in 1/8192ths of a cent. See [`WAVMap::fine_cents`].
     pub fine: i16,
     /// Initial volume 0-127
     pub volume: u8,
 }
 
 impl WAVMap {
+    /// Create a default `WAVMap` with a fine tuning offset in cents.
+    ///
+    /// The offset is clamped to the representable range of -1 to +8191/8192 cents, and rounded to the nearest 1/8192th of a cent.
+    pub fn from_fine_cents(cents: f32) -> Self {
+        Self {
+            fine: F32Ext::round(cents.clamp(-1.0, 1.0) * 8192.0).clamp(-8192.0, 8191.0) as i16,
+            ..Default::default()
+        }
+    }
+
+    /// The fine tuning offset in cents.
+    pub fn fine_cents(&self) -> f32 {
+        self.fine.clamp(-8192, 8191) as f32 / 8192.0
+    }
+
     fn extend_midi(&self, v: &mut Vec<u8>) {
         push_u14(self.dst_bank, v);
         push_u7(self.dst_prog, v);
@@ -193,6 +314,21 @@ impl WAVMap {
         v.push(msb);
         push_u7(self.volume, v);
     }
+
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        if m.len() < 9 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        Ok(Self {
+            dst_bank: u14_from_midi(m)?,
+            dst_prog: u7_from_midi(&m[2..])?,
+            base: u7_from_midi(&m[3..])?,
+            lokey: u7_from_midi(&m[4..])?,
+            hikey: u7_from_midi(&m[5..])?,
+            fine: i14_from_u7s(u7_from_midi(&m[7..])?, u7_from_midi(&m[6..])?),
+            volume: u7_from_midi(&m[8..])?,
+        })
+    }
 }
 
 impl Default for WAVMap {
@@ -210,6 +346,11 @@ impl Default for WAVMap {
 }
 
 /// How to map a file for MIDI reference. Used by [`FileReferenceMsg::SelectContents`].
+///
+/// When deserializing a [`FileReferenceMsg::SelectContents`], which does not include the type of the
+/// file being mapped, the kind of map is inferred from its length. A 9 byte map is ambiguous: it is
+/// read as a `SoundFile` map with a single `SoundFileMap` if it starts with a count of 1, otherwise
+/// as a `WAV` map.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SelectMap {
     /// Used for DLS or SF2 files. No more than 127 `SoundFileMap`s.
@@ -248,7 +389,6 @@ impl SelectMap {
                 src_drum,
             } => {
                 map.extend_midi(v);
-                v.push(0); // count
                 v.push(0); // Extension ID 1
                 v.push(1); // Extension ID 2
                 v.push(3); // len
@@ -284,6 +424,63 @@ impl SelectMap {
         }
     }
 
+    /// Read a map that spans all of `m`, given the type of the file it refers to, if known.
+    fn from_midi(m: &[u8], file_type: Option<FileReferenceType>) -> Result<Self, ParseError> {
+        let is_wav = match file_type {
+            Some(file_type) => file_type == FileReferenceType::WAV,
+            None => m.len() == 15 || (m.len() == 9 && m[0] != 1),
+        };
+        if is_wav {
+            match m.len() {
+                9 => Ok(Self::WAV(WAVMap::from_midi(m)?)),
+                15 => {
+                    let (bank_offset, src_drum) = Self::bank_offset_from_midi(&m[9..])?;
+                    Ok(Self::WAVBankOffset {
+                        map: WAVMap::from_midi(m)?,
+                        bank_offset,
+                        src_drum,
+                    })
+                }
+                _ => Err(ParseError::Invalid("Invalid length of a WAV SelectMap")),
+            }
+        } else {
+            let count = u7_from_midi(m)? as usize;
+            if count == 0 && m.len() == 7 {
+                let (bank_offset, src_drum) = Self::bank_offset_from_midi(&m[1..])?;
+                Ok(Self::SoundFileBankOffset {
+                    bank_offset,
+                    src_drum,
+                })
+            } else if m.len() == 1 + count * 8 {
+                let mut maps = Vec::with_capacity(count);
+                for i in 0..count {
+                    maps.push(SoundFileMap::from_midi(&m[1 + i * 8..])?);
+                }
+                Ok(Self::SoundFile(maps))
+            } else {
+                Err(ParseError::Invalid(
+                    "Invalid length of a SoundFile SelectMap",
+                ))
+            }
+        }
+    }
+
+    /// Read the CA-028 bank offset extension
+    fn bank_offset_from_midi(m: &[u8]) -> Result<(u16, bool), ParseError> {
+        match m.get(0..3) {
+            Some([0, 1, 3]) => (),
+            Some(_) => {
+                return Err(ParseError::Invalid(
+                    "Unknown SelectMap extension, expected a bank offset",
+                ))
+            }
+            None => return Err(ParseError::UnexpectedEnd),
+        }
+        let bank_offset = u14_from_midi(&m[3..])?;
+        let flags = u7_from_midi(&m[5..])?;
+        Ok((bank_offset, flags & 0b01 != 0))
+    }
+
     fn len(&self) -> usize {
         match self {
             Self::WAV(_) => 9,
@@ -343,4 +540,143 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn deserialize_file_reference_msg() {
+        let mut ctx = ReceiverContext::new();
+        let file_reference = |msg| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::Device(5),
+                msg: UniversalNonRealTimeMsg::FileReference(msg),
+            },
+        };
+        let wav_map = WAVMap {
+            dst_bank: 300,
+            dst_prog: 4,
+            base: 62,
+            lokey: 10,
+            hikey: 100,
+            fine: -2000,
+            volume: 100,
+        };
+
+        test_serialization(
+            file_reference(FileReferenceMsg::Open {
+                ctx: 1000,
+                file_type: FileReferenceType::SF2,
+                url: BString::from("file://piano.sf2"),
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::Close { ctx: 1000 }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::SelectContents {
+                ctx: 3,
+                map: SelectMap::SoundFileBankOffset {
+                    bank_offset: 200,
+                    src_drum: true,
+                },
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::OpenSelectContents {
+                ctx: 3,
+                file_type: FileReferenceType::SF2,
+                url: BString::from("file://drums.sf2"),
+                map: SelectMap::SoundFileBankOffset {
+                    bank_offset: 16383,
+                    src_drum: false,
+                },
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::OpenSelectContents {
+                ctx: 44,
+                file_type: FileReferenceType::DLS,
+                url: BString::from("file://foo.dls"),
+                map: SelectMap::SoundFile(vec![
+                    SoundFileMap {
+                        dst_bank: 1 << 10,
+                        src_prog: 1,
+                        ..Default::default()
+                    },
+                    SoundFileMap {
+                        dst_drum: true,
+                        src_drum: true,
+                        volume: 5,
+                        ..Default::default()
+                    },
+                ]),
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::OpenSelectContents {
+                ctx: 44,
+                file_type: FileReferenceType::WAV,
+                url: BString::from("file://foo.wav"),
+                map: SelectMap::WAVBankOffset {
+                    map: wav_map,
+                    bank_offset: 7,
+                    src_drum: true,
+                },
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            file_reference(FileReferenceMsg::SelectContents {
+                ctx: 44,
+                map: SelectMap::WAV(wav_map),
+            }),
+            &mut ctx,
+        );
+    }
+
+    #[test]
+    fn test_open_checked() {
+        assert_eq!(
+            FileReferenceMsg::open_checked(3, FileReferenceType::WAV, "file://sounds/kick.wav"),
+            Ok(FileReferenceMsg::Open {
+                ctx: 3,
+                file_type: FileReferenceType::WAV,
+                url: BString::from("file://sounds/kick.wav"),
+            })
+        );
+        let url = "a".repeat(260);
+        assert!(FileReferenceMsg::open_checked(3, FileReferenceType::WAV, &url).is_ok());
+
+        let url = "a".repeat(261);
+        assert_eq!(
+            FileReferenceMsg::open_checked(3, FileReferenceType::WAV, &url),
+            Err(ParseError::Invalid(
+                "A FileReferenceMsg url may not be longer than 260 characters"
+            ))
+        );
+        assert!(
+            FileReferenceMsg::open_checked(3, FileReferenceType::WAV, "file://kické.wav").is_err()
+        );
+        // The null character would terminate the url early
+        assert!(FileReferenceMsg::open_checked(3, FileReferenceType::WAV, "file://a\0b").is_err());
+    }
+
+    #[test]
+    fn test_wav_map_fine_cents() {
+        let map = WAVMap::from_fine_cents(0.5);
+        assert_eq!(map.fine, 4096);
+        assert_eq!(map.fine_cents(), 0.5);
+        assert_eq!(map.volume, WAVMap::default().volume);
+
+        assert_eq!(WAVMap::from_fine_cents(-0.25).fine, -2048);
+        // +50 cents is outside of the representable range, so it is clamped
+        let map = WAVMap::from_fine_cents(50.0);
+        assert_eq!(map.fine, 8191);
+        assert_eq!(map.fine_cents(), 8191.0 / 8192.0);
+        assert_eq!(WAVMap::from_fine_cents(-50.0).fine, -8192);
+        assert_eq!(WAVMap::from_fine_cents(-50.0).fine_cents(), -1.0);
+    }
 }
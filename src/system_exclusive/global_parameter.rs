@@ -154,9 +154,42 @@ impl GlobalParameterControl {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("GlobalParameterControl"))
+    /// Parameters run to the end of `m`, so the whole of `m` is always consumed.
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let slot_path_len = u7_from_midi(m)?;
+        let param_id_width = u7_from_midi(&m[1..])?;
+        let value_width = u7_from_midi(&m[2..])?;
+        let mut i = 3;
+
+        let mut slot_paths = vec![];
+        for _ in 0..slot_path_len {
+            let (slot_path, len) = SlotPath::from_midi(&m[i..])?;
+            slot_paths.push(slot_path);
+            i += len;
+        }
+
+        let mut params = vec![];
+        while i < m.len() {
+            if param_id_width == 0 || value_width == 0 {
+                return Err(ParseError::Invalid(
+                    "GlobalParameterControl parameters must have a non-zero width",
+                ));
+            }
+            let (param, len) =
+                GlobalParameter::from_midi_with_limits(&m[i..], param_id_width, value_width)?;
+            params.push(param);
+            i += len;
+        }
+
+        Ok((
+            Self {
+                slot_paths,
+                param_id_width,
+                value_width,
+                params,
+            },
+            i,
+        ))
     }
 }
 
@@ -188,9 +221,15 @@ impl SlotPath {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("SlotPath"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let msb = u7_from_midi(m)?;
+        let lsb = u7_from_midi(&m[1..])?;
+        let slot_path = match (msb, lsb) {
+            (1, 1) => Self::Reverb,
+            (1, 2) => Self::Chorus,
+            (a, b) => Self::Unregistered(a, b),
+        };
+        Ok((slot_path, 2))
     }
 }
 
@@ -226,9 +265,26 @@ impl GlobalParameter {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("GlobalParameter"))
+    pub(crate) fn from_midi_with_limits(
+        m: &[u8],
+        param_id_width: u8,
+        value_width: u8,
+    ) -> Result<(Self, usize), ParseError> {
+        let (id_width, value_width) = (param_id_width as usize, value_width as usize);
+        if m.len() < id_width + value_width {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let mut id = Vec::with_capacity(id_width);
+        for i in 0..id_width {
+            // MSB first
+            id.push(u7_from_midi(&m[i..])?);
+        }
+        let mut value = Vec::with_capacity(value_width);
+        for i in (0..value_width).rev() {
+            // LSB first
+            value.push(u7_from_midi(&m[id_width + i..])?);
+        }
+        Ok((Self { id, value }, id_width + value_width))
     }
 }
 
@@ -316,4 +372,71 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn deserialize_global_parameter() {
+        let mut ctx = ReceiverContext::new();
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::GlobalParameterControl(GlobalParameterControl {
+                        slot_paths: vec![
+                            SlotPath::Unregistered(1, 0x47),
+                            SlotPath::Unregistered(2, 3),
+                        ],
+                        param_id_width: 1,
+                        value_width: 2,
+                        params: vec![
+                            GlobalParameter {
+                                id: vec![4],
+                                value: vec![5, 6],
+                            },
+                            GlobalParameter {
+                                id: vec![4],
+                                value: vec![1, 0],
+                            },
+                        ],
+                    }),
+                },
+            },
+            &mut ctx,
+        );
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::GlobalParameterControl(
+                        GlobalParameterControl::reverb(Some(ReverbType::LargeHall), Some(1.2)),
+                    ),
+                },
+            },
+            &mut ctx,
+        );
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::GlobalParameterControl(GlobalParameterControl {
+                        slot_paths: vec![],
+                        param_id_width: 2,
+                        value_width: 1,
+                        params: vec![],
+                    }),
+                },
+            },
+            &mut ctx,
+        );
+
+        // The last parameter is missing its value MSB
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x4, 0x5, 1, 1, 2, 1, 1, 0, 5, 0, 1, 5, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+        // The slot paths are shorter than declared
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x4, 0x5, 2, 1, 1, 1, 1, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
 }
@@ -19,6 +19,7 @@ pub use show_control::*;
 mod tuning;
 pub use tuning::*;
 
+use alloc::borrow::Cow;
 use alloc::vec::Vec;
 
 use super::general_midi::GeneralMidi;
@@ -100,7 +101,14 @@ impl SystemExclusiveMsg {
         v.push(0xF7);
     }
 
-    fn sysex_bytes_from_midi(m: &[u8], first_byte_is_f0: bool) -> Result<&[u8], ParseError> {
+    /// Returns the payload of the sysex message that starts `m`, along with the number of bytes it spans,
+    /// including the leading 0xF0 (if present) and the trailing 0xF7.
+    ///
+    /// System real-time bytes (0xF8-0xFE) may be interleaved with the payload, and are skipped.
+    fn sysex_bytes_from_midi(
+        m: &[u8],
+        first_byte_is_f0: bool,
+    ) -> Result<(Cow<'_, [u8]>, usize), ParseError> {
         if first_byte_is_f0 && m.first() != Some(&0xF0) {
             return Err(ParseError::UndefinedSystemExclusiveMessage(
                 if let Some(first_byte) = m.first() {
@@ -111,11 +119,20 @@ impl SystemExclusiveMsg {
             ));
         }
         let offset = if first_byte_is_f0 { 1 } else { 0 };
+        let mut has_real_time = false;
         for (i, b) in m[offset..].iter().enumerate() {
             if b == &0xF7 {
-                return Ok(&m[offset..i + offset]);
+                let bytes = &m[offset..i + offset];
+                let payload = if has_real_time {
+                    Cow::Owned(bytes.iter().copied().filter(|b| b <= &127).collect())
+                } else {
+                    Cow::Borrowed(bytes)
+                };
+                return Ok((payload, i + offset + 1));
             }
-            if b > &127 {
+            if (0xF8..=0xFE).contains(b) {
+                has_real_time = true;
+            } else if b > &127 {
                 return Err(ParseError::ByteOverflow);
             }
         }
@@ -126,27 +143,37 @@ impl SystemExclusiveMsg {
         m: &[u8],
         ctx: &mut ReceiverContext,
     ) -> Result<(Self, usize), ParseError> {
-        let m = Self::sysex_bytes_from_midi(m, !ctx.is_smf_sysex)?;
+        let (payload, msg_len) = Self::sysex_bytes_from_midi(m, !ctx.is_smf_sysex)?;
+        let m: &[u8] = &payload;
         match m.get(0) {
             Some(0x7D) => Ok((
                 Self::NonCommercial {
                     data: m[1..].to_vec(),
                 },
-                m.len() + 2,
-            )),
-            Some(0x7E) => Ok((
-                Self::UniversalNonRealTime {
-                    device: DeviceID::from_midi(&m[1..])?,
-                    msg: UniversalNonRealTimeMsg::from_midi(&m[2..])?,
-                },
-                m.len() + 2,
+                msg_len,
             )),
+            Some(0x7E) => {
+                let device = DeviceID::from_midi(&m[1..])?;
+                let msg = UniversalNonRealTimeMsg::from_midi(&m[2..])?;
+                if let UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::Packet { .. }) = msg {
+                    // The checksum covers everything from 0x7E up to itself, as in `extend_midi`
+                    let q = m.len();
+                    let computed = checksum(&m[..q - 1]);
+                    if computed != m[q - 1] {
+                        return Err(ParseError::ChecksumMismatch {
+                            computed,
+                            received: m[q - 1],
+                        });
+                    }
+                }
+                Ok((Self::UniversalNonRealTime { device, msg }, msg_len))
+            }
             Some(0x7F) => Ok((
                 Self::UniversalRealTime {
                     device: DeviceID::from_midi(&m[1..])?,
                     msg: UniversalRealTimeMsg::from_midi(&m[2..], ctx)?,
                 },
-                m.len() + 2,
+                msg_len,
             )),
             Some(_) => {
                 let (id, len) = ManufacturerID::from_midi(m)?;
@@ -155,7 +182,7 @@ impl SystemExclusiveMsg {
                         id,
                         data: m[len..].to_vec(),
                     },
-                    m.len() + 2,
+                    msg_len,
                 ))
             }
             None => Err(crate::ParseError::UnexpectedEnd),
@@ -167,11 +194,41 @@ impl SystemExclusiveMsg {
 /// See [the published list of IDs](https://www.midi.org/specifications-old/item/manufacturer-id-numbers).
 ///
 /// If second byte is None, it is a one-byte ID.
-/// The first byte in a one-byte ID may not be greater than 0x7C.
+/// The first byte in a one-byte ID may not be 0x00 or greater than 0x7C.
+///
+/// When deserializing, only the first byte of a message determines whether its ID is a one-byte ID: a 0x00 marks
+/// the start of a three-byte ID (0x00 followed by the two given bytes), so the data of a message with a one-byte ID
+/// may itself start with 0x00. A one-byte ID of 0x00 is indistinguishable from this escape, and is read back as the
+/// start of a three-byte ID.
+///
+/// Use [`ManufacturerID::new`] to reject invalid IDs. When constructed directly, or through `From<u8>`, a one-byte ID
+/// greater than 0x7C is clamped to 0x7C.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ManufacturerID(pub u8, pub Option<u8>);
 
 impl ManufacturerID {
+    /// Create a one-byte ID, when `b` is `None`, or a three-byte ID (0x00 followed by `a` and `b`).
+    ///
+    /// Returns an error if a one-byte ID is not between 0x01 and 0x7C, since 0x7D-0x7F are reserved for non-commercial
+    /// and universal messages, or if either byte of a three-byte ID is greater than 0x7F.
+    pub fn new(a: u8, b: Option<u8>) -> Result<Self, ParseError> {
+        match b {
+            None if !(0x01..=0x7C).contains(&a) => Err(ParseError::Invalid(
+                "A one-byte ManufacturerID must be between 0x01 and 0x7C",
+            )),
+            Some(b) if a > 0x7F || b > 0x7F => Err(ParseError::ByteOverflow),
+            _ => Ok(Self(a, b)),
+        }
+    }
+
+    /// The name of the manufacturer with this ID, if it is one of the well-known IDs from the MMA's list.
+    pub fn name(&self) -> Option<&'static str> {
+        MANUFACTURER_NAMES
+            .iter()
+            .find(|(id, _)| id == self)
+            .map(|(_, name)| *name)
+    }
+
     fn extend_midi(&self, v: &mut Vec<u8>) {
         if let Some(second) = self.1 {
             v.push(0x00);
@@ -197,9 +254,54 @@ impl ManufacturerID {
     }
 }
 
+/// Well-known manufacturer IDs, used by [`ManufacturerID::name`].
+static MANUFACTURER_NAMES: &[(ManufacturerID, &str)] = &[
+    (ManufacturerID(0x01, None), "Sequential Circuits"),
+    (ManufacturerID(0x04, None), "Moog"),
+    (ManufacturerID(0x06, None), "Lexicon"),
+    (ManufacturerID(0x07, None), "Kurzweil"),
+    (ManufacturerID(0x08, None), "Fender"),
+    (ManufacturerID(0x0F, None), "Ensoniq"),
+    (ManufacturerID(0x10, None), "Oberheim"),
+    (ManufacturerID(0x11, None), "Apple"),
+    (ManufacturerID(0x13, None), "Digidesign"),
+    (ManufacturerID(0x18, None), "E-mu"),
+    (ManufacturerID(0x1C, None), "Eventide"),
+    (ManufacturerID(0x29, None), "PPG"),
+    (ManufacturerID(0x2F, None), "Elka"),
+    (ManufacturerID(0x30, None), "Dynacord"),
+    (ManufacturerID(0x33, None), "Clavia"),
+    (ManufacturerID(0x3E, None), "Waldorf"),
+    (ManufacturerID(0x40, None), "Kawai"),
+    (ManufacturerID(0x41, None), "Roland"),
+    (ManufacturerID(0x42, None), "Korg"),
+    (ManufacturerID(0x43, None), "Yamaha"),
+    (ManufacturerID(0x44, None), "Casio"),
+    (ManufacturerID(0x47, None), "Akai"),
+    (ManufacturerID(0x4C, None), "Sony"),
+    (ManufacturerID(0x51, None), "Fostex"),
+    (ManufacturerID(0x52, None), "Zoom"),
+    (ManufacturerID(0x00, Some(0x0E)), "Alesis"),
+    (ManufacturerID(0x00, Some(0x1B)), "Peavey"),
+    (ManufacturerID(0x00, Some(0x3B)), "Mark of the Unicorn"),
+    (ManufacturerID(0x00, Some(0x41)), "Microsoft"),
+    (ManufacturerID(0x00, Some(0x58)), "Atari"),
+    (ManufacturerID(0x00, Some(0x65)), "Turtle Beach"),
+    (ManufacturerID(0x00, Some(0x66)), "Mackie"),
+    (ManufacturerID(0x01, Some(0x05)), "M-Audio"),
+    (ManufacturerID(0x20, Some(0x1F)), "TC Electronic"),
+    (ManufacturerID(0x20, Some(0x29)), "Focusrite/Novation"),
+    (ManufacturerID(0x20, Some(0x32)), "Behringer"),
+    (ManufacturerID(0x20, Some(0x33)), "Access Music"),
+    (ManufacturerID(0x20, Some(0x3C)), "Elektron"),
+    (ManufacturerID(0x20, Some(0x6B)), "Arturia"),
+    (ManufacturerID(0x21, Some(0x09)), "Native Instruments"),
+];
+
+/// Creates a one-byte ID, clamped to 0x7C. Use [`ManufacturerID::new`] to reject invalid IDs instead.
 impl From<u8> for ManufacturerID {
     fn from(a: u8) -> Self {
-        Self(a, None)
+        Self(a.min(0x7C), None)
     }
 }
 
@@ -289,6 +391,15 @@ pub enum UniversalRealTimeMsg {
 }
 
 impl UniversalRealTimeMsg {
+    /// A [`ScaleTuning1Byte`](UniversalRealTimeMsg::ScaleTuning1Byte) message that returns
+    /// every channel to equal temperament, i.e. a "reset" of any scale microtuning.
+    pub fn scale_tuning_reset_all_channels() -> Self {
+        UniversalRealTimeMsg::ScaleTuning1Byte(ScaleTuning1Byte {
+            channels: ChannelBitMap::all(),
+            tuning: [0; 12],
+        })
+    }
+
     fn extend_midi(&self, v: &mut Vec<u8>) {
         match self {
             UniversalRealTimeMsg::TimeCodeFull(code) => {
@@ -422,6 +533,110 @@ impl UniversalRealTimeMsg {
                     Ok(Self::TimeCodeFull(time_code))
                 }
             }
+            (0x2, _) => {
+                let (msg, _) = ShowControlMsg::from_midi(&m[1..])?;
+                Ok(Self::ShowControl(msg))
+            }
+            (0x3, 0x1) => {
+                let (marker, len) = BarMarker::from_midi(&m[2..])?;
+                if m.len() > len + 2 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::BarMarker",
+                    ))
+                } else {
+                    Ok(Self::BarMarker(marker))
+                }
+            }
+            (0x3, 0x2) => {
+                let (signature, len) = TimeSignature::from_midi(&m[2..])?;
+                if m.len() > len + 2 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::TimeSignature",
+                    ))
+                } else {
+                    Ok(Self::TimeSignature(signature))
+                }
+            }
+            (0x3, 0x42) => {
+                let (signature, len) = TimeSignature::from_midi(&m[2..])?;
+                if m.len() > len + 2 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::TimeSignatureDelayed",
+                    ))
+                } else {
+                    Ok(Self::TimeSignatureDelayed(signature))
+                }
+            }
+            (0x4, 0x1) => {
+                if m.len() > 4 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::MasterVolume",
+                    ))
+                } else {
+                    Ok(Self::MasterVolume(u14_from_midi(&m[2..])?))
+                }
+            }
+            (0x4, 0x2) => {
+                if m.len() > 4 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::MasterBalance",
+                    ))
+                } else {
+                    Ok(Self::MasterBalance(u14_from_midi(&m[2..])?))
+                }
+            }
+            (0x4, 0x3) => {
+                if m.len() > 4 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::MasterFineTuning",
+                    ))
+                } else {
+                    // Serialized as LSB, MSB
+                    let lsb = u7_from_midi(&m[2..])?;
+                    let msb = u7_from_midi(&m[3..])?;
+                    Ok(Self::MasterFineTuning(i14_from_u7s(msb, lsb)))
+                }
+            }
+            (0x4, 0x4) => {
+                if m.len() > 3 {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::MasterCoarseTuning",
+                    ))
+                } else {
+                    Ok(Self::MasterCoarseTuning(u7_to_i(u7_from_midi(&m[2..])?)))
+                }
+            }
+            (0x4, 0x5) => {
+                let (gp, _) = GlobalParameterControl::from_midi(&m[2..])?;
+                Ok(Self::GlobalParameterControl(gp))
+            }
+            (0x5, _) => {
+                let (msg, _) = TimeCodeCueingMsg::from_midi(&m[1..])?;
+                Ok(Self::TimeCodeCueing(msg))
+            }
+            (0x9, 0x1) => {
+                let (destination, _) = ControllerDestination::from_midi(&m[2..])?;
+                Ok(Self::ChannelPressureControllerDestination(destination))
+            }
+            (0x9, 0x2) => {
+                let (destination, _) = ControllerDestination::from_midi(&m[2..])?;
+                Ok(Self::PolyphonicKeyPressureControllerDestination(
+                    destination,
+                ))
+            }
+            (0x9, 0x3) => {
+                let (destination, _) = ControlChangeControllerDestination::from_midi(&m[2..])?;
+                Ok(Self::ControlChangeControllerDestination(destination))
+            }
+            (0x8, 0x2) | (0x8, 0x7) => {
+                let (note_change, len) = TuningNoteChange::from_midi(&m[2..], m[1] == 0x7)?;
+                if len < m.len() - 2 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalRealTimeMsg::TuningNoteChange",
+                    ));
+                }
+                Ok(Self::TuningNoteChange(note_change))
+            }
             _ => Err(ParseError::NotImplemented("UniversalRealTimeMsg")),
         }
     }
@@ -615,12 +830,103 @@ impl UniversalNonRealTimeMsg {
         }
 
         match (m[0], m[1]) {
+            (0x6, 0x1) => {
+                if m.len() > 2 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::IdentityRequest",
+                    ));
+                }
+                Ok(Self::IdentityRequest)
+            }
             (06, 02) => {
                 if m.len() < 3 {
                     return Err(crate::ParseError::UnexpectedEnd);
                 }
                 Ok(Self::IdentityReply(IdentityReply::from_midi(&m[2..])?))
             }
+            (0x4, _) => {
+                let (msg, _) = TimeCodeCueingSetupMsg::from_midi(&m[1..])?;
+                Ok(Self::TimeCodeCueingSetup(msg))
+            }
+            (0x1..=0x3, _) | (0x5, 0x1..=0x2) => {
+                let (msg, len) = SampleDumpMsg::from_midi(m)?;
+                if len < m.len() {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::SampleDump",
+                    ));
+                }
+                Ok(Self::SampleDump(msg))
+            }
+            // Extended sample dump messages share their leading 0x05 with the sample dump loop point messages
+            (0x5, 0x3..=0x7) => {
+                let (msg, len) = ExtendedSampleDumpMsg::from_midi(m)?;
+                if len < m.len() {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::ExtendedSampleDump",
+                    ));
+                }
+                Ok(Self::ExtendedSampleDump(msg))
+            }
+            (0x8, 0x0) => {
+                if m.len() > 3 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::TuningBulkDumpRequest",
+                    ));
+                }
+                Ok(Self::TuningBulkDumpRequest(u7_from_midi(&m[2..])?, None))
+            }
+            (0x8, 0x3) => {
+                if m.len() > 4 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::TuningBulkDumpRequest",
+                    ));
+                }
+                let bank_num = u7_from_midi(&m[2..])?;
+                Ok(Self::TuningBulkDumpRequest(
+                    u7_from_midi(&m[3..])?,
+                    Some(bank_num),
+                ))
+            }
+            (0xB, _) => {
+                let (msg, len) = FileReferenceMsg::from_midi(&m[1..])?;
+                if len < m.len() - 1 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::FileReference",
+                    ));
+                }
+                Ok(Self::FileReference(msg))
+            }
+            (0x8, 0x7) => {
+                let (note_change, len) = TuningNoteChange::from_midi(&m[2..], true)?;
+                if len < m.len() - 2 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::TuningNoteChange",
+                    ));
+                }
+                Ok(Self::TuningNoteChange(note_change))
+            }
+            (0x9, mode) => {
+                if m.len() > 2 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a UniversalNonRealTimeMsg::GeneralMidi",
+                    ));
+                }
+                match mode {
+                    0x1 => Ok(Self::GeneralMidi(GeneralMidi::GM1)),
+                    0x2 => Ok(Self::GeneralMidi(GeneralMidi::Off)),
+                    0x3 => Ok(Self::GeneralMidi(GeneralMidi::GM2)),
+                    _ => Err(ParseError::Invalid("Unknown GeneralMidi mode")),
+                }
+            }
+            (0x7B..=0x7F, _) if m.len() > 2 => Err(ParseError::Invalid(
+                "Extra bytes after a UniversalNonRealTimeMsg handshake message",
+            )),
+            // The packet number of these messages is not used
+            (0x7B, _) => Ok(Self::EOF),
+            (0x7C, _) => Ok(Self::Wait),
+            (0x7D, _) => Ok(Self::Cancel),
+            (0x7E, _) => Ok(Self::NAK(u7_from_midi(&m[1..])?)),
+            (0x7F, _) => Ok(Self::ACK(u7_from_midi(&m[1..])?)),
             _ => Err(ParseError::NotImplemented("UniversalNonRealTimeMsg")),
         }
     }
@@ -675,6 +981,17 @@ mod tests {
     use super::super::*;
     use alloc::vec;
 
+    #[test]
+    fn test_scale_tuning_reset_all_channels() {
+        match UniversalRealTimeMsg::scale_tuning_reset_all_channels() {
+            UniversalRealTimeMsg::ScaleTuning1Byte(tuning) => {
+                assert_eq!(tuning.tuning, [0; 12]);
+                assert_eq!(tuning.channels, ChannelBitMap::all());
+            }
+            msg => panic!("Unexpected message {:?}", msg),
+        }
+    }
+
     #[test]
     fn serialize_system_exclusive_msg() {
         assert_eq!(
@@ -699,6 +1016,18 @@ mod tests {
             vec![0xF0, 0x00, 0x01, 0x03, 0x7F, 0x77, 0x00, 0xF7]
         );
 
+        // A one-byte ID of 0x00 is kept, even though it reads back as the start of a three-byte ID
+        assert_eq!(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::Commercial {
+                    id: 0.into(),
+                    data: vec![0x00, 0x10]
+                }
+            }
+            .to_midi(),
+            vec![0xF0, 0x00, 0x00, 0x10, 0xF7]
+        );
+
         assert_eq!(
             MidiMsg::SystemExclusive {
                 msg: SystemExclusiveMsg::NonCommercial {
@@ -732,6 +1061,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_manufacturer_id_new() {
+        assert_eq!(
+            ManufacturerID::new(0x41, None),
+            Ok(ManufacturerID(0x41, None))
+        );
+        assert_eq!(
+            ManufacturerID::new(0x21, Some(0x09)),
+            Ok(ManufacturerID(0x21, Some(0x09)))
+        );
+        for id in [0x00, 0x7D, 0x7E, 0x7F].iter() {
+            assert_eq!(
+                ManufacturerID::new(*id, None),
+                Err(ParseError::Invalid(
+                    "A one-byte ManufacturerID must be between 0x01 and 0x7C"
+                ))
+            );
+        }
+        assert_eq!(
+            ManufacturerID::new(0x21, Some(0x80)),
+            Err(ParseError::ByteOverflow)
+        );
+
+        // From<u8> clamps
+        assert_eq!(ManufacturerID::from(0x7E), ManufacturerID(0x7C, None));
+        assert_eq!(ManufacturerID::from(0x00), ManufacturerID(0x00, None));
+    }
+
+    #[test]
+    fn test_manufacturer_id_name() {
+        assert_eq!(ManufacturerID::from(0x41).name(), Some("Roland"));
+        assert_eq!(ManufacturerID::from(0x43).name(), Some("Yamaha"));
+        assert_eq!(
+            ManufacturerID::from(0x01).name(),
+            Some("Sequential Circuits")
+        );
+        // 0x00 0x00 0x0E
+        assert_eq!(ManufacturerID::from((0x00, 0x0E)).name(), Some("Alesis"));
+        // The one-byte and three-byte forms are distinct
+        assert_eq!(ManufacturerID::from((0x00, 0x41)).name(), Some("Microsoft"));
+        assert_eq!(ManufacturerID::from(0x7C).name(), None);
+        assert_eq!(ManufacturerID::from((0x7F, 0x7F)).name(), None);
+    }
+
+    #[test]
+    fn test_system_exclusive_msg_len() {
+        let mut ctx = ReceiverContext::new();
+        let expected = SystemExclusiveMsg::UniversalRealTime {
+            device: DeviceID::Device(3),
+            msg: UniversalRealTimeMsg::MasterVolume(1000),
+        };
+
+        // Streamed, with a leading 0xF0 and followed by another message
+        let midi = [
+            0xF0, 0x7F, 0x03, 0x04, 0x01, 0x68, 0x07, 0xF7, 0x90, 0x40, 0x7F,
+        ];
+        assert_eq!(
+            SystemExclusiveMsg::from_midi(&midi, &mut ctx),
+            Ok((expected.clone(), 8))
+        );
+
+        // Embedded in an SMF event, without the 0xF0
+        ctx.is_smf_sysex = true;
+        assert_eq!(
+            SystemExclusiveMsg::from_midi(&midi[1..], &mut ctx),
+            Ok((expected, 7))
+        );
+        assert_eq!(
+            SystemExclusiveMsg::from_midi(&[0x7D, 0x01, 0x02, 0xF7, 0x00], &mut ctx),
+            Ok((
+                SystemExclusiveMsg::NonCommercial {
+                    data: vec![0x01, 0x02]
+                },
+                4
+            ))
+        );
+    }
+
+    #[test]
+    fn test_system_exclusive_msg_interleaved_real_time() {
+        // A timing clock between two data bytes of a master volume message
+        let midi = [0xF0, 0x7F, 0x03, 0x04, 0x01, 0x68, 0xF8, 0x07, 0xF7];
+        assert_eq!(
+            MidiMsg::from_midi(&midi),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalRealTime {
+                        device: DeviceID::Device(3),
+                        msg: UniversalRealTimeMsg::MasterVolume(1000),
+                    }
+                },
+                9
+            ))
+        );
+
+        // Other status bytes are still an error
+        let midi = [0xF0, 0x7F, 0x03, 0x04, 0x01, 0x68, 0x90, 0x07, 0xF7];
+        assert_eq!(MidiMsg::from_midi(&midi), Err(ParseError::ByteOverflow));
+    }
+
     #[test]
     fn deserialize_system_exclusive_msg() {
         let mut ctx = ReceiverContext::new();
@@ -756,6 +1185,27 @@ mod tests {
             &mut ctx,
         );
 
+        // Data starting with 0x00 isn't mistaken for a three-byte ID
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::Commercial {
+                    id: 0x41.into(),
+                    data: vec![0x00, 0x10],
+                },
+            },
+            &mut ctx,
+        );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::Commercial {
+                    id: (0x21, 0x09).into(),
+                    data: vec![0x00, 0x10],
+                },
+            },
+            &mut ctx,
+        );
+
         test_serialization(
             MidiMsg::SystemExclusive {
                 msg: SystemExclusiveMsg::NonCommercial {
@@ -791,5 +1241,131 @@ mod tests {
                 code_type: TimeCodeType::DF30,
             }
         );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::MasterVolume(1000),
+                },
+            },
+            &mut ctx,
+        );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::Device(3),
+                    msg: UniversalRealTimeMsg::MasterBalance(16383),
+                },
+            },
+            &mut ctx,
+        );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::MasterFineTuning(-4000),
+                },
+            },
+            &mut ctx,
+        );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::MasterCoarseTuning(-12),
+                },
+            },
+            &mut ctx,
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x04, 0x04, 0x34, 0x00, 0xF7]),
+            Err(ParseError::Invalid(
+                "Extra bytes after a UniversalRealTimeMsg::MasterCoarseTuning"
+            ))
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x04, 0x01, 0x68, 0x07, 0x00, 0xF7]),
+            Err(ParseError::Invalid(
+                "Extra bytes after a UniversalRealTimeMsg::MasterVolume"
+            ))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x04, 0x02, 0x68, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+
+        for msg in [
+            UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM1),
+            UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM2),
+            UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::Off),
+            UniversalNonRealTimeMsg::EOF,
+            UniversalNonRealTimeMsg::Wait,
+            UniversalNonRealTimeMsg::Cancel,
+            UniversalNonRealTimeMsg::NAK(5),
+            UniversalNonRealTimeMsg::ACK(127),
+            UniversalNonRealTimeMsg::TuningBulkDumpRequest(5, None),
+            UniversalNonRealTimeMsg::TuningBulkDumpRequest(127, Some(3)),
+            UniversalNonRealTimeMsg::IdentityRequest,
+        ] {
+            test_serialization(
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalNonRealTime {
+                        device: DeviceID::Device(2),
+                        msg,
+                    },
+                },
+                &mut ctx,
+            );
+        }
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x7F, 0x01, 0x00, 0xF7]),
+            Err(ParseError::Invalid(
+                "Extra bytes after a UniversalNonRealTimeMsg handshake message"
+            ))
+        );
+        // GM1 System On
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7]),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalNonRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalNonRealTimeMsg::GeneralMidi(GeneralMidi::GM1),
+                    },
+                },
+                6
+            ))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x09, 0x04, 0xF7]),
+            Err(ParseError::Invalid("Unknown GeneralMidi mode"))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x7E, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+        // Tuning bulk dump request for bank 2, program 9
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x08, 0x03, 0x02, 0x09, 0xF7]),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalNonRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalNonRealTimeMsg::TuningBulkDumpRequest(9, Some(2)),
+                    },
+                },
+                8
+            ))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x08, 0x03, 0x02, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
     }
 }
@@ -40,9 +40,14 @@ impl BarMarker {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("BarMarker"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let marker = match i14_from_midi(m)? {
+            -8192 => Self::NotRunning,
+            8191 => Self::RunningUnknown,
+            x if x <= 0 => Self::CountIn(-x as u16),
+            x => Self::Number(x as u16),
+        };
+        Ok((marker, 2))
     }
 }
 
@@ -89,9 +94,31 @@ impl TimeSignature {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("TimeSignature"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        let len = u7_from_midi(m)? as usize;
+        if len < 4 || len & 1 != 0 {
+            return Err(ParseError::Invalid(
+                "TimeSignature must be followed by 4 bytes, plus 2 for each compound signature",
+            ));
+        }
+        if m.len() < len + 1 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let (signature, _) = Signature::from_midi(&m[1..])?;
+        let mut compound = vec![];
+        for i in (5..len + 1).step_by(2) {
+            let (s, _) = Signature::from_midi(&m[i..])?;
+            compound.push(s);
+        }
+        Ok((
+            Self {
+                signature,
+                midi_clocks_in_metronome_click: u7_from_midi(&m[3..])?,
+                thirty_second_notes_in_midi_quarter_note: u7_from_midi(&m[4..])?,
+                compound,
+            },
+            len + 1,
+        ))
     }
 }
 
@@ -110,9 +137,14 @@ impl Signature {
         v.push(self.beat_value.to_u8());
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("Signature"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        Ok((
+            Self {
+                beats: u7_from_midi(m)?,
+                beat_value: BeatValue::from_byte(u7_from_midi(&m[1..])?),
+            },
+            2,
+        ))
     }
 }
 
@@ -154,10 +186,17 @@ impl BeatValue {
         }
     }
 
-    #[allow(dead_code)]
-    fn from_byte(_m: u8) -> Self {
-        // TODO
-        Self::Quarter
+    fn from_byte(m: u8) -> Self {
+        match m {
+            0 => Self::Whole,
+            1 => Self::Half,
+            2 => Self::Quarter,
+            3 => Self::Eighth,
+            4 => Self::Sixteenth,
+            5 => Self::ThirtySecond,
+            6 => Self::SixtyFourth,
+            x => Self::Other(x),
+        }
     }
 }
 
@@ -243,4 +282,83 @@ mod tests {
             vec![0xF0, 0x7F, 0x7f, 03, 0x02, 6, 4, 2, 24, 8, 3, 3, 0xF7]
         );
     }
+
+    #[test]
+    fn deserialize_bar_marker() {
+        let mut ctx = ReceiverContext::new();
+        for marker in [
+            BarMarker::NotRunning,
+            BarMarker::CountIn(1),
+            BarMarker::CountIn(8191),
+            BarMarker::Number(1),
+            BarMarker::Number(300),
+            BarMarker::RunningUnknown,
+        ]
+        .iter()
+        {
+            test_serialization(
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalRealTimeMsg::BarMarker(*marker),
+                    },
+                },
+                &mut ctx,
+            );
+        }
+    }
+
+    #[test]
+    fn deserialize_time_signature() {
+        let mut ctx = ReceiverContext::new();
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::TimeSignature(TimeSignature {
+                        signature: Signature {
+                            beats: 7,
+                            beat_value: BeatValue::Sixteenth,
+                        },
+                        midi_clocks_in_metronome_click: 12,
+                        thirty_second_notes_in_midi_quarter_note: 8,
+                        compound: vec![
+                            Signature {
+                                beats: 3,
+                                beat_value: BeatValue::Eighth,
+                            },
+                            Signature {
+                                beats: 2,
+                                beat_value: BeatValue::Other(9),
+                            },
+                        ],
+                    }),
+                },
+            },
+            &mut ctx,
+        );
+
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::Device(5),
+                    msg: UniversalRealTimeMsg::TimeSignatureDelayed(TimeSignature::default()),
+                },
+            },
+            &mut ctx,
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7f, 0x03, 0x02, 5, 4, 2, 24, 8, 3, 0xF7]),
+            Err(ParseError::Invalid(
+                "TimeSignature must be followed by 4 bytes, plus 2 for each compound signature"
+            ))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7f, 0x03, 0x02, 4, 4, 2, 24, 8, 3, 0xF7]),
+            Err(ParseError::Invalid(
+                "Extra bytes after a UniversalRealTimeMsg::TimeSignature"
+            ))
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use crate::parse_error::*;
 use crate::util::*;
+use crate::UniversalNonRealTimeMsg;
 use alloc::vec::Vec;
 use bstr::BString;
 
@@ -117,9 +118,88 @@ impl SampleDumpMsg {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("SampleDumpMsg"))
+    /// Parse a sample dump message, starting from its sub-ID.
+    ///
+    /// The checksum of a `Packet` is not verified here, since it covers the preceding device ID:
+    /// see `SystemExclusiveMsg::from_midi`.
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        match m.first() {
+            Some(0x01) => {
+                if m.len() < 17 {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                Ok((
+                    Self::Header {
+                        sample_num: u14_from_midi(&m[1..])?,
+                        format: u7_from_midi(&m[3..])?,
+                        period: u21_from_midi(&m[4..])?,
+                        length: u21_from_midi(&m[7..])?,
+                        sustain_loop_start: u21_from_midi(&m[10..])?,
+                        sustain_loop_end: u21_from_midi(&m[13..])?,
+                        loop_type: LoopType::from_midi(&m[16..])?,
+                    },
+                    17,
+                ))
+            }
+            Some(0x02) => {
+                if m.len() < 123 {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                let running_count = u7_from_midi(&m[1..])?;
+                let mut data = Vec::with_capacity(120);
+                for b in m[2..122].iter() {
+                    data.push(u8_from_u7(*b)?);
+                }
+                Ok((
+                    Self::Packet {
+                        running_count,
+                        data,
+                    },
+                    123,
+                ))
+            }
+            Some(0x03) => Ok((
+                Self::Request {
+                    sample_num: u14_from_midi(&m[1..])?,
+                },
+                3,
+            )),
+            Some(0x05) => match m.get(1) {
+                Some(0x01) => {
+                    if m.len() < 13 {
+                        return Err(ParseError::UnexpectedEnd);
+                    }
+                    Ok((
+                        Self::LoopPointTransmission {
+                            sample_num: u14_from_midi(&m[2..])?,
+                            loop_num: LoopNumber::from_midi(&m[4..], LoopNumber::DeleteAll)?,
+                            loop_type: LoopType::from_midi(&m[6..])?,
+                            start_addr: u21_from_midi(&m[7..])?,
+                            end_addr: u21_from_midi(&m[10..])?,
+                        },
+                        13,
+                    ))
+                }
+                Some(0x02) => {
+                    if m.len() < 6 {
+                        return Err(ParseError::UnexpectedEnd);
+                    }
+                    Ok((
+                        Self::LoopPointsRequest {
+                            sample_num: u14_from_midi(&m[2..])?,
+                            loop_num: LoopNumber::from_midi(&m[4..], LoopNumber::RequestAll)?,
+                        },
+                        6,
+                    ))
+                }
+                Some(_) => Err(ParseError::Invalid(
+                    "Unknown SampleDumpMsg loop point message",
+                )),
+                None => Err(ParseError::UnexpectedEnd),
+            },
+            Some(_) => Err(ParseError::Invalid("Unknown SampleDumpMsg")),
+            None => Err(ParseError::UnexpectedEnd),
+        }
     }
 
     /// Construct a packet of exactly 120 7-bit "bytes".
@@ -161,6 +241,14 @@ impl LoopNumber {
             Self::Loop(x) => push_u14(*x, v),
         }
     }
+
+    /// `all` is the variant that 0x7F7F stands for in the message being parsed.
+    fn from_midi(m: &[u8], all: Self) -> Result<Self, ParseError> {
+        match u14_from_midi(m)? {
+            0x3FFF => Ok(all),
+            x => Ok(Self::Loop(x)),
+        }
+    }
 }
 
 /// The type of loop being described by a [`SampleDumpMsg`].
@@ -174,6 +262,17 @@ pub enum LoopType {
     Off = 127,
 }
 
+impl LoopType {
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        match u7_from_midi(m)? {
+            0 => Ok(Self::Forward),
+            1 => Ok(Self::BiDirectional),
+            127 => Ok(Self::Off),
+            _ => Err(ParseError::Invalid("Unknown LoopType")),
+        }
+    }
+}
+
 /// The extended sample dump messages described in CA-019, used to allow for longer, named samples.
 /// Used by [`UniversalNonRealTimeMsg::SampleDump`](crate::UniversalNonRealTimeMsg::SampleDump).
 #[derive(Debug, Clone, PartialEq)]
@@ -287,9 +386,85 @@ impl ExtendedSampleDumpMsg {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("ExtendedSampleDumpMsg"))
+    /// Parse an extended sample dump message, starting from its leading 0x05 sub-ID.
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        if m.len() < 2 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        if m[0] != 0x05 {
+            return Err(ParseError::Invalid("Unknown ExtendedSampleDumpMsg"));
+        }
+        match m[1] {
+            0x03 => {
+                if m.len() < 5 {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                let sample_num = u14_from_midi(&m[2..])?;
+                // Skip the language tag, which should be empty
+                let len_pos = 5 + u7_from_midi(&m[4..])? as usize;
+                let len = u7_from_midi(&m[len_pos.min(m.len())..])? as usize;
+                let name_start = len_pos + 1;
+                if m.len() < name_start + len {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                Ok((
+                    Self::SampleName {
+                        sample_num,
+                        name: BString::from(&m[name_start..name_start + len]),
+                    },
+                    name_start + len,
+                ))
+            }
+            0x04 => Ok((
+                Self::SampleNameRequest {
+                    sample_num: u14_from_midi(&m[2..])?,
+                },
+                4,
+            )),
+            0x05 => {
+                if m.len() < 30 {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                let sample_rate_integer = u28_from_midi(&m[5..])? as f64;
+                let sample_rate_fraction = u28_from_midi(&m[9..])? as f64 / ((1 << 28) as f64);
+                Ok((
+                    Self::Header {
+                        sample_num: u14_from_midi(&m[2..])?,
+                        format: u7_from_midi(&m[4..])?,
+                        sample_rate: sample_rate_integer + sample_rate_fraction,
+                        length: u35_from_midi(&m[13..])?,
+                        sustain_loop_start: u35_from_midi(&m[18..])?,
+                        sustain_loop_end: u35_from_midi(&m[23..])?,
+                        loop_type: ExtendedLoopType::from_midi(&m[28..])?,
+                        num_channels: u7_from_midi(&m[29..])?,
+                    },
+                    30,
+                ))
+            }
+            0x06 => {
+                if m.len() < 17 {
+                    return Err(ParseError::UnexpectedEnd);
+                }
+                Ok((
+                    Self::LoopPointTransmission {
+                        sample_num: u14_from_midi(&m[2..])?,
+                        loop_num: LoopNumber::from_midi(&m[4..], LoopNumber::DeleteAll)?,
+                        loop_type: ExtendedLoopType::from_midi(&m[6..])?,
+                        start_addr: u35_from_midi(&m[7..])?,
+                        end_addr: u35_from_midi(&m[12..])?,
+                    },
+                    17,
+                ))
+            }
+            0x07 => Ok((
+                Self::LoopPointsRequest {
+                    sample_num: u14_from_midi(&m[2..])?,
+                    loop_num: LoopNumber::from_midi(&m[4..], LoopNumber::RequestAll)?,
+                },
+                6,
+            )),
+            _ => Err(ParseError::Invalid("Unknown ExtendedSampleDumpMsg")),
+        }
     }
 }
 
@@ -318,8 +493,133 @@ pub enum ExtendedLoopType {
     OneShot = 0x7F,
 }
 
+impl ExtendedLoopType {
+    fn from_midi(m: &[u8]) -> Result<Self, ParseError> {
+        match u7_from_midi(m)? {
+            0x00 => Ok(Self::Forward),
+            0x01 => Ok(Self::BiDirectional),
+            0x02 => Ok(Self::ForwardRelease),
+            0x03 => Ok(Self::BiDirectionalRelease),
+            0x40 => Ok(Self::Backward),
+            0x41 => Ok(Self::BackwardBiDirectional),
+            0x42 => Ok(Self::BackwardRelease),
+            0x43 => Ok(Self::BackwardBiDirectionalRelease),
+            0x7E => Ok(Self::BackwardOneShot),
+            0x7F => Ok(Self::OneShot),
+            _ => Err(ParseError::Invalid("Unknown ExtendedLoopType")),
+        }
+    }
+}
+
+/// The progress of a sample dump, as tracked by a [`SampleDumpSession`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum SampleDumpState {
+    /// No dump has been started.
+    #[default]
+    Idle,
+    /// A header has been sent, and packets are being sent.
+    Transferring,
+    /// The receiver sent a [`Wait`](crate::UniversalNonRealTimeMsg::Wait): no more packets should be sent until it
+    /// sends an `ACK` or `NAK`.
+    Waiting,
+    /// The dump was aborted by a [`Cancel`](crate::UniversalNonRealTimeMsg::Cancel).
+    Cancelled,
+    /// All packets have been sent, as indicated by an [`EOF`](crate::UniversalNonRealTimeMsg::EOF).
+    Finished,
+}
+
+/// Tracks the handshake of a sample dump, so that a sender or receiver can be built on top of it.
+///
+/// Pass every sample dump and handshake message that is sent or received to [`SampleDumpSession::receive`]: a
+/// [`SampleDumpMsg::Header`] or [`ExtendedSampleDumpMsg::Header`] starts a new dump, each
+/// [`SampleDumpMsg::Packet`] is counted, and the `ACK`, `NAK`, `Wait`, `Cancel`, and `EOF` handshake messages
+/// update the [`SampleDumpState`]. Packets and handshake messages are ignored when no dump is in progress.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SampleDumpSession {
+    state: SampleDumpState,
+    sample_num: Option<u16>,
+    packet_count: u32,
+    last_packet: Option<u8>,
+    last_ack: Option<u8>,
+    last_nak: Option<u8>,
+}
+
+impl SampleDumpSession {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the session with a message of the dump.
+    pub fn receive(&mut self, msg: &UniversalNonRealTimeMsg) {
+        use SampleDumpState::*;
+        match msg {
+            UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::Header { sample_num, .. })
+            | UniversalNonRealTimeMsg::ExtendedSampleDump(ExtendedSampleDumpMsg::Header {
+                sample_num,
+                ..
+            }) => {
+                *self = Self {
+                    state: Transferring,
+                    sample_num: Some(*sample_num),
+                    ..Self::default()
+                };
+            }
+            _ if !matches!(self.state, Transferring | Waiting) => (),
+            UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::Packet {
+                running_count, ..
+            }) => {
+                self.packet_count += 1;
+                self.last_packet = Some(*running_count);
+            }
+            UniversalNonRealTimeMsg::ACK(packet_num) => {
+                self.last_ack = Some(*packet_num);
+                self.state = Transferring;
+            }
+            UniversalNonRealTimeMsg::NAK(packet_num) => {
+                self.last_nak = Some(*packet_num);
+                self.state = Transferring;
+            }
+            UniversalNonRealTimeMsg::Wait => self.state = Waiting,
+            UniversalNonRealTimeMsg::Cancel => self.state = Cancelled,
+            UniversalNonRealTimeMsg::EOF => self.state = Finished,
+            _ => (),
+        }
+    }
+
+    /// The current state of the dump.
+    pub fn state(&self) -> SampleDumpState {
+        self.state
+    }
+
+    /// The ID of the sample being dumped, as given by the header of the dump.
+    pub fn sample_num(&self) -> Option<u16> {
+        self.sample_num
+    }
+
+    /// The number of packets sent since the header, including any that were sent again after a `NAK`.
+    pub fn packet_count(&self) -> u32 {
+        self.packet_count
+    }
+
+    /// The running count, 0-127, of the last packet sent.
+    pub fn last_packet(&self) -> Option<u8> {
+        self.last_packet
+    }
+
+    /// The packet number of the last `ACK`, i.e. of the last packet that was received correctly.
+    pub fn last_ack(&self) -> Option<u8> {
+        self.last_ack
+    }
+
+    /// The packet number of the last `NAK`, i.e. of the last packet that must be sent again.
+    pub fn last_nak(&self) -> Option<u8> {
+        self.last_nak
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::*;
     use alloc::vec;
 
@@ -360,4 +660,228 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn deserialize_sample_dump_msg() {
+        let mut ctx = ReceiverContext::new();
+        let sample_dump = |msg: SampleDumpMsg| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::Device(3),
+                msg: UniversalNonRealTimeMsg::SampleDump(msg),
+            },
+        };
+
+        test_serialization(
+            sample_dump(SampleDumpMsg::Header {
+                sample_num: 1000,
+                format: 16,
+                period: 22675,
+                length: 2097151,
+                sustain_loop_start: 20,
+                sustain_loop_end: 300000,
+                loop_type: LoopType::BiDirectional,
+            }),
+            &mut ctx,
+        );
+        let mut data = [0; 120];
+        for (i, d) in data.iter_mut().enumerate() {
+            *d = i as u8;
+        }
+        test_serialization(sample_dump(SampleDumpMsg::packet(130, data)), &mut ctx);
+        test_serialization(
+            sample_dump(SampleDumpMsg::Request { sample_num: 16383 }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(SampleDumpMsg::LoopPointTransmission {
+                sample_num: 2,
+                loop_num: LoopNumber::DeleteAll,
+                loop_type: LoopType::Off,
+                start_addr: 0,
+                end_addr: 1,
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(SampleDumpMsg::LoopPointTransmission {
+                sample_num: 2,
+                loop_num: LoopNumber::Loop(12),
+                loop_type: LoopType::Forward,
+                start_addr: 100,
+                end_addr: 2000,
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(SampleDumpMsg::LoopPointsRequest {
+                sample_num: 2,
+                loop_num: LoopNumber::RequestAll,
+            }),
+            &mut ctx,
+        );
+    }
+
+    #[test]
+    fn deserialize_sample_dump_packet_checksum_mismatch() {
+        let mut midi = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::packet(0, [1; 120])),
+            },
+        }
+        .to_midi();
+        let q = midi.len();
+        let computed = midi[q - 2];
+        midi[q - 2] = computed ^ 0x01;
+
+        assert_eq!(
+            MidiMsg::from_midi(&midi),
+            Err(ParseError::ChecksumMismatch {
+                computed,
+                received: computed ^ 0x01
+            })
+        );
+    }
+
+    #[test]
+    fn deserialize_extended_sample_dump_msg() {
+        let mut ctx = ReceiverContext::new();
+        let sample_dump = |msg: ExtendedSampleDumpMsg| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalNonRealTimeMsg::ExtendedSampleDump(msg),
+            },
+        };
+
+        test_serialization(
+            sample_dump(ExtendedSampleDumpMsg::Header {
+                sample_num: 5,
+                format: 8,
+                sample_rate: 4000.5,
+                length: 2u64.pow(30),
+                sustain_loop_start: 2u64.pow(10),
+                sustain_loop_end: 34359738367,
+                loop_type: ExtendedLoopType::BiDirectionalRelease,
+                num_channels: 2,
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(ExtendedSampleDumpMsg::SampleName {
+                sample_num: 300,
+                name: BString::from("Piano C4"),
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(ExtendedSampleDumpMsg::SampleNameRequest { sample_num: 300 }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(ExtendedSampleDumpMsg::LoopPointTransmission {
+                sample_num: 7,
+                loop_num: LoopNumber::Loop(3),
+                loop_type: ExtendedLoopType::BackwardOneShot,
+                start_addr: 2u64.pow(29),
+                end_addr: 2u64.pow(33),
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            sample_dump(ExtendedSampleDumpMsg::LoopPointsRequest {
+                sample_num: 7,
+                loop_num: LoopNumber::RequestAll,
+            }),
+            &mut ctx,
+        );
+
+        // A plain sample dump loop point request is not mistaken for an extended one
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7E, 0x7F, 0x05, 0x02, 0x07, 0x00, 0x7F, 0x7F, 0xF7]),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalNonRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalNonRealTimeMsg::SampleDump(
+                            SampleDumpMsg::LoopPointsRequest {
+                                sample_num: 7,
+                                loop_num: LoopNumber::RequestAll,
+                            }
+                        ),
+                    },
+                },
+                10
+            ))
+        );
+    }
+
+    #[test]
+    fn test_sample_dump_session() {
+        let mut session = SampleDumpSession::new();
+        let packet = UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::packet(0, [0; 120]));
+        // Nothing is tracked until the dump starts
+        session.receive(&packet);
+        session.receive(&UniversalNonRealTimeMsg::Wait);
+        assert_eq!(session, SampleDumpSession::new());
+
+        session.receive(&UniversalNonRealTimeMsg::SampleDump(
+            SampleDumpMsg::Header {
+                sample_num: 5,
+                format: 8,
+                period: 1000,
+                length: 240,
+                sustain_loop_start: 0,
+                sustain_loop_end: 0,
+                loop_type: LoopType::Off,
+            },
+        ));
+        assert_eq!(session.state(), SampleDumpState::Transferring);
+        assert_eq!(session.sample_num(), Some(5));
+        assert_eq!(session.packet_count(), 0);
+
+        session.receive(&packet);
+        assert_eq!(session.packet_count(), 1);
+        assert_eq!(session.last_packet(), Some(0));
+        session.receive(&UniversalNonRealTimeMsg::Wait);
+        assert_eq!(session.state(), SampleDumpState::Waiting);
+        session.receive(&UniversalNonRealTimeMsg::ACK(0));
+        assert_eq!(session.state(), SampleDumpState::Transferring);
+        assert_eq!(session.last_ack(), Some(0));
+
+        session.receive(&UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::packet(
+            1, [0; 120],
+        )));
+        session.receive(&UniversalNonRealTimeMsg::NAK(1));
+        assert_eq!(session.last_nak(), Some(1));
+        session.receive(&UniversalNonRealTimeMsg::SampleDump(SampleDumpMsg::packet(
+            1, [0; 120],
+        )));
+        session.receive(&UniversalNonRealTimeMsg::ACK(1));
+        assert_eq!(session.packet_count(), 3);
+        assert_eq!(session.last_packet(), Some(1));
+        assert_eq!(session.last_ack(), Some(1));
+
+        session.receive(&UniversalNonRealTimeMsg::EOF);
+        assert_eq!(session.state(), SampleDumpState::Finished);
+        // Handshakes after the end of the dump are ignored
+        session.receive(&UniversalNonRealTimeMsg::Cancel);
+        assert_eq!(session.state(), SampleDumpState::Finished);
+
+        let mut cancelled = SampleDumpSession::new();
+        cancelled.receive(&UniversalNonRealTimeMsg::ExtendedSampleDump(
+            ExtendedSampleDumpMsg::Header {
+                sample_num: 6,
+                format: 16,
+                sample_rate: 44100.0,
+                length: 240,
+                sustain_loop_start: 0,
+                sustain_loop_end: 0,
+                loop_type: ExtendedLoopType::OneShot,
+                num_channels: 1,
+            },
+        ));
+        cancelled.receive(&UniversalNonRealTimeMsg::Cancel);
+        assert_eq!(cancelled.state(), SampleDumpState::Cancelled);
+        assert_eq!(cancelled.sample_num(), Some(6));
+    }
 }
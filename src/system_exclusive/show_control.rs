@@ -1,15 +1,23 @@
 use crate::parse_error::*;
+use crate::time_code::*;
+use crate::util::*;
 use alloc::vec::Vec;
+use bstr::BString;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 /// A MIDI Show Control command.
 /// Used by [`UniversalRealTimeMsg::ShowControl`](crate::UniversalRealTimeMsg::ShowControl).
 ///
-/// Unimplemented, though the `Unimplemented` value can be used to
+/// Only the most common commands are implemented, though the `Unimplemented` value can be used to
 /// represent the commands not supported here.
 ///
 /// As defined in MIDI Show Control 1.1.1 (RP002/RP014)
 pub enum ShowControlMsg {
+    /// A command directed at devices of the given `format`.
+    Command {
+        format: CommandFormat,
+        command: ShowControlCommand,
+    },
     /// Used to represent all unimplemented MSC messages.
     /// Is inherently not guaranteed to be a valid message.
     Unimplemented(Vec<u8>),
@@ -18,22 +26,607 @@ pub enum ShowControlMsg {
 impl ShowControlMsg {
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         match self {
+            Self::Command { format, command } => {
+                v.push(format.to_u8());
+                command.extend_midi(v);
+            }
             Self::Unimplemented(d) => v.extend_from_slice(d),
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("ShowControlMsg"))
+    /// Parse everything from the command format byte until the end of the message,
+    /// returning the number of bytes consumed.
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        if m.len() < 2 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let format = CommandFormat::from_u8(u7_from_midi(m)?);
+        match ShowControlCommand::from_midi(&m[1..])? {
+            Some((command, len)) => Ok((Self::Command { format, command }, len + 1)),
+            None => Ok((Self::Unimplemented(m.to_vec()), m.len())),
+        }
+    }
+}
+
+/// The type of device a [`ShowControlMsg`] is directed at.
+///
+/// Only the general categories are named here; the specific device types
+/// within a category (e.g. `0x02`, Moving Lights) can be given with `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandFormat {
+    Lighting,
+    Sound,
+    Machinery,
+    Video,
+    Projection,
+    ProcessControl,
+    Pyro,
+    AllTypes,
+    Other(u8),
+}
+
+impl CommandFormat {
+    fn to_u8(self) -> u8 {
+        match self {
+            Self::Lighting => 0x01,
+            Self::Sound => 0x10,
+            Self::Machinery => 0x20,
+            Self::Video => 0x30,
+            Self::Projection => 0x40,
+            Self::ProcessControl => 0x50,
+            Self::Pyro => 0x60,
+            Self::AllTypes => 0x7F,
+            Self::Other(x) => to_u7(x),
+        }
+    }
+
+    fn from_u8(x: u8) -> Self {
+        match x {
+            0x01 => Self::Lighting,
+            0x10 => Self::Sound,
+            0x20 => Self::Machinery,
+            0x30 => Self::Video,
+            0x40 => Self::Projection,
+            0x50 => Self::ProcessControl,
+            0x60 => Self::Pyro,
+            0x7F => Self::AllTypes,
+            x => Self::Other(x),
+        }
+    }
+}
+
+/// The commands that can be sent by a [`ShowControlMsg`].
+///
+/// Commands taking an `Option<Cue>` refer to the "next" cue when no cue is given. Likewise, commands taking an
+/// optional list refer to all open cue lists when none is given.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShowControlCommand {
+    Go(Option<Cue>),
+    Stop(Option<Cue>),
+    Resume(Option<Cue>),
+    TimedGo {
+        time: StandardTimeCode,
+        cue: Option<Cue>,
+    },
+    Load(Cue),
+    /// Set a generic control, e.g. a fader, to a value.
+    Set {
+        /// The control number, 0-16383.
+        control: u16,
+        /// The value to set the control to, 0-16383.
+        value: u16,
+        /// When the value should be reached, if given.
+        time: Option<StandardTimeCode>,
+    },
+    /// Trigger the given macro number, 0-127.
+    Fire(u8),
+    AllOff,
+    Restore,
+    Reset,
+    GoOff(Option<Cue>),
+    GoJamClock(Option<Cue>),
+    StandbyPlus(Option<BString>),
+    StandbyMinus(Option<BString>),
+    SequencePlus(Option<BString>),
+    SequenceMinus(Option<BString>),
+    StartClock(Option<BString>),
+    StopClock(Option<BString>),
+    ZeroClock(Option<BString>),
+    SetClock {
+        time: StandardTimeCode,
+        list: Option<BString>,
+    },
+    MtcChaseOn(Option<BString>),
+    MtcChaseOff(Option<BString>),
+    OpenCueList(BString),
+    CloseCueList(BString),
+    OpenCuePath(BString),
+    CloseCuePath(BString),
+}
+
+impl ShowControlCommand {
+    fn extend_midi(&self, v: &mut Vec<u8>) {
+        match self {
+            Self::Go(cue) => {
+                v.push(0x01);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::Stop(cue) => {
+                v.push(0x02);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::Resume(cue) => {
+                v.push(0x03);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::TimedGo { time, cue } => {
+                v.push(0x04);
+                time.extend_midi(v);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::Load(cue) => {
+                v.push(0x05);
+                cue.extend_midi(v);
+            }
+            Self::Set {
+                control,
+                value,
+                time,
+            } => {
+                v.push(0x06);
+                push_u14(*control, v);
+                push_u14(*value, v);
+                if let Some(time) = time {
+                    time.extend_midi(v);
+                }
+            }
+            Self::Fire(macro_num) => {
+                v.push(0x07);
+                push_u7(*macro_num, v);
+            }
+            Self::AllOff => v.push(0x08),
+            Self::Restore => v.push(0x09),
+            Self::Reset => v.push(0x0A),
+            Self::GoOff(cue) => {
+                v.push(0x0B);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::GoJamClock(cue) => {
+                v.push(0x10);
+                push_cue(cue.as_ref(), v);
+            }
+            Self::StandbyPlus(list) => {
+                v.push(0x11);
+                push_field(list.as_ref(), v);
+            }
+            Self::StandbyMinus(list) => {
+                v.push(0x12);
+                push_field(list.as_ref(), v);
+            }
+            Self::SequencePlus(list) => {
+                v.push(0x13);
+                push_field(list.as_ref(), v);
+            }
+            Self::SequenceMinus(list) => {
+                v.push(0x14);
+                push_field(list.as_ref(), v);
+            }
+            Self::StartClock(list) => {
+                v.push(0x15);
+                push_field(list.as_ref(), v);
+            }
+            Self::StopClock(list) => {
+                v.push(0x16);
+                push_field(list.as_ref(), v);
+            }
+            Self::ZeroClock(list) => {
+                v.push(0x17);
+                push_field(list.as_ref(), v);
+            }
+            Self::SetClock { time, list } => {
+                v.push(0x18);
+                time.extend_midi(v);
+                push_field(list.as_ref(), v);
+            }
+            Self::MtcChaseOn(list) => {
+                v.push(0x19);
+                push_field(list.as_ref(), v);
+            }
+            Self::MtcChaseOff(list) => {
+                v.push(0x1A);
+                push_field(list.as_ref(), v);
+            }
+            Self::OpenCueList(list) => {
+                v.push(0x1B);
+                push_field(Some(list), v);
+            }
+            Self::CloseCueList(list) => {
+                v.push(0x1C);
+                push_field(Some(list), v);
+            }
+            Self::OpenCuePath(path) => {
+                v.push(0x1D);
+                push_field(Some(path), v);
+            }
+            Self::CloseCuePath(path) => {
+                v.push(0x1E);
+                push_field(Some(path), v);
+            }
+        }
+    }
+
+    /// Returns `None` for commands that are not implemented.
+    fn from_midi(m: &[u8]) -> Result<Option<(Self, usize)>, ParseError> {
+        let data = &m[1..];
+        let command = match u7_from_midi(m)? {
+            0x01 => Self::Go(Cue::from_midi(data)?),
+            0x02 => Self::Stop(Cue::from_midi(data)?),
+            0x03 => Self::Resume(Cue::from_midi(data)?),
+            0x04 => {
+                let (time, len) = StandardTimeCode::from_midi(data)?;
+                Self::TimedGo {
+                    time,
+                    cue: Cue::from_midi(&data[len..])?,
+                }
+            }
+            0x05 => Self::Load(Cue::from_midi(data)?.ok_or(ParseError::Invalid(
+                "A ShowControlCommand::Load must have a cue number",
+            ))?),
+            0x06 => {
+                let control = u14_from_midi(data)?;
+                let value = u14_from_midi(&data[2..])?;
+                let time = match data.len() {
+                    4 => None,
+                    9 => Some(StandardTimeCode::from_midi(&data[4..])?.0),
+                    _ => {
+                        return Err(ParseError::Invalid(
+                            "A ShowControlCommand::Set must have 4 or 9 bytes",
+                        ))
+                    }
+                };
+                Self::Set {
+                    control,
+                    value,
+                    time,
+                }
+            }
+            0x07 => {
+                if data.len() > 1 {
+                    return Err(ParseError::Invalid(
+                        "Extra bytes after a ShowControlCommand::Fire",
+                    ));
+                }
+                Self::Fire(u7_from_midi(data)?)
+            }
+            0x08 => Self::AllOff,
+            0x09 => Self::Restore,
+            0x0A => Self::Reset,
+            0x0B => Self::GoOff(Cue::from_midi(data)?),
+            0x10 => Self::GoJamClock(Cue::from_midi(data)?),
+            0x11 => Self::StandbyPlus(field_from_midi(data)?),
+            0x12 => Self::StandbyMinus(field_from_midi(data)?),
+            0x13 => Self::SequencePlus(field_from_midi(data)?),
+            0x14 => Self::SequenceMinus(field_from_midi(data)?),
+            0x15 => Self::StartClock(field_from_midi(data)?),
+            0x16 => Self::StopClock(field_from_midi(data)?),
+            0x17 => Self::ZeroClock(field_from_midi(data)?),
+            0x18 => {
+                let (time, len) = StandardTimeCode::from_midi(data)?;
+                Self::SetClock {
+                    time,
+                    list: field_from_midi(&data[len..])?,
+                }
+            }
+            0x19 => Self::MtcChaseOn(field_from_midi(data)?),
+            0x1A => Self::MtcChaseOff(field_from_midi(data)?),
+            0x1B => Self::OpenCueList(required_field_from_midi(data)?),
+            0x1C => Self::CloseCueList(required_field_from_midi(data)?),
+            0x1D => Self::OpenCuePath(required_field_from_midi(data)?),
+            0x1E => Self::CloseCuePath(required_field_from_midi(data)?),
+            _ => return Ok(None),
+        };
+        if matches!(command, Self::AllOff | Self::Restore | Self::Reset) && !data.is_empty() {
+            return Err(ParseError::Invalid(
+                "Extra bytes after a ShowControlCommand",
+            ));
+        }
+        // All other commands consume the remainder of the message
+        Ok(Some((command, m.len())))
     }
 }
 
+/// The cue referred to by a [`ShowControlCommand`].
+///
+/// Each field is an ASCII number, where `.` may be used as a decimal point, e.g. `"23.5"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cue {
+    pub number: BString,
+    pub list: Option<BString>,
+    /// Only sent if `list` is also given.
+    pub path: Option<BString>,
+}
+
+impl Cue {
+    /// A cue with the given number, in the currently open cue list.
+    pub fn new<N: Into<BString>>(number: N) -> Self {
+        Self {
+            number: number.into(),
+            list: None,
+            path: None,
+        }
+    }
+
+    fn extend_midi(&self, v: &mut Vec<u8>) {
+        push_field(Some(&self.number), v);
+        if let Some(list) = &self.list {
+            v.push(0x00);
+            push_field(Some(list), v);
+            if let Some(path) = &self.path {
+                v.push(0x00);
+                push_field(Some(path), v);
+            }
+        }
+    }
+
+    fn from_midi(m: &[u8]) -> Result<Option<Self>, ParseError> {
+        if m.is_empty() {
+            return Ok(None);
+        }
+        let mut fields = m.split(|b| *b == 0x00);
+        let number = fields.next().unwrap_or_default();
+        let list = fields.next();
+        let path = fields.next();
+        if fields.next().is_some() {
+            return Err(ParseError::Invalid(
+                "A show control Cue must have at most a number, list, and path",
+            ));
+        }
+        Ok(Some(Self {
+            number: field_from_u7s(number)?,
+            list: list.map(field_from_u7s).transpose()?,
+            path: path.map(field_from_u7s).transpose()?,
+        }))
+    }
+}
+
+fn push_cue(cue: Option<&Cue>, v: &mut Vec<u8>) {
+    if let Some(cue) = cue {
+        cue.extend_midi(v);
+    }
+}
+
+fn push_field(field: Option<&BString>, v: &mut Vec<u8>) {
+    if let Some(field) = field {
+        v.extend(field.iter().map(|b| to_u7(*b)));
+    }
+}
+
+fn field_from_u7s(m: &[u8]) -> Result<BString, ParseError> {
+    for b in m.iter() {
+        u8_from_u7(*b)?;
+    }
+    Ok(BString::from(m))
+}
+
+fn field_from_midi(m: &[u8]) -> Result<Option<BString>, ParseError> {
+    if m.is_empty() {
+        return Ok(None);
+    }
+    if m.contains(&0x00) {
+        return Err(ParseError::Invalid(
+            "Expected a single field in a ShowControlCommand",
+        ));
+    }
+    Ok(Some(field_from_u7s(m)?))
+}
+
+fn required_field_from_midi(m: &[u8]) -> Result<BString, ParseError> {
+    field_from_midi(m)?.ok_or(ParseError::UnexpectedEnd)
+}
+
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use crate::*;
+    use alloc::vec;
+    use bstr::BString;
 
     #[test]
     fn serialize_show_control_msg() {
-        // TODO
+        assert_eq!(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::Device(1),
+                    msg: UniversalRealTimeMsg::ShowControl(ShowControlMsg::Command {
+                        format: CommandFormat::Lighting,
+                        command: ShowControlCommand::Go(Some(Cue {
+                            number: BString::from("23.5"),
+                            list: Some(BString::from("1")),
+                            path: None,
+                        })),
+                    }),
+                },
+            }
+            .to_midi(),
+            vec![
+                0xF0, 0x7F, 0x01, // Device
+                0x02, // MSC
+                0x01, // Lighting
+                0x01, // GO
+                b'2', b'3', b'.', b'5', 0x00, b'1', 0xF7
+            ]
+        );
+    }
+
+    #[test]
+    fn deserialize_show_control_msg() {
+        let mut ctx = ReceiverContext::new();
+        let show_control = |format, command| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalRealTimeMsg::ShowControl(ShowControlMsg::Command { format, command }),
+            },
+        };
+
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::Go(Some(Cue::new("5"))),
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::Stop(Some(Cue {
+                    number: BString::from("12.3"),
+                    list: Some(BString::from("2")),
+                    path: Some(BString::from("1.1")),
+                })),
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(CommandFormat::Sound, ShowControlCommand::Resume(None)),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::AllTypes,
+                ShowControlCommand::TimedGo {
+                    time: StandardTimeCode {
+                        hours: 1,
+                        minutes: 2,
+                        seconds: 3,
+                        frames: -4,
+                        subframes: SubFrames::Status(TimeCodeStatus {
+                            estimated_code: true,
+                            ..Default::default()
+                        }),
+                        code_type: TimeCodeType::FPS25,
+                    },
+                    cue: Some(Cue::new("7")),
+                },
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::Other(0x02),
+                ShowControlCommand::Load(Cue::new("1")),
+            ),
+            &mut ctx,
+        );
+        // A fader moved to 3/4 of its travel
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::Set {
+                    control: 12,
+                    value: 12288,
+                    time: None,
+                },
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::Set {
+                    control: 300,
+                    value: 16383,
+                    time: Some(StandardTimeCode {
+                        seconds: 5,
+                        ..Default::default()
+                    }),
+                },
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(CommandFormat::Pyro, ShowControlCommand::Fire(9)),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(CommandFormat::Video, ShowControlCommand::AllOff),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::SetClock {
+                    time: Default::default(),
+                    list: Some(BString::from("3")),
+                },
+            ),
+            &mut ctx,
+        );
+        test_serialization(
+            show_control(
+                CommandFormat::Lighting,
+                ShowControlCommand::OpenCueList(BString::from("4")),
+            ),
+            &mut ctx,
+        );
+
+        // A GO with a cue number, as sent by a console
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x01, b'1', b'0', 0xF7]),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalRealTime {
+                        device: DeviceID::Device(1),
+                        msg: UniversalRealTimeMsg::ShowControl(ShowControlMsg::Command {
+                            format: CommandFormat::Lighting,
+                            command: ShowControlCommand::Go(Some(Cue::new("10"))),
+                        }),
+                    },
+                },
+                9
+            ))
+        );
+
+        // Commands that aren't implemented are preserved
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::ShowControl(ShowControlMsg::Unimplemented(vec![
+                        0x01, 0x20, 0x01,
+                    ])),
+                },
+            },
+            &mut ctx,
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x06, 0x0C, 0x00, 0x00, 0x60, 0xF7]),
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalRealTime {
+                        device: DeviceID::Device(1),
+                        msg: UniversalRealTimeMsg::ShowControl(ShowControlMsg::Command {
+                            format: CommandFormat::Lighting,
+                            command: ShowControlCommand::Set {
+                                control: 12,
+                                value: 12288,
+                                time: None,
+                            },
+                        }),
+                    },
+                },
+                11
+            ))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x06, 0x0C, 0x00, 0x00, 0xF7]),
+            Err(ParseError::UnexpectedEnd)
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x01, 0x02, 0x01, 0x05, 0xF7]),
+            Err(ParseError::Invalid(
+                "A ShowControlCommand::Load must have a cue number"
+            ))
+        );
     }
 }
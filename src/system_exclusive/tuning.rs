@@ -1,6 +1,7 @@
 use crate::parse_error::*;
 use crate::util::*;
 use alloc::vec::Vec;
+use micromath::F32Ext;
 
 /// Change the tunings of one or more notes, either real-time or not.
 /// Used by [`UniversalNonRealTimeMsg`](crate::UniversalNonRealTimeMsg) and [`UniversalRealTimeMsg`](crate::UniversalRealTimeMsg).
@@ -33,9 +34,32 @@ impl TuningNoteChange {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("TuningNoteChange"))
+    /// Parse a note change, starting after its sub-ID #2. Whether the message starts with a tuning
+    /// bank number depends on its sub-IDs, so the caller must say if one is expected.
+    pub(crate) fn from_midi(m: &[u8], has_bank: bool) -> Result<(Self, usize), ParseError> {
+        let (tuning_bank_num, offset) = if has_bank {
+            (Some(u7_from_midi(m)?), 1)
+        } else {
+            (None, 0)
+        };
+        let tuning_program_num = u7_from_midi(&m[offset.min(m.len())..])?;
+        let count = u7_from_midi(&m[(offset + 1).min(m.len())..])? as usize;
+        let len = offset + 2 + count * 4;
+        if m.len() < len {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let mut tunings = Vec::with_capacity(count);
+        for t in m[offset + 2..len].chunks(4) {
+            tunings.push((u7_from_midi(t)?, Tuning::maybe_from_midi(&t[1..])?));
+        }
+        Ok((
+            Self {
+                tuning_program_num,
+                tuning_bank_num,
+                tunings,
+            },
+            len,
+        ))
     }
 }
 
@@ -57,6 +81,24 @@ pub struct KeyBasedTuningDump {
 }
 
 impl KeyBasedTuningDump {
+    /// Create a dump that tunes each MIDI note to the frequency, in Hertz, at the same index of `freqs`.
+    ///
+    /// Frequencies are converted with [`Tuning::from_freq`], so out of range frequencies are clamped to
+    /// the lowest or highest tuning.
+    pub fn from_frequencies(
+        tuning_program_num: u8,
+        tuning_bank_num: Option<u8>,
+        name: [u8; 16],
+        freqs: &[f32; 128],
+    ) -> Self {
+        Self {
+            tuning_program_num,
+            tuning_bank_num,
+            name,
+            tunings: freqs.iter().map(|f| Some(Tuning::from_freq(*f))).collect(),
+        }
+    }
+
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         if let Some(bank_num) = self.tuning_bank_num {
             v.push(to_u7(bank_num))
@@ -127,12 +169,36 @@ impl Tuning {
         }
     }
 
+    /// The frequency, in Hertz, of this tuning. The inverse of [`Tuning::from_freq`].
+    pub fn to_freq(&self) -> f32 {
+        midi_note_cents_to_freq(self.semitone, self.cents())
+    }
+
+    /// The number of cents above the `semitone` of this tuning: 0-100.
+    pub fn cents(&self) -> f32 {
+        self.fraction as f32 / 16383.0 * 100.0
+    }
+
     fn extend_midi(&self, v: &mut Vec<u8>) {
         push_u7(self.semitone, v);
         let [msb, lsb] = to_u14(self.fraction);
         v.push(msb); // For some reason this is the opposite order of everything else???
         v.push(lsb);
     }
+
+    /// Read a tuning, where `7F 7F 7F` represents "No change".
+    fn maybe_from_midi(m: &[u8]) -> Result<Option<Self>, ParseError> {
+        if m.len() < 3 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        if m[0..3] == [0x7F, 0x7F, 0x7F] {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            semitone: u7_from_midi(m)?,
+            fraction: u14_from_u7s(u7_from_midi(&m[1..])?, u7_from_midi(&m[2..])?),
+        }))
+    }
 }
 
 /// Set the tuning of all octaves for a tuning program/bank.
@@ -256,6 +322,17 @@ pub struct ScaleTuning2Byte {
 }
 
 impl ScaleTuning2Byte {
+    /// Create a scale tuning from 12 adjustments in cents, starting with C, relative to equal temperament.
+    ///
+    /// Each value is clamped to -100 to 100 cents, and rounded to the nearest .012207 cent step.
+    pub fn from_cents(channels: ChannelBitMap, cents: [f32; 12]) -> Self {
+        let mut tuning = [0; 12];
+        for (t, c) in tuning.iter_mut().zip(cents.iter()) {
+            *t = (F32Ext::round(c.clamp(-100.0, 100.0) * 8192.0 / 100.0) as i16).min(8191);
+        }
+        Self { channels, tuning }
+    }
+
     pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
         self.channels.extend_midi(v);
         for t in self.tuning.iter() {
@@ -380,8 +457,32 @@ impl ChannelBitMap {
     }
 
     #[allow(dead_code)]
-    pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-        Err(ParseError::NotImplemented("ChannelBitMap"))
+    pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+        if m.len() < 3 {
+            return Err(ParseError::UnexpectedEnd);
+        }
+        let bit = |byte: u8, n: u8| byte & (1 << n) != 0;
+        Ok((
+            Self {
+                channel_1: bit(m[2], 0),
+                channel_2: bit(m[2], 1),
+                channel_3: bit(m[2], 2),
+                channel_4: bit(m[2], 3),
+                channel_5: bit(m[2], 4),
+                channel_6: bit(m[2], 5),
+                channel_7: bit(m[2], 6),
+                channel_8: bit(m[1], 0),
+                channel_9: bit(m[1], 1),
+                channel_10: bit(m[1], 2),
+                channel_11: bit(m[1], 3),
+                channel_12: bit(m[1], 4),
+                channel_13: bit(m[1], 5),
+                channel_14: bit(m[1], 6),
+                channel_15: bit(m[0], 0),
+                channel_16: bit(m[0], 1),
+            },
+            3,
+        ))
     }
 }
 
@@ -435,6 +536,100 @@ mod tests {
         );
     }
 
+    #[test]
+    fn deserialize_tuning_note_change() {
+        let mut ctx = ReceiverContext::new();
+        let note_change = |tuning_bank_num| TuningNoteChange {
+            tuning_program_num: 5,
+            tuning_bank_num,
+            tunings: vec![
+                (
+                    1,
+                    Some(Tuning {
+                        semitone: 1,
+                        fraction: 255,
+                    }),
+                ),
+                (0x33, None),
+                (
+                    0x45,
+                    Some(Tuning {
+                        semitone: 0x46,
+                        fraction: 0x3FFE,
+                    }),
+                ),
+            ],
+        };
+
+        for tuning_bank_num in [None, Some(3)] {
+            test_serialization(
+                MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::UniversalRealTime {
+                        device: DeviceID::AllCall,
+                        msg: UniversalRealTimeMsg::TuningNoteChange(note_change(tuning_bank_num)),
+                    },
+                },
+                &mut ctx,
+            );
+        }
+        test_serialization(
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalNonRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalNonRealTimeMsg::TuningNoteChange(note_change(Some(3))),
+                },
+            },
+            &mut ctx,
+        );
+
+        // The count must match the number of tunings
+        assert_eq!(
+            MidiMsg::from_midi(&[
+                0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x05, 0x02, 0x01, 0x01, 0x00, 0x00, 0xF7
+            ]),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn deserialize_tuning_note_change_bank() {
+        let bank_of = |m: &[u8]| match MidiMsg::from_midi(m) {
+            Ok((
+                MidiMsg::SystemExclusive {
+                    msg:
+                        SystemExclusiveMsg::UniversalRealTime {
+                            msg: UniversalRealTimeMsg::TuningNoteChange(note_change),
+                            ..
+                        },
+                },
+                _,
+            )) => {
+                assert_eq!(note_change.tuning_program_num, 5);
+                assert_eq!(note_change.tunings.len(), 1);
+                note_change.tuning_bank_num
+            }
+            msg => panic!("Unexpected message {:?}", msg),
+        };
+
+        // Sub-ID #2 0x02 has no bank byte
+        assert_eq!(
+            bank_of(&[0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x05, 0x01, 0x01, 0x01, 0x00, 0x00, 0xF7]),
+            None
+        );
+        // Sub-ID #2 0x07 starts with the bank
+        assert_eq!(
+            bank_of(&[
+                0xF0, 0x7F, 0x7F, 0x08, 0x07, 0x03, 0x05, 0x01, 0x01, 0x01, 0x00, 0x00, 0xF7
+            ]),
+            Some(3)
+        );
+        // A bank byte where none is expected is read as the program number
+        assert!(MidiMsg::from_midi(&[
+            0xF0, 0x7F, 0x7F, 0x08, 0x02, 0x03, 0x05, 0x01, 0x01, 0x01, 0x00, 0x00, 0xF7
+        ])
+        .is_err());
+    }
+
     #[test]
     fn serialize_tuning_bulk_dump_reply() {
         let packet_msg = MidiMsg::SystemExclusive {
@@ -468,4 +663,130 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_key_based_tuning_dump_from_frequencies() {
+        // A stretched tuning, where each semitone is 1.005 equal tempered semitones
+        let mut freqs = [0.0; 128];
+        for (note, freq) in freqs.iter_mut().enumerate() {
+            *freq = midi_note_float_to_freq(69.0 + (note as f32 - 69.0) * 1.005);
+        }
+        freqs[127] = 20000.0;
+        let dump = KeyBasedTuningDump::from_frequencies(
+            5,
+            Some(2),
+            B("Stretched tuning").try_into().unwrap(),
+            &freqs,
+        );
+
+        assert_eq!(dump.tuning_program_num, 5);
+        assert_eq!(dump.tuning_bank_num, Some(2));
+        assert_eq!(dump.tunings.len(), 128);
+        assert_eq!(
+            dump.tunings[69],
+            Some(Tuning {
+                semitone: 69,
+                fraction: 0
+            })
+        );
+        // An octave up is 6 cents sharp (within the precision of micromath's approximations)
+        let tuning = dump.tunings[81].unwrap();
+        assert_eq!(tuning.semitone, 81);
+        assert!((tuning.fraction as i32 - 983).abs() < 50);
+        // An octave down is 6 cents flat
+        let tuning = dump.tunings[57].unwrap();
+        assert_eq!(tuning.semitone, 56);
+        assert!((tuning.fraction as i32 - 15400).abs() < 50);
+        // Clamped
+        assert_eq!(
+            dump.tunings[127],
+            Some(Tuning {
+                semitone: 127,
+                fraction: 16383
+            })
+        );
+    }
+
+    #[test]
+    fn test_channel_bit_map_round_trip() {
+        let mut v = vec![];
+        ChannelBitMap::all().extend_midi(&mut v);
+        assert_eq!(v, vec![0b11, 0x7F, 0x7F]);
+        assert_eq!(
+            ChannelBitMap::from_midi(&v).unwrap(),
+            (ChannelBitMap::all(), 3)
+        );
+
+        let channels = ChannelBitMap {
+            channel_1: true,
+            channel_8: true,
+            channel_14: true,
+            channel_16: true,
+            ..ChannelBitMap::none()
+        };
+        let mut v = vec![];
+        channels.extend_midi(&mut v);
+        assert_eq!(v, vec![0b10, 0b1000001, 0b1]);
+        assert_eq!(ChannelBitMap::from_midi(&v).unwrap(), (channels, 3));
+
+        assert_eq!(
+            ChannelBitMap::from_midi(&[0, 0]),
+            Err(ParseError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_scale_tuning_2_byte_from_cents() {
+        // Just intonation, relative to C
+        let just = [
+            0.0, 11.73, 3.91, 15.64, -13.69, -1.96, -9.78, 1.96, 13.69, -15.64, 17.6, -11.73,
+        ];
+        let scale_tuning = ScaleTuning2Byte::from_cents(ChannelBitMap::all(), just);
+        assert_eq!(scale_tuning.channels, ChannelBitMap::all());
+        assert_eq!(
+            scale_tuning.tuning,
+            [0, 961, 320, 1281, -1121, -161, -801, 161, 1121, -1281, 1442, -961]
+        );
+
+        let midi = MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalNonRealTimeMsg::ScaleTuning2Byte(scale_tuning),
+            },
+        }
+        .to_midi();
+        // E: -1121 + 8192 = 7071, LSB then MSB
+        assert_eq!(&midi[16..18], &[(7071 & 0x7F) as u8, (7071 >> 7) as u8]);
+
+        // Out of range values are clamped
+        let mut cents = [0.0; 12];
+        cents[0] = -150.0;
+        cents[1] = 100.0;
+        cents[2] = 250.0;
+        let scale_tuning = ScaleTuning2Byte::from_cents(ChannelBitMap::none(), cents);
+        assert_eq!(&scale_tuning.tuning[0..3], &[-8192, 8191, 8191]);
+    }
+
+    #[test]
+    fn test_tuning_to_freq() {
+        assert!((Tuning::from_freq(440.0).to_freq() - 440.0).abs() < 0.01);
+        // Within the precision of micromath's approximations
+        assert!((Tuning::from_freq(261.63).to_freq() - 261.63).abs() < 0.2);
+        assert!((Tuning::from_freq(1234.5).to_freq() - 1234.5).abs() < 0.5);
+
+        let quarter_tone = Tuning {
+            semitone: 69,
+            fraction: 8192,
+        };
+        assert!((quarter_tone.cents() - 50.0).abs() < 0.01);
+        assert!((quarter_tone.to_freq() - 452.89).abs() < 0.1);
+        assert_eq!(
+            Tuning {
+                semitone: 60,
+                fraction: 0
+            }
+            .cents(),
+            0.0
+        );
+    }
 }
@@ -22,6 +22,12 @@ pub enum SystemRealTimeMsg {
     /// with the MIDI file's Meta messages. If you add this message to a
     /// MIDI file, it will be ignored upon serialization.
     SystemReset,
+    /// The undefined system real-time status byte 0xF9.
+    ///
+    /// This is reserved by the spec, but some hardware sends it anyway.
+    UndefinedF9,
+    /// The undefined system real-time status byte 0xFD. See [`SystemRealTimeMsg::UndefinedF9`].
+    UndefinedFD,
 }
 
 impl SystemRealTimeMsg {
@@ -33,15 +39,19 @@ impl SystemRealTimeMsg {
             Self::Stop => v.push(0xFC),
             Self::ActiveSensing => v.push(0xFE),
             Self::SystemReset => v.push(0xFF),
+            Self::UndefinedF9 => v.push(0xF9),
+            Self::UndefinedFD => v.push(0xFD),
         }
     }
 
     pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
         match m.first() {
             Some(0xF8) => Ok((Self::TimingClock, 1)),
+            Some(0xF9) => Ok((Self::UndefinedF9, 1)),
             Some(0xFA) => Ok((Self::Start, 1)),
             Some(0xFB) => Ok((Self::Continue, 1)),
             Some(0xFC) => Ok((Self::Stop, 1)),
+            Some(0xFD) => Ok((Self::UndefinedFD, 1)),
             Some(0xFE) => Ok((Self::ActiveSensing, 1)),
             Some(0xFF) => Ok((Self::SystemReset, 1)),
             Some(x) => Err(ParseError::UndefinedSystemRealTimeMessage(*x)),
@@ -89,4 +99,16 @@ mod tests {
             Ok((system_reset, 1)),
         );
     }
+
+    #[test]
+    fn serde_undefined() {
+        for (msg, b) in [
+            (SystemRealTimeMsg::UndefinedF9, 0xF9),
+            (SystemRealTimeMsg::UndefinedFD, 0xFD),
+        ] {
+            let undefined = MidiMsg::SystemRealTime { msg };
+            assert_eq!(undefined.to_midi(), vec![b]);
+            test_serialization(undefined, &mut ReceiverContext::new());
+        }
+    }
 }
@@ -102,6 +102,25 @@ impl Default for TimeCodeType {
 }
 
 impl TimeCodeType {
+    /// The nominal number of frames per second of this time code type.
+    pub fn frames_per_second(&self) -> u8 {
+        match self {
+            Self::FPS24 => 24,
+            Self::FPS25 => 25,
+            Self::DF30 | Self::NDF30 => 30,
+        }
+    }
+
+    /// The number of frames that pass in one second of real time.
+    ///
+    /// Drop frame time code counts 30 frames per second, but runs at 29.97 (30 × 1000/1001) frames per second.
+    pub fn real_frames_per_second(&self) -> f32 {
+        match self {
+            Self::DF30 => 30000.0 / 1001.0,
+            _ => self.frames_per_second() as f32,
+        }
+    }
+
     fn from_code_hour(code_hour: u8) -> Self {
         match (code_hour & 0b01100000) >> 5 {
             0 => Self::FPS24,
@@ -121,7 +140,54 @@ mod sysex_types {
     use alloc::vec::Vec;
     use bstr::BString;
 
+    /// How a [`TimeCode`] position is being synchronized. Used by [`TimeCode::to_sync_midi`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum TimeCodeSync {
+        /// Jumping to a position, e.g. when rewinding, fast-forwarding or cueing, or while stopped.
+        Locate,
+        /// Playing forward at normal speed.
+        Play,
+        /// Playing in reverse at normal speed.
+        PlayReverse,
+    }
+
     impl TimeCode {
+        /// Return the messages that synchronize a receiver to this position, following the MIDI Time Code spec's
+        /// guidance on which messages to use:
+        ///
+        /// - When locating, a single [`UniversalRealTimeMsg::TimeCodeFull`](crate::UniversalRealTimeMsg::TimeCodeFull)
+        ///   message is sent to all devices, since sending quarter frames continuously would be excessive.
+        /// - When playing, the eight [`SystemCommonMsg::TimeCodeQuarterFrame`](crate::SystemCommonMsg::TimeCodeQuarterFrame1)
+        ///   messages are returned. They should be sent evenly over the next two frames, four per frame. When playing in
+        ///   reverse they are returned in reverse order.
+        pub fn to_sync_midi(self, sync: TimeCodeSync) -> Vec<MidiMsg> {
+            use crate::SystemCommonMsg::*;
+
+            let quarter_frames = [
+                TimeCodeQuarterFrame1(self),
+                TimeCodeQuarterFrame2(self),
+                TimeCodeQuarterFrame3(self),
+                TimeCodeQuarterFrame4(self),
+                TimeCodeQuarterFrame5(self),
+                TimeCodeQuarterFrame6(self),
+                TimeCodeQuarterFrame7(self),
+                TimeCodeQuarterFrame8(self),
+            ];
+            let quarter_frame = |msg: &crate::SystemCommonMsg| MidiMsg::SystemCommon { msg: *msg };
+            match sync {
+                TimeCodeSync::Locate => alloc::vec![MidiMsg::SystemExclusive {
+                    msg: crate::SystemExclusiveMsg::UniversalRealTime {
+                        device: crate::DeviceID::AllCall,
+                        msg: crate::UniversalRealTimeMsg::TimeCodeFull(self),
+                    },
+                }],
+                TimeCodeSync::Play => quarter_frames.iter().map(quarter_frame).collect(),
+                TimeCodeSync::PlayReverse => {
+                    quarter_frames.iter().rev().map(quarter_frame).collect()
+                }
+            }
+        }
+
         pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
             let [frame, seconds, minutes, codehour] = self.to_bytes();
             v.extend_from_slice(&[codehour, minutes, seconds, frame]);
@@ -253,6 +319,33 @@ mod sysex_types {
             let [subframes, frames] = self.to_bytes_short();
             v.extend_from_slice(&[frames, subframes]);
         }
+
+        pub(crate) fn from_midi(v: &[u8]) -> Result<(Self, usize), ParseError> {
+            if v.len() < 5 {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            let code_hour = u8_from_u7(v[0])?;
+            let frames = u8_from_u7(v[3])?;
+            let subframes = u8_from_u7(v[4])?;
+            let subframes = if frames & 0b00100000 != 0 {
+                SubFrames::Status(TimeCodeStatus::from_byte(subframes))
+            } else {
+                SubFrames::FractionalFrames(subframes)
+            };
+            let negative = frames & 0b01000000 != 0;
+            let frames = (frames & 0b00011111) as i8;
+            Ok((
+                Self {
+                    subframes,
+                    frames: if negative { -frames } else { frames },
+                    seconds: u8_from_u7(v[2])?,
+                    minutes: u8_from_u7(v[1])?,
+                    hours: code_hour & 0b00011111,
+                    code_type: TimeCodeType::from_code_hour(code_hour),
+                },
+                5,
+            ))
+        }
     }
 
     impl From<TimeCode> for StandardTimeCode {
@@ -318,6 +411,15 @@ mod sysex_types {
             }
             b
         }
+
+        fn from_byte(b: u8) -> Self {
+            Self {
+                estimated_code: b & (1 << 6) != 0,
+                invalid_code: b & (1 << 5) != 0,
+                video_field1: b & (1 << 4) != 0,
+                no_time_code: b & (1 << 3) != 0,
+            }
+        }
     }
 
     /// 32 bits defined by SMPTE for "special functions". Used in [`UniversalRealTimeMsg::TimeCodeUserBits`](crate::UniversalRealTimeMsg::TimeCodeUserBits).
@@ -655,9 +757,89 @@ mod sysex_types {
             }
         }
 
-        #[allow(dead_code)]
-        pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-            Err(ParseError::NotImplemented("TimeCodeCueingSetupMsg"))
+        /// Everything after the sub-ID #2 byte is consumed, so the returned length is always `m.len()`.
+        pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+            if m.len() < 8 {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            let (time_code, _) = HighResTimeCode::from_midi(&m[1..])?;
+            let event_number = u14_from_midi(&m[6..])?;
+            let data = &m[8..];
+            let no_data = |msg: Self| {
+                if data.is_empty() {
+                    Ok(msg)
+                } else {
+                    Err(ParseError::Invalid(
+                        "Extra bytes after a TimeCodeCueingSetupMsg",
+                    ))
+                }
+            };
+            let msg = match m[0] {
+                // The special messages use the event number bytes to identify themselves
+                0x00 => match (m[6], m[7]) {
+                    (0x00, 0x00) => no_data(Self::TimeCodeOffset { time_code })?,
+                    (0x01, 0x00) => no_data(Self::EnableEventList)?,
+                    (0x02, 0x00) => no_data(Self::DisableEventList)?,
+                    (0x03, 0x00) => no_data(Self::ClearEventList)?,
+                    (0x04, 0x00) => no_data(Self::SystemStop)?,
+                    (0x05, 0x00) => no_data(Self::EventListRequest { time_code })?,
+                    _ => {
+                        return Err(ParseError::Invalid(
+                            "Unknown special TimeCodeCueingSetupMsg",
+                        ))
+                    }
+                },
+                0x01 => no_data(Self::PunchIn {
+                    time_code,
+                    event_number,
+                })?,
+                0x02 => no_data(Self::PunchOut {
+                    time_code,
+                    event_number,
+                })?,
+                0x03 => no_data(Self::DeletePunchIn {
+                    time_code,
+                    event_number,
+                })?,
+                0x04 => no_data(Self::DeletePunchOut {
+                    time_code,
+                    event_number,
+                })?,
+                0x05 | 0x07 => Self::EventStart {
+                    time_code,
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x06 | 0x08 => Self::EventStop {
+                    time_code,
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x09 => no_data(Self::DeleteEventStart {
+                    time_code,
+                    event_number,
+                })?,
+                0x0A => no_data(Self::DeleteEventStop {
+                    time_code,
+                    event_number,
+                })?,
+                0x0B | 0x0C => Self::Cue {
+                    time_code,
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x0D => no_data(Self::DeleteCue {
+                    time_code,
+                    event_number,
+                })?,
+                0x0E => Self::EventName {
+                    time_code,
+                    event_number,
+                    name: read_nibblized_name(data)?,
+                },
+                _ => return Err(ParseError::Invalid("Unknown time code cueing setup type")),
+            };
+            Ok((msg, m.len()))
         }
     }
 
@@ -701,6 +883,24 @@ mod sysex_types {
         }
     }
 
+    /// The inverse of `push_nibblized_midi`: reassemble the bytes of a nibblized MIDI stream and parse them.
+    ///
+    /// Any message may be embedded, including system exclusive messages, whose F0 and F7 bytes are nibblized along
+    /// with the rest of the stream. Messages are parsed with a default [`ReceiverContext`](crate::ReceiverContext),
+    /// so control changes are parsed as simple CC messages.
+    pub(crate) fn read_nibblized_midi(m: &[u8]) -> Result<Vec<MidiMsg>, ParseError> {
+        let bytes = read_nibbles(m)?;
+        let mut ctx = crate::ReceiverContext::new();
+        let mut msgs = Vec::new();
+        let mut offset = 0;
+        while offset < bytes.len() {
+            let (msg, len) = MidiMsg::from_midi_with_context(&bytes[offset..], &mut ctx)?;
+            msgs.push(msg);
+            offset += len;
+        }
+        Ok(msgs)
+    }
+
     fn push_nibblized_name(name: &BString, v: &mut Vec<u8>) {
         // Not sure if this actually handles newlines correctly
         for b in name.iter() {
@@ -710,6 +910,28 @@ mod sysex_types {
         }
     }
 
+    fn read_nibblized_name(m: &[u8]) -> Result<BString, ParseError> {
+        Ok(BString::from(read_nibbles(m)?))
+    }
+
+    /// Join pairs of nibbles, least significant first, into bytes.
+    fn read_nibbles(m: &[u8]) -> Result<Vec<u8>, ParseError> {
+        if m.len() & 1 != 0 {
+            return Err(ParseError::Invalid(
+                "Nibblized MIDI must contain an even number of nibbles",
+            ));
+        }
+        let mut bytes = Vec::with_capacity(m.len() / 2);
+        for pair in m.chunks(2) {
+            let (lsn, msn) = (pair[0], pair[1]);
+            if lsn > 0x0F || msn > 0x0F {
+                return Err(ParseError::Invalid("Nibblized MIDI contains a byte > 0x0F"));
+            }
+            bytes.push((msn << 4) + lsn);
+        }
+        Ok(bytes)
+    }
+
     impl TimeCodeCueingMsg {
         pub(crate) fn extend_midi(&self, v: &mut Vec<u8>) {
             match self {
@@ -770,9 +992,43 @@ mod sysex_types {
             }
         }
 
-        #[allow(dead_code)]
-        pub(crate) fn from_midi(_m: &[u8]) -> Result<(Self, usize), ParseError> {
-            Err(ParseError::NotImplemented("TimeCodeCueingMsg"))
+        /// Everything after the sub-ID #2 byte is consumed, so the returned length is always `m.len()`.
+        pub(crate) fn from_midi(m: &[u8]) -> Result<(Self, usize), ParseError> {
+            if m.len() < 3 {
+                return Err(ParseError::UnexpectedEnd);
+            }
+            let event_number = u14_from_midi(&m[1..])?;
+            let data = &m[3..];
+            let no_data = |msg: Self| {
+                if data.is_empty() {
+                    Ok(msg)
+                } else {
+                    Err(ParseError::Invalid("Extra bytes after a TimeCodeCueingMsg"))
+                }
+            };
+            let msg = match m[0] {
+                0x00 if m[1..3] == [0x04, 0x00] => no_data(Self::SystemStop)?,
+                0x01 => no_data(Self::PunchIn { event_number })?,
+                0x02 => no_data(Self::PunchOut { event_number })?,
+                0x05 | 0x07 => Self::EventStart {
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x06 | 0x08 => Self::EventStop {
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x0B | 0x0C => Self::Cue {
+                    event_number,
+                    additional_information: read_nibblized_midi(data)?,
+                },
+                0x0E => Self::EventName {
+                    event_number,
+                    name: read_nibblized_name(data)?,
+                },
+                _ => return Err(ParseError::NotImplemented("TimeCodeCueingMsg")),
+            };
+            Ok((msg, m.len()))
         }
     }
 }
@@ -855,4 +1111,298 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_read_nibblized_midi() {
+        let msgs = vec![
+            MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::Commercial {
+                    id: 0x41.into(),
+                    data: vec![0x10, 0x42, 0x12],
+                },
+            },
+            MidiMsg::ChannelVoice {
+                channel: Channel::Ch2,
+                msg: ChannelVoiceMsg::NoteOn {
+                    note: 0x55,
+                    velocity: 0x67,
+                },
+            },
+        ];
+        let mut nibbles = vec![];
+        for b in MidiMsg::messages_to_midi(&msgs) {
+            nibbles.push(b & 0x0F);
+            nibbles.push(b >> 4);
+        }
+        // F0 (sysex start) is nibblized along with everything else
+        assert_eq!(&nibbles[0..2], &[0x00, 0x0F]);
+        assert_eq!(super::read_nibblized_midi(&nibbles), Ok(msgs));
+
+        assert_eq!(super::read_nibblized_midi(&[]), Ok(vec![]));
+        assert_eq!(
+            super::read_nibblized_midi(&nibbles[1..]),
+            Err(ParseError::Invalid(
+                "Nibblized MIDI must contain an even number of nibbles"
+            ))
+        );
+        // Truncated sysex
+        assert_eq!(
+            super::read_nibblized_midi(&nibbles[0..6]),
+            Err(ParseError::NoEndOfSystemExclusiveFlag)
+        );
+    }
+
+    #[test]
+    fn test_to_sync_midi_locate() {
+        let time_code = TimeCode {
+            frames: 10,
+            seconds: 20,
+            minutes: 30,
+            hours: 4,
+            code_type: TimeCodeType::FPS25,
+        };
+        let msgs = time_code.to_sync_midi(TimeCodeSync::Locate);
+        assert_eq!(
+            msgs,
+            vec![MidiMsg::SystemExclusive {
+                msg: SystemExclusiveMsg::UniversalRealTime {
+                    device: DeviceID::AllCall,
+                    msg: UniversalRealTimeMsg::TimeCodeFull(time_code),
+                },
+            }]
+        );
+
+        let mut ctx = ReceiverContext::new();
+        MidiMsg::from_callback_bytes(&MidiMsg::messages_to_midi(&msgs), &mut ctx).unwrap();
+        assert_eq!(ctx.time_code, time_code);
+    }
+
+    #[test]
+    fn test_to_sync_midi_play() {
+        let time_code = TimeCode {
+            frames: 10,
+            seconds: 20,
+            minutes: 30,
+            hours: 4,
+            code_type: TimeCodeType::FPS25,
+        };
+        let msgs = time_code.to_sync_midi(TimeCodeSync::Play);
+        assert_eq!(msgs.len(), 8);
+        assert_eq!(
+            msgs[0],
+            MidiMsg::SystemCommon {
+                msg: SystemCommonMsg::TimeCodeQuarterFrame1(time_code)
+            }
+        );
+        assert_eq!(
+            msgs[7],
+            MidiMsg::SystemCommon {
+                msg: SystemCommonMsg::TimeCodeQuarterFrame8(time_code)
+            }
+        );
+
+        let mut ctx = ReceiverContext::new();
+        MidiMsg::from_callback_bytes(&MidiMsg::messages_to_midi(&msgs), &mut ctx).unwrap();
+        assert_eq!(ctx.time_code, time_code);
+
+        let reversed = time_code.to_sync_midi(TimeCodeSync::PlayReverse);
+        assert_eq!(
+            reversed,
+            msgs.into_iter().rev().collect::<std::vec::Vec<_>>()
+        );
+        let mut ctx = ReceiverContext::new();
+        MidiMsg::from_callback_bytes(&MidiMsg::messages_to_midi(&reversed), &mut ctx).unwrap();
+        assert_eq!(ctx.time_code, time_code);
+    }
+
+    #[test]
+    fn deserialize_time_code_cuing_setup_msg() {
+        let mut ctx = ReceiverContext::new();
+        let setup = |msg| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalNonRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalNonRealTimeMsg::TimeCodeCueingSetup(msg),
+            },
+        };
+        let time_code = HighResTimeCode {
+            fractional_frames: 45,
+            frames: 12,
+            seconds: 30,
+            minutes: 59,
+            hours: 1,
+            code_type: TimeCodeType::DF30,
+        };
+
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::TimeCodeOffset { time_code }),
+            &mut ctx,
+        );
+        test_serialization(setup(TimeCodeCueingSetupMsg::EnableEventList), &mut ctx);
+        test_serialization(setup(TimeCodeCueingSetupMsg::DisableEventList), &mut ctx);
+        test_serialization(setup(TimeCodeCueingSetupMsg::ClearEventList), &mut ctx);
+        test_serialization(setup(TimeCodeCueingSetupMsg::SystemStop), &mut ctx);
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::EventListRequest { time_code }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::PunchIn {
+                time_code,
+                event_number: 300,
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::DeleteCue {
+                time_code,
+                event_number: 2,
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::EventStart {
+                time_code,
+                event_number: 511,
+                additional_information: vec![],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::EventStart {
+                time_code,
+                event_number: 511,
+                additional_information: vec![MidiMsg::ChannelVoice {
+                    channel: Channel::Ch2,
+                    msg: ChannelVoiceMsg::NoteOn {
+                        note: 0x55,
+                        velocity: 0x67,
+                    },
+                }],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::Cue {
+                time_code,
+                event_number: 7,
+                additional_information: vec![
+                    MidiMsg::ChannelVoice {
+                        channel: Channel::Ch1,
+                        msg: ChannelVoiceMsg::ProgramChange { program: 4 },
+                    },
+                    MidiMsg::SystemRealTime {
+                        msg: SystemRealTimeMsg::Start,
+                    },
+                ],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            setup(TimeCodeCueingSetupMsg::EventName {
+                time_code,
+                event_number: 8,
+                name: bstr::BString::from("Scene 2"),
+            }),
+            &mut ctx,
+        );
+
+        // Special messages must have an event number low byte of 0
+        assert!(MidiMsg::from_midi(&[
+            0xF0, 0x7E, 0x7F, 0x04, 0x00, 0x60, 0x00, 0x00, 0x00, 0x00, 0x04, 0x01, 0xF7
+        ])
+        .is_err());
+
+        // Unknown setup types are invalid
+        assert_eq!(
+            MidiMsg::from_midi(&[
+                0xF0, 0x7E, 0x7F, 0x04, 0x0F, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF7
+            ]),
+            Err(ParseError::Invalid("Unknown time code cueing setup type"))
+        );
+    }
+
+    #[test]
+    fn deserialize_time_code_cuing_msg() {
+        let mut ctx = ReceiverContext::new();
+        let cueing = |msg| MidiMsg::SystemExclusive {
+            msg: SystemExclusiveMsg::UniversalRealTime {
+                device: DeviceID::AllCall,
+                msg: UniversalRealTimeMsg::TimeCodeCueing(msg),
+            },
+        };
+        test_serialization(cueing(TimeCodeCueingMsg::SystemStop), &mut ctx);
+        test_serialization(
+            cueing(TimeCodeCueingMsg::PunchIn { event_number: 3 }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::PunchOut { event_number: 300 }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::EventStart {
+                event_number: 511,
+                additional_information: vec![],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::EventStart {
+                event_number: 511,
+                additional_information: vec![MidiMsg::ChannelVoice {
+                    channel: Channel::Ch2,
+                    msg: ChannelVoiceMsg::NoteOn {
+                        note: 0x55,
+                        velocity: 0x67,
+                    },
+                }],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::EventStop {
+                event_number: 2,
+                additional_information: vec![MidiMsg::SystemExclusive {
+                    msg: SystemExclusiveMsg::Commercial {
+                        id: 0x41.into(),
+                        data: vec![0x10, 0x42],
+                    },
+                }],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::Cue {
+                event_number: 1,
+                additional_information: vec![
+                    MidiMsg::ChannelVoice {
+                        channel: Channel::Ch1,
+                        msg: ChannelVoiceMsg::ProgramChange { program: 4 },
+                    },
+                    MidiMsg::SystemRealTime {
+                        msg: SystemRealTimeMsg::Start,
+                    },
+                ],
+            }),
+            &mut ctx,
+        );
+        test_serialization(
+            cueing(TimeCodeCueingMsg::EventName {
+                event_number: 12,
+                name: bstr::BString::from("Scene 2\nTake 1"),
+            }),
+            &mut ctx,
+        );
+
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x05, 0x01, 0x03, 0x00, 0x01, 0xF7]),
+            Err(ParseError::Invalid("Extra bytes after a TimeCodeCueingMsg"))
+        );
+        assert_eq!(
+            MidiMsg::from_midi(&[0xF0, 0x7F, 0x7F, 0x05, 0x0E, 0x03, 0x00, 0x01, 0xF7]),
+            Err(ParseError::Invalid(
+                "Nibblized MIDI must contain an even number of nibbles"
+            ))
+        );
+    }
 }
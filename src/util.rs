@@ -154,6 +154,17 @@ mod sysex_util {
         v.push(msb);
     }
 
+    /// Read a 14-bit two's complement value, LSB first. The inverse of `push_i14`.
+    #[inline]
+    pub fn i14_from_midi(m: &[u8]) -> Result<i16, super::ParseError> {
+        let x = super::u14_from_midi(m)?;
+        if x > 8191 {
+            Ok(x as i16 - 16384)
+        } else {
+            Ok(x as i16)
+        }
+    }
+
     #[inline]
     pub fn push_u21(x: u32, v: &mut Vec<u8>) {
         let [msb, b, lsb] = to_u21(x);
@@ -162,6 +173,47 @@ mod sysex_util {
         v.push(msb);
     }
 
+    /// Read a 21-bit value, LSB first. The inverse of `push_u21`.
+    #[inline]
+    pub fn u21_from_midi(m: &[u8]) -> Result<u32, super::ParseError> {
+        if m.len() < 3 {
+            Err(super::ParseError::UnexpectedEnd)
+        } else {
+            let (lsb, b, msb) = (m[0], m[1], m[2]);
+            if lsb > 127 || b > 127 || msb > 127 {
+                Err(super::ParseError::ByteOverflow)
+            } else {
+                Ok(lsb as u32 + ((b as u32) << 7) + ((msb as u32) << 14))
+            }
+        }
+    }
+
+    /// Read a 28-bit value, LSB first. The inverse of `push_u28`.
+    #[inline]
+    pub fn u28_from_midi(m: &[u8]) -> Result<u32, super::ParseError> {
+        Ok(u7s_from_midi(m, 4)? as u32)
+    }
+
+    /// Read a 35-bit value, LSB first. The inverse of `push_u35`.
+    #[inline]
+    pub fn u35_from_midi(m: &[u8]) -> Result<u64, super::ParseError> {
+        u7s_from_midi(m, 5)
+    }
+
+    fn u7s_from_midi(m: &[u8], n: usize) -> Result<u64, super::ParseError> {
+        if m.len() < n {
+            return Err(super::ParseError::UnexpectedEnd);
+        }
+        let mut x: u64 = 0;
+        for (i, b) in m[..n].iter().enumerate() {
+            if *b > 127 {
+                return Err(super::ParseError::ByteOverflow);
+            }
+            x += (*b as u64) << (7 * i);
+        }
+        Ok(x)
+    }
+
     #[inline]
     pub fn push_u28(x: u32, v: &mut Vec<u8>) {
         let [mmsb, msb, lsb, llsb] = to_u28(x);
@@ -296,6 +348,19 @@ mod file_util {
         }
     }
 
+    /// The number of bytes that `push_vlq` uses for `x`
+    pub fn vlq_len(x: u32) -> usize {
+        if x < 0x00000080 {
+            1
+        } else if x < 0x00004000 {
+            2
+        } else if x < 0x00200000 {
+            3
+        } else {
+            4
+        }
+    }
+
     /*
     #[inline]
         pub fn u16_from_midi(m: &[u8]) -> Result<u16, ParseError> {
@@ -275,10 +275,7 @@ fn test_score_file() {
             TrackEvent {
                 delta_time: 0,
                 event: MidiMsg::Meta {
-                    msg: Meta::Unknown {
-                        meta_type: 33,
-                        data: vec![0],
-                    },
+                    msg: Meta::MidiPort(0),
                 },
                 beat_or_frame: 0.0,
             },
@@ -399,3 +396,78 @@ fn test_smf_file_zero_length_meta() {
         ParseError::Invalid("Sequence number meta event must have exactly 2 bytes")
     );
 }
+
+#[test]
+#[cfg(feature = "file")]
+fn test_smf_file_smpte_offset() {
+    let test_file = include_bytes!("./1442jsop26.mid");
+    let file = MidiFile::from_midi(test_file).unwrap();
+    assert_eq!(
+        file.smpte_offset(),
+        Some(HighResTimeCode {
+            fractional_frames: 0,
+            frames: 0,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            code_type: TimeCodeType::NDF30,
+        })
+    );
+    assert_eq!(file.start_seconds(), 0.0);
+}
+
+#[test]
+#[cfg(feature = "parallel")]
+fn test_smf_file_parallel() {
+    let test_files: [&[u8]; 8] = [
+        include_bytes!("./test1.mid"),
+        include_bytes!("./test_score1.mid"),
+        include_bytes!("./breaking-the-law.mid"),
+        include_bytes!("./echoes.mid"),
+        include_bytes!("./shine-on.mid"),
+        include_bytes!("./1442jsop26.mid"),
+        include_bytes!("./the-snow-goose.mid"),
+        // Fails to parse
+        include_bytes!("./kalinka.mid"),
+    ];
+    for test_file in test_files.iter() {
+        assert_eq!(
+            MidiFile::from_midi_parallel(test_file),
+            MidiFile::from_midi(test_file)
+        );
+    }
+
+    // Truncated files produce the same errors
+    let test_file = include_bytes!("./breaking-the-law.mid");
+    let truncated = &test_file[..test_file.len() / 2];
+    let error = MidiFile::from_midi(truncated).unwrap_err();
+    assert_eq!(MidiFile::from_midi_parallel(truncated), Err(error));
+}
+
+#[test]
+#[cfg(feature = "file")]
+fn test_smf_file_characteristics() {
+    let running_status = include_bytes!("./shine-on.mid");
+    let file = MidiFile::from_midi(running_status).unwrap();
+    assert_eq!(MidiFile::scan_running_status(running_status), Ok(true));
+    assert!(file.uses_high_res_cc());
+
+    let no_running_status = include_bytes!("./the-snow-goose.mid");
+    let file = MidiFile::from_midi(no_running_status).unwrap();
+    assert_eq!(MidiFile::scan_running_status(no_running_status), Ok(false));
+    assert!(!file.uses_high_res_cc());
+
+    // Files written by midi-msg only use running status when asked to
+    assert_eq!(MidiFile::scan_running_status(&file.to_midi()), Ok(false));
+    let compressed = file.to_midi_with_running_status();
+    assert_eq!(MidiFile::scan_running_status(&compressed), Ok(true));
+    assert!(compressed.len() < file.to_midi().len());
+    assert_eq!(
+        MidiFile::from_midi(&compressed).unwrap(),
+        MidiFile::from_midi(&file.to_midi()).unwrap()
+    );
+    assert_eq!(
+        MidiFile::scan_running_status(&no_running_status[..no_running_status.len() - 1]),
+        Err(ParseError::UnexpectedEnd)
+    );
+}